@@ -0,0 +1,33 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use scraper::{Html, Selector};
+
+/// A deeply nested document in which most elements have no `.results` ancestor.
+fn deep(depth: usize, sections: usize) -> Html {
+    let open = "<div class=\"wrapper\"><section>".repeat(depth);
+    let close = "</section></div>".repeat(depth);
+    let items = "<div class=\"item\"><a href=\"#\">x</a><span>y</span></div>".repeat(20);
+    let section = format!("{open}{items}{close}");
+    let results = format!("<div class=\"results\">{section}</div>");
+    Html::parse_document(&format!("{}{results}", section.repeat(sections)))
+}
+
+fn descendant_combinators(c: &mut Criterion) {
+    let html = deep(30, 20);
+    let selector = Selector::parse(".results .item a").unwrap();
+
+    let mut group = c.benchmark_group("descendant combinators");
+    group.sample_size(20);
+
+    group.bench_function("Html::select", |b| {
+        b.iter(|| html.select(&selector).count())
+    });
+
+    group.bench_function("ElementRef::select", |b| {
+        b.iter(|| html.root_element().select(&selector).count())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, descendant_combinators);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use scraper::{Html, Selector};
+
+/// An article with many links, followed by a short comment section.
+fn article(paragraphs: usize, comments: usize) -> Html {
+    let body = r#"<p>Text <a href="/x">link</a> and <span class="author">Ann</span> <a href="/y">more</a>.</p>"#
+        .repeat(paragraphs);
+    let comments = r#"<div class="comment"><span class="author">Bob</span><p>Nice, <a href="/z">see</a></p></div>"#
+        .repeat(comments);
+    Html::parse_document(&format!(
+        r#"<main><article><div><div>{body}</div></div></article><section id="comments">{comments}</section></main>"#
+    ))
+}
+
+fn anchored(c: &mut Criterion) {
+    let html = article(2000, 20);
+
+    let mut group = c.benchmark_group("anchored selectors");
+    group.sample_size(20);
+
+    // `[id=comments]` matches the same elements as `#comments`, but does not restrict traversal.
+    for (name, css) in [
+        ("#comments a", "#comments a"),
+        ("[id=comments] a", "[id=comments] a"),
+        ("#comments .author", "#comments .author"),
+        ("[id=comments] .author", "[id=comments] .author"),
+    ] {
+        let selector = Selector::parse(css).unwrap();
+        group.bench_function(format!("Html::select {name}"), |b| {
+            b.iter(|| html.select(&selector).count())
+        });
+        group.bench_function(format!("ElementRef::select {name}"), |b| {
+            b.iter(|| html.root_element().select(&selector).count())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, anchored);
+criterion_main!(benches);
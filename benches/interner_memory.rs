@@ -0,0 +1,93 @@
+//! Measures the heap memory retained by a corpus of similar pages, with and without an
+//! `Interner`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use scraper::html::{Html, Interner};
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// A listing page as a typical site template would render it.
+fn page(index: usize) -> String {
+    let nav = ["home", "products", "about", "contact"]
+        .iter()
+        .map(|name| {
+            format!(r#"<li class="navigation-item"><a class="navigation-link" href="/{name}/">{name}</a></li>"#)
+        })
+        .collect::<String>();
+    let items = (0..50)
+        .map(|item| {
+            format!(
+                r#"<div class="product-card product-card--compact" data-category="electronics">
+                    <a class="product-card__link" href="/products/{index}-{item}" rel="noopener noreferrer">
+                        <img class="product-card__image" loading="lazy" src="/img/{item}.jpg">
+                    </a>
+                    <span class="product-card__price">{item}.99</span>
+                </div>"#
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<!DOCTYPE html><html><body><ul class="navigation">{nav}</ul><main class="listing">{items}</main></body></html>"#
+    )
+}
+
+fn retained(parse: impl FnOnce(&[String]) -> Vec<Html>, corpus: &[String]) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let documents = parse(corpus);
+    let after = ALLOCATED.load(Ordering::Relaxed);
+    drop(documents);
+    after - before
+}
+
+fn main() {
+    let corpus = (0..200).map(page).collect::<Vec<_>>();
+
+    let plain = retained(
+        |corpus| {
+            corpus
+                .iter()
+                .map(|page| Html::parse_document(page))
+                .collect()
+        },
+        &corpus,
+    );
+
+    let mut interner = Interner::new();
+    let interned = retained(
+        |corpus| {
+            corpus
+                .iter()
+                .map(|page| Html::parse_with_interner(page, &mut interner))
+                .collect()
+        },
+        &corpus,
+    );
+
+    println!("{} documents", corpus.len());
+    println!("parse_document:      {:>10} bytes", plain);
+    println!(
+        "parse_with_interner: {:>10} bytes ({} interned values, {:.1}%)",
+        interned,
+        interner.len(),
+        100.0 * interned as f64 / plain as f64
+    );
+}
@@ -0,0 +1,32 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use scraper::{local_name, Html, Node};
+
+fn page(sections: usize) -> Html {
+    let section = r#"<div class="card"><h2>Title</h2><p>Some <b>bold</b> text</p><ul><li>a</li><li>b</li></ul></div>"#;
+    Html::parse_document(&section.repeat(sections))
+}
+
+fn count_divs(c: &mut Criterion) {
+    let html = page(5000);
+    let elements = html
+        .tree
+        .values()
+        .filter_map(Node::as_element)
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("count divs");
+
+    group.bench_function("name", |b| {
+        b.iter(|| elements.iter().filter(|e| e.name() == "div").count())
+    });
+
+    group.bench_function("name_is", |b| {
+        let div = local_name!("div");
+        b.iter(|| elements.iter().filter(|e| e.name_is(&div)).count())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, count_divs);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use scraper::{Html, Selector};
+
+/// Forms whose controls are nested deeply in layout elements.
+fn forms(forms: usize, controls: usize, depth: usize) -> Html {
+    let control = format!(
+        r#"{}<input name="x">{}"#,
+        "<span>".repeat(depth),
+        "</span>".repeat(depth)
+    );
+    let form = format!("<form>{}</form>", control.repeat(controls));
+    Html::parse_document(&form.repeat(forms))
+}
+
+fn order_index(c: &mut Criterion) {
+    let html = forms(50, 20, 100);
+    let mut indexed = html.clone();
+    indexed.build_order_index();
+
+    // Associating every control with every form, as `Html::forms` does.
+    let associate = |html: &Html| {
+        let forms = html
+            .select(&Selector::parse("form").unwrap())
+            .collect::<Vec<_>>();
+        let inputs = html
+            .select(&Selector::parse("input").unwrap())
+            .collect::<Vec<_>>();
+        forms
+            .iter()
+            .map(|form| {
+                inputs
+                    .iter()
+                    .filter(|input| input.is_child_of(form))
+                    .count()
+            })
+            .sum::<usize>()
+    };
+
+    let mut group = c.benchmark_group("form controls");
+    group.sample_size(20);
+    group.bench_function("is_child_of", |b| b.iter(|| associate(&html)));
+    group.bench_function("is_child_of with order index", |b| {
+        b.iter(|| associate(&indexed))
+    });
+    group.bench_function("Html::forms", |b| b.iter(|| html.forms().len()));
+    group.bench_function("Html::forms with order index", |b| {
+        b.iter(|| indexed.forms().len())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, order_index);
+criterion_main!(benches);
@@ -0,0 +1,96 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use scraper::{Html, Selector};
+
+/// A product listing with a header, filters and many product cards.
+fn listing(products: usize) -> Html {
+    let filters = (0..20)
+        .map(|i| {
+            format!(r#"<li class="filter"><input type="checkbox" name="f{i}"> Filter {i}</li>"#)
+        })
+        .collect::<String>();
+    let cards = (0..products)
+        .map(|i| {
+            format!(
+                r#"<div class="card" data-id="{i}">
+    <a class="title" href="/p/{i}"><h3>Product {i}</h3></a>
+    <img src="/i/{i}.jpg" alt="">
+    <span class="price">{i}.99</span><span class="rating">4.5</span>
+    <ul class="tags"><li>new</li><li>sale</li></ul>
+</div>"#
+            )
+        })
+        .collect::<String>();
+    Html::parse_document(&format!(
+        r#"<header id="top"><nav><a href="/">Home</a><a id="cart" href="/cart">Cart</a></nav></header>
+<aside id="filters"><ul>{filters}</ul></aside><main id="results">{cards}</main>
+<footer id="bottom"><p class="legal">Legal</p></footer>"#
+    ))
+}
+
+/// The kind of selectors a scraper extracts fields with, most starting from a tag or class.
+fn selectors() -> Vec<Selector> {
+    let fields = [
+        ".card .title",
+        ".card .price",
+        ".card .rating",
+        ".tags li",
+        "img[src]",
+        "h3",
+        "#cart",
+        "#top nav a",
+        ".filter input",
+        "footer .legal",
+        "a.title > h3",
+        "#results > .card",
+        "span.price, span.rating",
+        "input[type=checkbox]",
+        "ul.tags",
+        "#missing",
+    ];
+    (0..5)
+        .flat_map(|_| fields)
+        .map(|css| Selector::parse(css).unwrap())
+        .collect()
+}
+
+fn many_selectors(c: &mut Criterion) {
+    let html = listing(500);
+    let selectors = selectors();
+
+    let mut group = c.benchmark_group("many selectors");
+    group.sample_size(20);
+
+    group.bench_function("Html::select", |b| {
+        b.iter(|| {
+            selectors
+                .iter()
+                .map(|selector| html.select(selector).count())
+                .sum::<usize>()
+        })
+    });
+
+    group.bench_function("Html::select_indexed", |b| {
+        b.iter(|| {
+            selectors
+                .iter()
+                .map(|selector| html.select_indexed(selector).len())
+                .sum::<usize>()
+        })
+    });
+
+    group.bench_function("Html::select_indexed with building the index", |b| {
+        b.iter(|| {
+            let mut html = html.clone();
+            html.build_index();
+            selectors
+                .iter()
+                .map(|selector| html.select_indexed(selector).len())
+                .sum::<usize>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, many_selectors);
+criterion_main!(benches);
@@ -0,0 +1,65 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use scraper::selector::MatchContext;
+use scraper::{Html, Selector};
+
+fn table(rows: usize, columns: usize) -> Html {
+    let row = "<td>x</td>".repeat(columns);
+    let rows = format!("<tr>{row}</tr>").repeat(rows);
+    Html::parse_document(&format!("<table>{rows}</table>"))
+}
+
+fn select_nth_columns(c: &mut Criterion) {
+    let html = table(500, 50);
+    let selectors = (1..=50)
+        .map(|column| Selector::parse(&format!("tr:nth-child(odd) > td:nth-child({column})")))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let mut group = c.benchmark_group("nth-child columns");
+    group.sample_size(10);
+
+    group.bench_function("select", |b| {
+        b.iter(|| {
+            selectors
+                .iter()
+                .map(|selector| html.select(selector).count())
+                .sum::<usize>()
+        })
+    });
+
+    group.bench_function("select_with_context", |b| {
+        b.iter(|| {
+            let mut context = MatchContext::new();
+            selectors
+                .iter()
+                .map(|selector| html.select_with_context(selector, &mut context).count())
+                .sum::<usize>()
+        })
+    });
+
+    group.finish();
+}
+
+fn filter_cells(c: &mut Criterion) {
+    let html = table(100, 10);
+    let cells = html
+        .select(&Selector::parse("td").unwrap())
+        .collect::<Vec<_>>();
+    let selector = Selector::parse("tr:nth-child(odd) > td:nth-last-child(3n)").unwrap();
+
+    let mut group = c.benchmark_group("filter cells");
+    group.sample_size(10);
+
+    group.bench_function("matches", |b| {
+        b.iter(|| cells.iter().filter(|cell| selector.matches(cell)).count())
+    });
+
+    group.bench_function("filter", |b| {
+        b.iter(|| selector.filter(cells.iter().copied()).len())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, select_nth_columns, filter_cells);
+criterion_main!(benches);
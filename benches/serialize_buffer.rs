@@ -0,0 +1,34 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use scraper::{Html, Selector};
+
+fn serialize(c: &mut Criterion) {
+    let html = Html::parse_fragment(
+        &r#"<li class="item"><a href="/x">Item &amp; more</a></li>"#.repeat(100_000),
+    );
+    let selector = Selector::parse("li").unwrap();
+    let items = html.select(&selector).collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("serialize 100k elements");
+    group.sample_size(10);
+
+    group.bench_function("ElementRef::html", |b| {
+        b.iter(|| items.iter().map(|item| item.html().len()).sum::<usize>())
+    });
+    group.bench_function("ElementRef::write_html into a reused buffer", |b| {
+        let mut out = String::new();
+        b.iter(|| {
+            let mut total = 0;
+            for item in &items {
+                out.clear();
+                item.write_html(&mut out);
+                total += out.len();
+            }
+            total
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
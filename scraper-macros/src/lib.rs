@@ -0,0 +1,102 @@
+//! Procedural macros for `scraper`.
+//!
+//! These are re-exported by `scraper` when its `macros` feature is enabled and should be used
+//! from there.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use scraper_selector_parser::render as utils;
+use selectors::parser::{ParseRelative, SelectorList};
+use syn::{parse_macro_input, LitStr};
+
+#[allow(dead_code, unreachable_pub)]
+mod parser {
+    use super::extensions::{Callback, Extensions};
+
+    scraper_selector_parser::define_parser!(callback = Callback, extensions = Extensions);
+}
+
+/// Stands in for `scraper`'s custom pseudo-class registry, which `selector!` does not support.
+mod extensions {
+    pub(crate) type Callback = std::sync::Arc<dyn Fn() + Send + Sync>;
+
+    pub(crate) struct Registered {
+        pub(crate) functional: bool,
+        pub(crate) callback: Callback,
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct Extensions;
+
+    impl Extensions {
+        pub(crate) fn get(&self, _name: &str) -> Option<&Registered> {
+            None
+        }
+    }
+}
+
+/// Parses a CSS selector group at compile time.
+///
+/// The argument must be a string literal. Invalid selectors are reported as compile errors.
+/// Otherwise the macro evaluates to a `&'static scraper::Selector` which is parsed once, on first
+/// use, and cached for the rest of the process.
+///
+/// ```
+/// use scraper::{selector, Html};
+///
+/// let fragment = Html::parse_fragment(r#"<div class="item"><a href="/1">1</a></div>"#);
+/// let link = fragment.select(selector!("div.item > a")).next().unwrap();
+/// assert_eq!(link.attr("href"), Some("/1"));
+/// ```
+#[proc_macro]
+pub fn selector(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let css = literal.value();
+
+    if let Err(message) = validate(&css) {
+        return syn::Error::new(literal.span(), message)
+            .to_compile_error()
+            .into();
+    }
+
+    quote! {
+        {
+            static SELECTOR: ::std::sync::LazyLock<::scraper::Selector> =
+                ::std::sync::LazyLock::new(|| {
+                    ::scraper::Selector::parse(#literal).expect("selector validated at compile time")
+                });
+            &*SELECTOR
+        }
+    }
+    .into()
+}
+
+/// Parses `css` with the same configuration as `Selector::parse`.
+fn validate(css: &str) -> Result<(), String> {
+    let mut parser_input = cssparser::ParserInput::new(css);
+    let mut parser = cssparser::Parser::new(&mut parser_input);
+
+    match SelectorList::parse(&parser::Parser, &mut parser, ParseRelative::No) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let message = match &err.kind {
+                cssparser::ParseErrorKind::Basic(cssparser::BasicParseErrorKind::EndOfInput) => {
+                    "Unexpected EOL".to_string()
+                }
+                cssparser::ParseErrorKind::Basic(
+                    cssparser::BasicParseErrorKind::UnexpectedToken(token),
+                ) => format!("Token {:?} was not expected", utils::render_token(token)),
+                cssparser::ParseErrorKind::Custom(kind) => {
+                    utils::describe_selector_error(kind).unwrap_or_else(|| format!("{:?}", kind))
+                }
+                cssparser::ParseErrorKind::Basic(kind) => format!("{:?}", kind),
+            };
+            Err(format!(
+                "invalid selector: {} at line {}, column {}",
+                message,
+                err.location.line + 1,
+                err.location.column
+            ))
+        }
+    }
+}
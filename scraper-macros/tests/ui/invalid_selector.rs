@@ -0,0 +1,3 @@
+fn main() {
+    let _ = scraper::selector!("div.item >");
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let css = "div";
+    let _ = scraper::selector!(css);
+}
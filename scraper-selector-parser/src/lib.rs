@@ -0,0 +1,451 @@
+//! The CSS selector parser shared by `scraper` and `scraper-macros`.
+//!
+//! `scraper` parses selectors at run time and `scraper-macros` validates them at compile time,
+//! and both must accept exactly the same syntax. This crate is an implementation detail of the
+//! two: its version is locked to theirs and nothing in it is a stable API. Use `scraper` instead.
+
+#[doc(hidden)]
+pub mod render;
+
+#[doc(hidden)]
+pub use {cssparser, html5ever, selectors};
+
+/// Defines the `selectors` parser and selector implementation of `scraper` in the calling module.
+///
+/// `$extensions` is the registry of custom pseudo-classes, whose `get` method looks up a
+/// pseudo-class by name, returning whether it is `functional` and its `$callback`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! define_parser {
+    (callback = $callback:ty, extensions = $extensions:ty $(,)?) => {
+        use std::fmt;
+        use std::sync::Arc;
+
+        use $crate::cssparser::{
+            match_ignore_ascii_case, CowRcStr, ParseError, SourceLocation, ToCss, Token,
+        };
+        use $crate::html5ever::{LocalName, Namespace};
+        use $crate::selectors::parser::{self, SelectorParseErrorKind};
+
+        /// Namespace prefixes which can be used in every selector.
+        pub(crate) const BUILTIN_NAMESPACES: &[(&str, &str)] = &[
+            ("svg", "http://www.w3.org/2000/svg"),
+            ("mathml", "http://www.w3.org/1998/Math/MathML"),
+            ("xlink", "http://www.w3.org/1999/xlink"),
+            ("xml", "http://www.w3.org/XML/1998/namespace"),
+        ];
+
+        /// An implementation of `Parser` for `selectors`
+        #[derive(Clone, Copy, Debug)]
+        pub struct Parser;
+
+        impl Parser {
+            fn config(&self) -> ParserConfig<'static> {
+                ParserConfig::default()
+            }
+        }
+
+        /// Parser state beyond the defaults used by [`Parser`].
+        #[derive(Clone, Copy, Debug, Default)]
+        pub(crate) struct ParserConfig<'a> {
+            /// Additional prefix to namespace URL mappings, taking precedence over the built-in ones.
+            pub(crate) namespaces: &'a [(&'a str, &'a str)],
+            /// Custom pseudo-classes.
+            pub(crate) extensions: Option<&'a $extensions>,
+        }
+
+        impl ParserConfig<'_> {
+            fn config(&self) -> Self {
+                *self
+            }
+        }
+
+        $crate::__impl_parser!(Parser, ParserConfig<'_>);
+
+        /// Parses the arguments of `:lang()`, a list of language ranges given as identifiers or strings.
+        fn parse_lang<'i>(
+            arguments: &mut $crate::cssparser::Parser<'i, '_>,
+        ) -> Result<NonTSPseudoClass, ParseError<'i, SelectorParseErrorKind<'i>>> {
+            let ranges = arguments.parse_comma_separated(|argument| {
+                Ok(argument.expect_ident_or_string()?.as_ref().to_owned())
+            })?;
+            Ok(NonTSPseudoClass::Lang(ranges))
+        }
+
+        /// Parses the argument of `:contains()` and `:icontains()`, an identifier or a string.
+        fn parse_text<'i>(
+            arguments: &mut $crate::cssparser::Parser<'i, '_>,
+        ) -> Result<String, ParseError<'i, SelectorParseErrorKind<'i>>> {
+            let text = arguments.expect_ident_or_string()?.as_ref().to_owned();
+            arguments.expect_exhausted()?;
+            Ok(text)
+        }
+
+        /// Parses the argument of `:dir()`, which is either `ltr` or `rtl`.
+        fn parse_dir<'i>(
+            arguments: &mut $crate::cssparser::Parser<'i, '_>,
+        ) -> Result<NonTSPseudoClass, ParseError<'i, SelectorParseErrorKind<'i>>> {
+            let location = arguments.current_source_location();
+            let ident = arguments.expect_ident()?.clone();
+            let direction = match_ignore_ascii_case! { &ident,
+                "ltr" => Direction::Ltr,
+                "rtl" => Direction::Rtl,
+                _ => return Err(location.new_unexpected_token_error(Token::Ident(ident))),
+            };
+            arguments.expect_exhausted()?;
+            Ok(NonTSPseudoClass::Dir(direction))
+        }
+
+        /// A simple implementation of `SelectorImpl` with no pseudo-classes or pseudo-elements.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Simple;
+
+        impl parser::SelectorImpl for Simple {
+            type AttrValue = CssString;
+            type Identifier = CssLocalName;
+            type LocalName = CssLocalName;
+            type NamespacePrefix = CssLocalName;
+            type NamespaceUrl = Namespace;
+            type BorrowedNamespaceUrl = Namespace;
+            type BorrowedLocalName = CssLocalName;
+
+            type NonTSPseudoClass = NonTSPseudoClass;
+            type PseudoElement = PseudoElement;
+
+            // see: https://github.com/servo/servo/pull/19747#issuecomment-357106065
+            type ExtraMatchingData<'a> = ();
+        }
+
+        /// Wraps [`String`] so that it can be used with [`selectors`]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct CssString(pub String);
+
+        impl<'a> From<&'a str> for CssString {
+            fn from(val: &'a str) -> Self {
+                Self(val.to_owned())
+            }
+        }
+
+        impl AsRef<str> for CssString {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl ToCss for CssString {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+            where
+                W: fmt::Write,
+            {
+                use std::fmt::Write;
+
+                // The surrounding quotes are written by `selectors` itself.
+                write!($crate::cssparser::CssStringWriter::new(dest), "{}", &self.0)
+            }
+        }
+
+        /// Wraps [`LocalName`] so that it can be used with [`selectors`]
+        #[derive(Debug, Default, Clone, PartialEq, Eq)]
+        pub struct CssLocalName(pub LocalName);
+
+        impl<'a> From<&'a str> for CssLocalName {
+            fn from(val: &'a str) -> Self {
+                Self(val.into())
+            }
+        }
+
+        impl ToCss for CssLocalName {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+            where
+                W: fmt::Write,
+            {
+                $crate::cssparser::serialize_identifier(&self.0, dest)
+            }
+        }
+
+        /// Non Tree-Structural Pseudo-Class.
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum NonTSPseudoClass {
+            /// `:lang()`, matching elements whose language is in one of the language ranges.
+            ///
+            /// The language of an element is given by the `xml:lang` or `lang` attribute of the nearest
+            /// inclusive ancestor having one. A range matches it if it is equal to the language or a
+            /// prefix of it ending before a `-`, ignoring ASCII case, as in the basic filtering of
+            /// [RFC 4647](https://www.rfc-editor.org/rfc/rfc4647#section-3.3.1). The range `*` matches
+            /// every language.
+            Lang(Vec<String>),
+            /// `:dir()`, matching elements with the given directionality.
+            ///
+            /// The directionality of an element is given by the `dir` attribute of the nearest inclusive
+            /// ancestor having one with a valid value, and is left-to-right without one. Elements whose
+            /// directionality is `dir="auto"` would have to be determined from their text, so they match
+            /// neither direction.
+            Dir(Direction),
+            /// The non-standard `:contains()` known from jQuery, matching elements whose text contains
+            /// the given string.
+            ///
+            /// The text of an element is that of all its descendent text nodes, concatenated, so the
+            /// string may span several of them. Since this text is collected for every candidate
+            /// element, prefer restricting the candidates, e.g. with `td:contains(Total)` rather than
+            /// `:contains(Total)`.
+            Contains(String),
+            /// The case-insensitive variant of [`NonTSPseudoClass::Contains`], `:icontains()`.
+            IContains(String),
+            /// `:link`, matching `<a>` and `<area>` elements with an `href` attribute.
+            ///
+            /// As there is no browsing history, every link is unvisited.
+            Link,
+            /// `:any-link`, matching the same elements as [`NonTSPseudoClass::Link`].
+            AnyLink,
+            /// `:visited`, which never matches, as there is no browsing history.
+            Visited,
+            /// `:hover`, which never matches, as there is no user interaction.
+            Hover,
+            /// `:active`, which never matches, as there is no user interaction.
+            Active,
+            /// `:focus`, which never matches, as there is no user interaction.
+            Focus,
+            /// `:focus-visible`, which never matches, as there is no user interaction.
+            FocusVisible,
+            /// `:focus-within`, which never matches, as there is no user interaction.
+            FocusWithin,
+            /// A pseudo-class registered with [`Extensions`](super::Extensions).
+            Custom(CustomPseudoClass),
+        }
+
+        /// The argument of [`NonTSPseudoClass::Dir`].
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Direction {
+            /// Left-to-right.
+            Ltr,
+            /// Right-to-left.
+            Rtl,
+        }
+
+        /// A user-defined pseudo-class, as parsed by
+        /// [`Selector::parse_with_extensions`](super::Selector::parse_with_extensions).
+        #[derive(Clone)]
+        pub struct CustomPseudoClass {
+            name: String,
+            argument: Option<String>,
+            callback: $callback,
+        }
+
+        impl CustomPseudoClass {
+            /// Returns the name of the pseudo-class, in lowercase.
+            pub fn name(&self) -> &str {
+                &self.name
+            }
+
+            /// Returns the argument of a functional pseudo-class.
+            pub fn argument(&self) -> Option<&str> {
+                self.argument.as_deref()
+            }
+
+            pub(crate) fn callback(&self) -> &$callback {
+                &self.callback
+            }
+        }
+
+        impl PartialEq for CustomPseudoClass {
+            fn eq(&self, other: &Self) -> bool {
+                self.name == other.name
+                    && self.argument == other.argument
+                    && Arc::ptr_eq(&self.callback, &other.callback)
+            }
+        }
+
+        impl Eq for CustomPseudoClass {}
+
+        impl fmt::Debug for CustomPseudoClass {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct("CustomPseudoClass")
+                    .field("name", &self.name)
+                    .field("argument", &self.argument)
+                    .finish_non_exhaustive()
+            }
+        }
+
+        impl parser::NonTSPseudoClass for NonTSPseudoClass {
+            type Impl = Simple;
+
+            fn is_active_or_hover(&self) -> bool {
+                matches!(self, Self::Active | Self::Hover)
+            }
+
+            fn is_user_action_state(&self) -> bool {
+                matches!(
+                    self,
+                    Self::Active
+                        | Self::Hover
+                        | Self::Focus
+                        | Self::FocusVisible
+                        | Self::FocusWithin
+                )
+            }
+        }
+
+        impl ToCss for NonTSPseudoClass {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+            where
+                W: fmt::Write,
+            {
+                match self {
+                    Self::Lang(ranges) => {
+                        dest.write_str(":lang(")?;
+                        for (i, range) in ranges.iter().enumerate() {
+                            if i > 0 {
+                                dest.write_str(", ")?;
+                            }
+                            if range.contains('*') {
+                                $crate::cssparser::serialize_string(range, dest)?;
+                            } else {
+                                $crate::cssparser::serialize_identifier(range, dest)?;
+                            }
+                        }
+                        dest.write_char(')')
+                    }
+                    Self::Dir(Direction::Ltr) => dest.write_str(":dir(ltr)"),
+                    Self::Dir(Direction::Rtl) => dest.write_str(":dir(rtl)"),
+                    Self::Contains(text) => {
+                        dest.write_str(":contains(")?;
+                        $crate::cssparser::serialize_string(text, dest)?;
+                        dest.write_char(')')
+                    }
+                    Self::IContains(text) => {
+                        dest.write_str(":icontains(")?;
+                        $crate::cssparser::serialize_string(text, dest)?;
+                        dest.write_char(')')
+                    }
+                    Self::Link => dest.write_str(":link"),
+                    Self::AnyLink => dest.write_str(":any-link"),
+                    Self::Visited => dest.write_str(":visited"),
+                    Self::Hover => dest.write_str(":hover"),
+                    Self::Active => dest.write_str(":active"),
+                    Self::Focus => dest.write_str(":focus"),
+                    Self::FocusVisible => dest.write_str(":focus-visible"),
+                    Self::FocusWithin => dest.write_str(":focus-within"),
+                    Self::Custom(custom) => {
+                        dest.write_char(':')?;
+                        $crate::cssparser::serialize_identifier(&custom.name, dest)?;
+                        if let Some(argument) = &custom.argument {
+                            dest.write_char('(')?;
+                            dest.write_str(argument)?;
+                            dest.write_char(')')?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+        }
+
+        /// CSS Pseudo-Element
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum PseudoElement {}
+
+        impl parser::PseudoElement for PseudoElement {
+            type Impl = Simple;
+        }
+
+        impl ToCss for PseudoElement {
+            fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+            where
+                W: fmt::Write,
+            {
+                dest.write_str("")
+            }
+        }
+    };
+}
+
+/// Implements `selectors::parser::Parser` in terms of the type's `ParserConfig`, so that `Parser`
+/// and `ParserConfig` cannot drift apart.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_parser {
+    ($($parser:ty),*) => {$(
+        impl<'i> parser::Parser<'i> for $parser {
+            type Impl = Simple;
+            type Error = SelectorParseErrorKind<'i>;
+
+            fn parse_has(&self) -> bool {
+                true
+            }
+
+            fn parse_nth_child_of(&self) -> bool {
+                true
+            }
+
+            fn namespace_for_prefix(&self, prefix: &CssLocalName) -> Option<Namespace> {
+                self.config()
+                    .namespaces
+                    .iter()
+                    .chain(BUILTIN_NAMESPACES)
+                    .find(|(name, _)| *name == &*prefix.0)
+                    .map(|(_, url)| Namespace::from(*url))
+            }
+
+            fn parse_non_ts_pseudo_class(
+                &self,
+                location: SourceLocation,
+                name: CowRcStr<'i>,
+            ) -> Result<NonTSPseudoClass, ParseError<'i, SelectorParseErrorKind<'i>>> {
+                $crate::cssparser::match_ignore_ascii_case! { &name,
+                    "link" => return Ok(NonTSPseudoClass::Link),
+                    "any-link" => return Ok(NonTSPseudoClass::AnyLink),
+                    "visited" => return Ok(NonTSPseudoClass::Visited),
+                    "hover" => return Ok(NonTSPseudoClass::Hover),
+                    "active" => return Ok(NonTSPseudoClass::Active),
+                    "focus" => return Ok(NonTSPseudoClass::Focus),
+                    "focus-visible" => return Ok(NonTSPseudoClass::FocusVisible),
+                    "focus-within" => return Ok(NonTSPseudoClass::FocusWithin),
+                    _ => {}
+                }
+
+                match self.config().extensions.and_then(|extensions| extensions.get(&name)) {
+                    Some(registered) if !registered.functional => {
+                        Ok(NonTSPseudoClass::Custom(CustomPseudoClass {
+                            name: name.to_ascii_lowercase(),
+                            argument: None,
+                            callback: registered.callback.clone(),
+                        }))
+                    }
+                    _ => Err(location.new_custom_error(
+                        SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name),
+                    )),
+                }
+            }
+
+            fn parse_non_ts_functional_pseudo_class<'t>(
+                &self,
+                name: CowRcStr<'i>,
+                arguments: &mut $crate::cssparser::Parser<'i, 't>,
+            ) -> Result<NonTSPseudoClass, ParseError<'i, SelectorParseErrorKind<'i>>> {
+                $crate::cssparser::match_ignore_ascii_case! { &name,
+                    "lang" => return parse_lang(arguments),
+                    "dir" => return parse_dir(arguments),
+                    "contains" => return parse_text(arguments).map(NonTSPseudoClass::Contains),
+                    "icontains" => return parse_text(arguments).map(NonTSPseudoClass::IContains),
+                    _ => {}
+                }
+
+                match self.config().extensions.and_then(|extensions| extensions.get(&name)) {
+                    Some(registered) if registered.functional => {
+                        let start = arguments.position();
+                        while arguments.next().is_ok() {}
+                        let argument = arguments.slice_from(start).trim();
+
+                        Ok(NonTSPseudoClass::Custom(CustomPseudoClass {
+                            name: name.to_ascii_lowercase(),
+                            argument: Some(argument.to_owned()),
+                            callback: registered.callback.clone(),
+                        }))
+                    }
+                    _ => Err(arguments.new_custom_error(
+                        SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name),
+                    )),
+                }
+            }
+        }
+    )*};
+}
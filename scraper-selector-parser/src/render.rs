@@ -1,6 +1,10 @@
+//! Human-readable descriptions of selector parse errors.
+
 use cssparser::Token;
+use selectors::parser::SelectorParseErrorKind;
 
-pub(crate) fn render_token(token: &Token<'_>) -> String {
+/// Renders `token` as it would appear in the selector.
+pub fn render_token(token: &Token<'_>) -> String {
     // THIS TOOK FOREVER TO IMPLEMENT
 
     match token {
@@ -30,11 +34,59 @@ pub(crate) fn render_token(token: &Token<'_>) -> String {
         Token::Function(name) => format!("{}()", name.clone()),
         Token::BadString(string) => format!("<Bad String {:?}>", string.clone()),
         Token::BadUrl(url) => format!("<Bad URL {:?}>", url.clone()),
+        Token::Delim(c) => c.to_string(),
         // Single-character token
         sc_token => render_single_char_token(sc_token),
     }
 }
 
+/// Renders the token which caused `err`, if there is one.
+pub fn render_selector_error_token(err: &SelectorParseErrorKind<'_>) -> Option<String> {
+    match err {
+        SelectorParseErrorKind::NoQualifiedNameInAttributeSelector(token)
+        | SelectorParseErrorKind::UnexpectedTokenInAttributeSelector(token)
+        | SelectorParseErrorKind::PseudoElementExpectedColon(token)
+        | SelectorParseErrorKind::PseudoElementExpectedIdent(token)
+        | SelectorParseErrorKind::NoIdentForPseudo(token)
+        | SelectorParseErrorKind::ExpectedBarInAttr(token)
+        | SelectorParseErrorKind::BadValueInAttr(token)
+        | SelectorParseErrorKind::InvalidQualNameInAttr(token)
+        | SelectorParseErrorKind::ExplicitNamespaceUnexpectedToken(token)
+        | SelectorParseErrorKind::ClassNeedsIdent(token) => Some(render_token(token)),
+        SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name)
+        | SelectorParseErrorKind::UnexpectedIdent(name)
+        | SelectorParseErrorKind::ExpectedNamespace(name) => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// Describes `err` in words, if it has a more specific description than its name.
+pub fn describe_selector_error(err: &SelectorParseErrorKind<'_>) -> Option<String> {
+    Some(match err {
+        SelectorParseErrorKind::EmptySelector => "Selector is empty".to_string(),
+        SelectorParseErrorKind::DanglingCombinator => {
+            "Combinator is not followed by a selector".to_string()
+        }
+        SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name) => {
+            format!("Pseudo-class or pseudo-element {:?} is not supported", name)
+        }
+        SelectorParseErrorKind::UnexpectedIdent(name) => {
+            format!("Identifier {:?} was not expected", name)
+        }
+        SelectorParseErrorKind::ExpectedNamespace(prefix) => {
+            format!("Namespace prefix {:?} is not declared", prefix)
+        }
+        SelectorParseErrorKind::ClassNeedsIdent(token) => format!(
+            "Expected a class name after '.', got {:?} instead",
+            render_token(token)
+        ),
+        SelectorParseErrorKind::BadValueInAttr(token) => {
+            format!("Invalid attribute value {:?}", render_token(token))
+        }
+        _ => return None,
+    })
+}
+
 fn render_single_char_token(token: &Token) -> String {
     String::from(match token {
         Token::Colon => ":",
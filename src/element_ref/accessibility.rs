@@ -0,0 +1,96 @@
+//! Accessors for accessibility attributes.
+
+use super::ElementRef;
+
+impl<'a> ElementRef<'a> {
+    /// Returns the `tabindex` attribute as an integer.
+    ///
+    /// Returns `None` if the attribute is missing or, after trimming whitespace, not an
+    /// integer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment(r#"<div tabindex=" -1 "></div><p tabindex="x"></p>"#);
+    /// let mut elements = fragment.root_element().child_elements();
+    ///
+    /// assert_eq!(elements.next().unwrap().tabindex(), Some(-1));
+    /// assert_eq!(elements.next().unwrap().tabindex(), None);
+    /// ```
+    pub fn tabindex(&self) -> Option<i32> {
+        self.attr("tabindex")?
+            .trim_matches(|c: char| c.is_ascii_whitespace())
+            .parse()
+            .ok()
+    }
+
+    /// Returns the ARIA role of the element, set by its `role` attribute.
+    ///
+    /// The attribute may list several roles separated by whitespace, of which the first one
+    /// supported by a user agent applies. This returns the first one. Implicit roles, such as
+    /// `button` for a `<button>` element, are not returned.
+    pub fn role(&self) -> Option<&'a str> {
+        self.attr("role")?.split_ascii_whitespace().next()
+    }
+
+    /// Returns the value of the ARIA attribute `aria-{name}`, e.g. `aria-label` for `"label"`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment(
+    ///     r#"<button role="switch checkbox" aria-checked="true" aria-label="Dark mode">"#,
+    /// );
+    /// let button = fragment.root_element().child_elements().next().unwrap();
+    ///
+    /// assert_eq!(button.role(), Some("switch"));
+    /// assert_eq!(button.aria("label"), Some("Dark mode"));
+    /// assert_eq!(button.aria("checked"), Some("true"));
+    /// assert_eq!(button.aria("hidden"), None);
+    /// ```
+    pub fn aria(&self, name: &str) -> Option<&'a str> {
+        self.attr(&format!("aria-{name}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Html, Selector};
+
+    #[test]
+    fn accessibility_attributes() {
+        let html = Html::parse_fragment(
+            r#"
+            <a id="a" tabindex="0" role="link">a</a>
+            <div id="b" tabindex="+3" role="  ">b</div>
+            <div id="c" tabindex="99999999999" aria-hidden="">c</div>
+            <div id="d" tabindex="1.5" ARIA-LABEL="D">d</div>
+            <div id="e">e</div>
+            "#,
+        );
+        let attributes = html
+            .select(&Selector::parse("[id]").unwrap())
+            .map(|element| {
+                (
+                    element.tabindex(),
+                    element.role(),
+                    element.aria("hidden"),
+                    element.aria("label"),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            attributes,
+            [
+                (Some(0), Some("link"), None, None),
+                (Some(3), None, None, None),
+                (None, None, Some(""), None),
+                (None, None, None, Some("D")),
+                (None, None, None, None),
+            ]
+        );
+    }
+}
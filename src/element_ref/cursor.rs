@@ -0,0 +1,140 @@
+//! Imperative navigation between elements.
+
+use super::{ElementRef, WrapElements};
+use crate::Selector;
+
+/// A movable position on an element of a tree, created by [`ElementRef::cursor`].
+///
+/// The navigation methods move the cursor to another element and return `true`, or leave it
+/// where it is and return `false` if there is no such element. Only elements are visited, so
+/// text, comments and the document node are skipped. This suits code ported from DOM-walking
+/// scrapers; the iterators of [`ElementRef`] are usually more concise otherwise.
+///
+/// # Example
+///
+/// ```
+/// use scraper::{Html, Selector};
+///
+/// let html = Html::parse_fragment("<dl><dt>Name</dt><dd>Ann</dd><dt>Age</dt><dd>42</dd></dl>");
+/// let mut cursor = html.root_element().cursor();
+///
+/// assert!(cursor.select_into(&Selector::parse("dt").unwrap()));
+/// let mut fields = Vec::new();
+/// loop {
+///     let name = cursor.current().inner_html();
+///     if !cursor.to_next_sibling() {
+///         break;
+///     }
+///     fields.push((name, cursor.current().inner_html()));
+///     if !cursor.to_next_sibling() {
+///         break;
+///     }
+/// }
+/// assert_eq!(fields, [("Name".to_owned(), "Ann".to_owned()), ("Age".to_owned(), "42".to_owned())]);
+///
+/// assert!(cursor.to_parent());
+/// assert_eq!(cursor.current().value().name(), "dl");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor<'a> {
+    current: ElementRef<'a>,
+}
+
+impl<'a> Cursor<'a> {
+    /// Creates a cursor on `element`.
+    pub fn new(element: ElementRef<'a>) -> Self {
+        Self { current: element }
+    }
+
+    /// Returns the element the cursor is on.
+    pub fn current(&self) -> ElementRef<'a> {
+        self.current
+    }
+
+    /// Moves to the parent element.
+    pub fn to_parent(&mut self) -> bool {
+        self.move_to(self.current.parent().and_then(ElementRef::wrap))
+    }
+
+    /// Moves to the first child element.
+    pub fn to_first_child(&mut self) -> bool {
+        self.move_to(self.current.children().wrap_elements().next())
+    }
+
+    /// Moves to the last child element.
+    pub fn to_last_child(&mut self) -> bool {
+        self.move_to(self.current.children().rev().wrap_elements().next())
+    }
+
+    /// Moves to the next sibling element.
+    pub fn to_next_sibling(&mut self) -> bool {
+        self.move_to(self.current.next_siblings().wrap_elements().next())
+    }
+
+    /// Moves to the previous sibling element.
+    pub fn to_prev_sibling(&mut self) -> bool {
+        self.move_to(self.current.prev_siblings().wrap_elements().next())
+    }
+
+    /// Moves to the first descendant element matching `selector`, in document order.
+    pub fn select_into(&mut self, selector: &Selector) -> bool {
+        self.move_to(self.current.select(selector).next())
+    }
+
+    fn move_to(&mut self, element: Option<ElementRef<'a>>) -> bool {
+        match element {
+            Some(element) => {
+                self.current = element;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<'a> From<ElementRef<'a>> for Cursor<'a> {
+    fn from(element: ElementRef<'a>) -> Self {
+        Self::new(element)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Html, Selector};
+
+    #[test]
+    fn navigation() {
+        let html = Html::parse_fragment(
+            "<div id=a>text<p id=b><i id=c></i></p><!-- x --><p id=d></p>tail</div>",
+        );
+        let mut cursor = html.root_element().cursor();
+        let id = |cursor: &super::Cursor| cursor.current().value().id().unwrap_or("").to_owned();
+
+        assert!(!cursor.to_parent());
+        assert!(cursor.to_first_child());
+        assert_eq!(id(&cursor), "a");
+        assert!(cursor.to_first_child());
+        assert_eq!(id(&cursor), "b");
+        assert!(!cursor.to_prev_sibling());
+        assert!(cursor.to_next_sibling());
+        assert_eq!(id(&cursor), "d");
+        assert!(!cursor.to_next_sibling());
+        assert!(!cursor.to_first_child());
+        assert!(!cursor.to_last_child());
+        assert_eq!(id(&cursor), "d");
+        assert!(cursor.to_prev_sibling());
+        assert!(cursor.to_last_child());
+        assert_eq!(id(&cursor), "c");
+        assert!(cursor.to_parent());
+        assert!(cursor.to_parent());
+        assert_eq!(id(&cursor), "a");
+        assert!(cursor.to_last_child());
+        assert_eq!(id(&cursor), "d");
+
+        assert!(cursor.to_parent());
+        assert!(cursor.select_into(&Selector::parse("p > i").unwrap()));
+        assert_eq!(id(&cursor), "c");
+        assert!(!cursor.select_into(&Selector::parse("i").unwrap()));
+        assert_eq!(id(&cursor), "c");
+    }
+}
@@ -5,9 +5,10 @@ use selectors::{
 };
 
 use super::ElementRef;
-use crate::selector::{CssLocalName, CssString, NonTSPseudoClass, PseudoElement, Simple};
+use crate::selector::{
+    CssLocalName, CssString, Direction, NonTSPseudoClass, PseudoElement, Simple,
+};
 
-/// Note: will never match against non-tree-structure pseudo-classes.
 impl<'a> Element for ElementRef<'a> {
     type Impl = Simple;
 
@@ -62,8 +63,9 @@ impl<'a> Element for ElementRef<'a> {
     }
 
     fn is_html_element_in_html_document(&self) -> bool {
-        // FIXME: Is there more to this?
-        self.value().name.ns == ns!(html)
+        // Selectors lowercase type selectors and attribute names only for these elements, which
+        // keeps matching case-sensitive in XML documents.
+        self.value().name.ns == ns!(html) && !self.value().in_xml_document
     }
 
     fn has_local_name(&self, name: &CssLocalName) -> bool {
@@ -80,6 +82,8 @@ impl<'a> Element for ElementRef<'a> {
         local_name: &CssLocalName,
         operation: &AttrSelectorOperation<&CssString>,
     ) -> bool {
+        // `operation` already carries the resolved case sensitivity, including the `i` and `s`
+        // flags, so `eval_str` applies it to every operator.
         self.value().attrs.iter().any(|(key, value)| {
             !matches!(*ns, NamespaceConstraint::Specific(url) if *url != key.ns)
                 && local_name.0 == key.local
@@ -89,10 +93,28 @@ impl<'a> Element for ElementRef<'a> {
 
     fn match_non_ts_pseudo_class(
         &self,
-        _pc: &NonTSPseudoClass,
+        pc: &NonTSPseudoClass,
         _context: &mut matching::MatchingContext<'_, Self::Impl>,
     ) -> bool {
-        false
+        match pc {
+            NonTSPseudoClass::Lang(ranges) => language(self)
+                .is_some_and(|language| ranges.iter().any(|range| lang_matches(range, language))),
+            NonTSPseudoClass::Dir(direction) => directionality(self) == Some(*direction),
+            NonTSPseudoClass::Contains(text) => self.text().collect::<String>().contains(&**text),
+            NonTSPseudoClass::IContains(text) => self
+                .text()
+                .collect::<String>()
+                .to_lowercase()
+                .contains(&text.to_lowercase()),
+            NonTSPseudoClass::Link | NonTSPseudoClass::AnyLink => is_hyperlink(self),
+            NonTSPseudoClass::Visited
+            | NonTSPseudoClass::Hover
+            | NonTSPseudoClass::Active
+            | NonTSPseudoClass::Focus
+            | NonTSPseudoClass::FocusVisible
+            | NonTSPseudoClass::FocusWithin => false,
+            NonTSPseudoClass::Custom(custom) => (custom.callback())(self, custom.argument()),
+        }
     }
 
     fn match_pseudo_element(
@@ -104,7 +126,7 @@ impl<'a> Element for ElementRef<'a> {
     }
 
     fn is_link(&self) -> bool {
-        self.value().name() == "link"
+        self.value().name_is(&local_name!("link"))
     }
 
     fn is_html_slot_element(&self) -> bool {
@@ -123,19 +145,68 @@ impl<'a> Element for ElementRef<'a> {
     }
 
     fn is_empty(&self) -> bool {
-        !self
-            .children()
-            .any(|child| child.value().is_element() || child.value().is_text())
+        self.is_empty_strict()
     }
 
     fn is_root(&self) -> bool {
         self.parent()
-            .map_or(false, |parent| parent.value().is_document())
+            .is_some_and(|parent| parent.value().is_document())
     }
 
     fn apply_selector_flags(&self, _flags: matching::ElementSelectorFlags) {}
 }
 
+/// Returns whether `element` is matched by `:link`, i.e. is an `<a>` or `<area>` element with an
+/// `href` attribute.
+fn is_hyperlink(element: &ElementRef) -> bool {
+    let value = element.value();
+    value.name.ns == ns!(html)
+        && matches!(value.name.local, local_name!("a") | local_name!("area"))
+        && value.attr("href").is_some()
+}
+
+/// Returns the language of `element`, or `None` if it is unknown.
+fn language<'a>(element: &ElementRef<'a>) -> Option<&'a str> {
+    let language =
+        std::iter::successors(Some(*element), ElementRef::parent_element).find_map(|element| {
+            let element = element.value();
+            element
+                .attrs
+                .iter()
+                .find(|(name, _)| name.ns == ns!(xml) && name.local == local_name!("lang"))
+                .map(|(_, value)| &**value)
+                .or_else(|| element.attr("lang"))
+        })?;
+    (!language.is_empty()).then_some(language)
+}
+
+/// Implements basic filtering of RFC 4647.
+fn lang_matches(range: &str, language: &str) -> bool {
+    range == "*"
+        || language
+            .get(..range.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(range))
+            && matches!(language.as_bytes().get(range.len()), None | Some(b'-'))
+}
+
+/// Returns the directionality of `element`, or `None` if it depends on its text.
+fn directionality(element: &ElementRef) -> Option<Direction> {
+    std::iter::successors(Some(*element), ElementRef::parent_element)
+        .find_map(|element| {
+            let dir = element.value().attr("dir")?;
+            if dir.eq_ignore_ascii_case("ltr") {
+                Some(Some(Direction::Ltr))
+            } else if dir.eq_ignore_ascii_case("rtl") {
+                Some(Some(Direction::Rtl))
+            } else if dir.eq_ignore_ascii_case("auto") {
+                Some(None)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(Some(Direction::Ltr))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::html::Html;
@@ -179,6 +250,38 @@ mod tests {
         assert!(!element.is_link());
     }
 
+    #[test]
+    fn test_attr_case_insensitive_flag() {
+        let html = r#"<p data-kind="Foo-Bar BAZ" title="Mixed Case Title">hey there</p>"#;
+        let fragment = Html::parse_fragment(html);
+        let matches = |selector: &str| {
+            fragment
+                .select(&Selector::parse(selector).unwrap())
+                .next()
+                .is_some()
+        };
+
+        for (operator, value) in [
+            ("=", "foo-bar baz"),
+            ("^=", "FOO"),
+            ("$=", "baz"),
+            ("*=", "o-b"),
+            ("~=", "baz"),
+            ("|=", "FOO"),
+        ] {
+            let insensitive = format!("p[data-kind{}\"{}\" i]", operator, value);
+            let sensitive = format!("p[data-kind{}\"{}\" s]", operator, value);
+            let default = format!("p[data-kind{}\"{}\"]", operator, value);
+            assert!(matches(&insensitive), "{} should match", insensitive);
+            assert!(!matches(&sensitive), "{} should not match", sensitive);
+            assert!(!matches(&default), "{} should not match", default);
+        }
+
+        assert!(matches("p[title='mixed case title' i]"));
+        assert!(matches("p[title='Mixed Case Title' s]"));
+        assert!(!matches("p[title='mixed case title']"));
+    }
+
     #[test]
     fn test_has_class() {
         let html = "<p class='my_class'>hey there</p>";
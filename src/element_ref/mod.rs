@@ -1,16 +1,23 @@
 //! Element references.
 
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
-use std::iter::FusedIterator;
+use std::hash::{Hash, Hasher};
+use std::iter::{once, FilterMap, Fuse, FusedIterator};
 use std::ops::Deref;
 
 use ego_tree::iter::{Edge, Traverse};
 use ego_tree::NodeRef;
-use html5ever::serialize::{serialize, SerializeOpts, TraversalScope};
-use selectors::NthIndexCache;
+use html5ever::serialize::{HtmlSerializer, SerializeOpts, TraversalScope};
 
-use crate::node::Element;
-use crate::{Node, Selector};
+use crate::error::SelectorParseError;
+use crate::extract::{ExtractedValue, Extraction};
+use crate::html::SerializeOptions;
+use crate::node::serializable::{StringWriter, XhtmlSerializer};
+use crate::node::{Element, OwnedElement};
+use crate::selector::{AncestorFilter, MatchContext, SelectCache};
+use crate::{Html, Node, Selector};
 
 /// Wrapper around a reference to an element node.
 ///
@@ -41,49 +48,846 @@ impl<'a> ElementRef<'a> {
     }
 
     /// Returns an iterator over descendent elements matching a selector.
+    ///
+    /// Selectors of the group starting with `:scope` and a sibling combinator, e.g. `:scope + p`
+    /// as parsed by [`Selector::parse_relative`], also find the following siblings of this
+    /// element and their descendants, after the descendants of this element.
+    ///
+    /// If every selector of the group requires an ancestor with an ID, like `#comments .author`,
+    /// only the subtrees of the descendants with such an ID are traversed.
     pub fn select<'b>(&self, selector: &'b Selector) -> Select<'a, 'b> {
         let mut inner = self.traverse();
         inner.next(); // Skip Edge::Open(self).
 
-        Select {
+        let mut select = Select {
             scope: *self,
-            inner,
+            inner: Some(inner),
             selector,
-            nth_index_cache: NthIndexCache::default(),
+            nth_index_cache: SelectCache::default(),
+            ancestor_filter: AncestorFilter::new(selector, once(**self).chain(self.ancestors())),
+            anchors: None,
+            following: selector
+                .sibling_relative()
+                .map(|(selector, indices)| FollowingSiblings {
+                    selector,
+                    indices,
+                    inner: None,
+                }),
+        };
+        if select.following.is_none() {
+            if let Some(mut anchors) = self.anchors(selector) {
+                anchors.reverse();
+                select.inner = None;
+                select.anchors = Some(anchors);
+                select.enter_next_anchor();
+            }
+        }
+        select
+    }
+
+    /// Returns the descendants of this element with one of the IDs returned by
+    /// `Selector::anchor_ids`, outside of which `selector` cannot match, in document order and
+    /// without those nested in others.
+    ///
+    /// Returns `None` if the selector does not require an ancestor with an ID, or if this
+    /// element or one of its ancestors has one, so that any descendant may match.
+    fn anchors(&self, selector: &Selector) -> Option<Vec<ElementRef<'a>>> {
+        let ids = selector.anchor_ids()?;
+        let is_anchor = |node: NodeRef<'a, Node>| {
+            let id = node.value().as_element().and_then(Element::id);
+            id.is_some_and(|id| ids.iter().any(|anchor| **anchor == *id))
+        };
+        if once(**self).chain(self.ancestors()).any(is_anchor) {
+            return None;
+        }
+
+        let mut anchors = Vec::<ElementRef<'a>>::new();
+        for node in self.descendants().skip(1) {
+            if !is_anchor(node) {
+                continue;
+            }
+            let nested = anchors
+                .last()
+                .is_some_and(|last| node.ancestors().any(|ancestor| ancestor == **last));
+            if !nested {
+                anchors.extend(ElementRef::wrap(node));
+            }
+        }
+        Some(anchors)
+    }
+
+    /// Returns an iterator over this element and its descendent elements matching a selector.
+    ///
+    /// Unlike [`ElementRef::select`], which like `querySelectorAll` only finds descendants,
+    /// this also tests the element itself first. `:scope` still refers to this element.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment(r#"<div class="item"><div class="item"></div></div>"#);
+    /// let item = Selector::parse(".item").unwrap();
+    /// let outer = fragment.select(&item).next().unwrap();
+    ///
+    /// assert_eq!(outer.select(&item).count(), 1);
+    /// assert_eq!(outer.select_inclusive(&item).next(), Some(outer));
+    /// assert_eq!(outer.select_inclusive(&item).count(), 2);
+    /// ```
+    pub fn select_inclusive<'b>(&self, selector: &'b Selector) -> Select<'a, 'b> {
+        let select = self.select(selector);
+        if select.anchors.is_some() {
+            // The element lacks an ancestor with an ID the selector requires, so it cannot match.
+            return select;
+        }
+        Select {
+            inner: Some(self.traverse()),
+            ancestor_filter: AncestorFilter::new(selector, self.ancestors()),
+            ..select
+        }
+    }
+
+    /// Returns an iterator over descendent elements matching a selector group, together with
+    /// the index of the selector in the group which matched each.
+    ///
+    /// If several selectors of the group match an element, the index of the first one is
+    /// returned. The elements are the same as those of [`ElementRef::select`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let fragment = Html::parse_fragment("<h1>Title</h1><h3>Detail</h3><h2>Section</h2>");
+    /// let headings = Selector::parse("h1, h2, h3").unwrap();
+    ///
+    /// let levels = fragment
+    ///     .root_element()
+    ///     .select_with_branch(&headings)
+    ///     .map(|(heading, branch)| (heading.inner_html(), branch + 1))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     levels,
+    ///     [("Title".to_owned(), 1), ("Detail".to_owned(), 3), ("Section".to_owned(), 2)]
+    /// );
+    /// ```
+    pub fn select_with_branch<'b>(&self, selector: &'b Selector) -> SelectWithBranch<'a, 'b> {
+        SelectWithBranch {
+            inner: self.select(selector),
         }
     }
 
-    fn serialize(&self, traversal_scope: TraversalScope) -> String {
+    /// Returns an iterator over descendent elements matching a selector, reusing the caches of
+    /// `context`.
+    ///
+    /// This yields the same elements as [`ElementRef::select`], but is faster when running many
+    /// selectors using structural pseudo-classes like `:nth-child` against the same document.
+    pub fn select_with_context<'b>(
+        &self,
+        selector: &'a Selector,
+        context: &'b mut MatchContext<'a>,
+    ) -> Select<'a, 'b> {
+        Select {
+            nth_index_cache: SelectCache::shared(context),
+            ..self.select(selector)
+        }
+    }
+
+    /// Returns an iterator over descendent elements matching a selector which are not hidden,
+    /// as determined by [`ElementRef::is_hidden`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment(
+    ///     r#"<ul><li>a</li><li hidden>b</li><li style="display: none">c</li></ul>
+    ///     <div style="visibility:hidden"><ul><li>d</li></ul></div>"#,
+    /// );
+    /// let li = Selector::parse("li").unwrap();
+    ///
+    /// let visible = fragment.root_element().select_visible(&li).map(|li| li.inner_html());
+    /// assert_eq!(visible.collect::<Vec<_>>(), ["a"]);
+    /// ```
+    pub fn select_visible<'b>(
+        &self,
+        selector: &'b Selector,
+    ) -> impl Iterator<Item = ElementRef<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        self.select(selector).filter(|element| !element.is_hidden())
+    }
+
+    /// Returns true if the markup of this element or one of its ancestors hides it.
+    ///
+    /// An element is hidden if it or an ancestor
+    ///
+    /// - has a `hidden` attribute,
+    /// - is an `<input type="hidden">` or a `<template>`,
+    /// - has `display: none` in its inline `style` attribute, or
+    /// - has `visibility: hidden` or `visibility: collapse` in its inline `style` attribute,
+    ///   unless a closer ancestor or the element itself sets `visibility: visible`.
+    ///
+    /// Only inline styles are considered: rules from `<style>` elements and external
+    /// stylesheets, which may hide elements too, are ignored.
+    pub fn is_hidden(&self) -> bool {
+        let mut visibility_set = false;
+
+        // Template contents are children of a fragment inside the template element.
+        for element in once(*self).chain(self.ancestors().filter_map(ElementRef::wrap)) {
+            let value = element.value();
+            if value.has_bool_attr("hidden")
+                || value.name_is(&local_name!("template"))
+                || (value.name_is(&local_name!("input"))
+                    && value
+                        .attr("type")
+                        .is_some_and(|kind| kind.eq_ignore_ascii_case("hidden")))
+            {
+                return true;
+            }
+
+            let Some(style) = value.attr("style") else {
+                continue;
+            };
+            if style::property(style, "display").is_some_and(|d| d.eq_ignore_ascii_case("none")) {
+                return true;
+            }
+            if !visibility_set {
+                if let Some(visibility) = style::property(style, "visibility") {
+                    if visibility.eq_ignore_ascii_case("hidden")
+                        || visibility.eq_ignore_ascii_case("collapse")
+                    {
+                        return true;
+                    }
+                    visibility_set = true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Extracts values from the descendent elements matching a Scrapy-style selector list.
+    ///
+    /// See [`Html::extract`](crate::Html::extract) for the supported syntax. A bare
+    /// pseudo-element, e.g. `::text`, applies to this element, and `:scope` matches this
+    /// element like in [`ElementRef::select`].
+    pub fn extract(&self, css: &str) -> Result<Vec<ExtractedValue<'a>>, SelectorParseError> {
+        Ok(Extraction::parse(css)?.run(*self, false, Some(*self)))
+    }
+
+    fn serialize(&self, traversal_scope: TraversalScope, options: SerializeOptions) -> String {
+        let mut out = String::new();
+        self.serialize_into(&mut out, traversal_scope, options);
+        out
+    }
+
+    fn serialize_into(
+        &self,
+        out: &mut String,
+        traversal_scope: TraversalScope,
+        options: SerializeOptions,
+    ) {
         let opts = SerializeOpts {
             scripting_enabled: false, // It's not clear what this does.
+            traversal_scope: traversal_scope.clone(),
+            create_missing_parent: false,
+        };
+        let mut serializer = HtmlSerializer::new(StringWriter(out), opts);
+        crate::node::serializable::serialize_with(
+            self.node,
+            &mut serializer,
             traversal_scope,
+            options,
+        )
+        .unwrap();
+    }
+
+    /// Returns the 1-based line and column at which this element's start tag begins in the
+    /// parsed source.
+    ///
+    /// Columns count characters, not bytes. Locations are only recorded by
+    /// [`Html::parse_document_with_source_locations`] and
+    /// [`Html::parse_fragment_with_source_locations`]; for elements parsed otherwise, or implied
+    /// by the parser without a start tag (like the `<tbody>` of a table without one), this returns
+    /// `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let html = Html::parse_fragment_with_source_locations("<div>\n  <p>x</p>\n</div>");
+    /// let p = html.select(&Selector::parse("p").unwrap()).next().unwrap();
+    ///
+    /// assert_eq!(p.source_location(), Some((2, 3)));
+    /// ```
+    ///
+    /// [`Html::parse_document_with_source_locations`]: crate::Html::parse_document_with_source_locations
+    /// [`Html::parse_fragment_with_source_locations`]: crate::Html::parse_fragment_with_source_locations
+    pub fn source_location(&self) -> Option<(usize, usize)> {
+        self.value().source_location()
+    }
+
+    /// Returns the HTML of this element.
+    pub fn html(&self) -> String {
+        self.html_with(SerializeOptions::default())
+    }
+
+    /// Appends the HTML of this element to `out`, like [`ElementRef::html`].
+    ///
+    /// Reusing `out` for many elements avoids allocating a new string for each of them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment("<li>a</li><li>b</li>");
+    ///
+    /// let mut out = String::new();
+    /// for li in fragment.select(&Selector::parse("li").unwrap()) {
+    ///     out.clear();
+    ///     li.write_html(&mut out);
+    ///     assert_eq!(out, li.html());
+    /// }
+    /// ```
+    pub fn write_html(&self, out: &mut String) {
+        self.serialize_into(
+            out,
+            TraversalScope::IncludeNode,
+            SerializeOptions::default(),
+        )
+    }
+
+    /// Returns the HTML of this element like [`ElementRef::html`], with the given options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// use scraper::html::SerializeOptions;
+    ///
+    /// let fragment = Html::parse_fragment("<p>a<!--[if IE]>b<![endif]-->c</p>");
+    /// let p = fragment.select(&Selector::parse("p").unwrap()).next().unwrap();
+    /// let options = SerializeOptions { include_comments: false };
+    ///
+    /// assert_eq!(p.html_with(options), "<p>ac</p>");
+    /// assert_eq!(p.inner_html_with(options), "ac");
+    /// ```
+    pub fn html_with(&self, options: SerializeOptions) -> String {
+        self.serialize(TraversalScope::IncludeNode, options)
+    }
+
+    /// Returns the HTML of this element, serialized as XHTML.
+    ///
+    /// Unlike [`ElementRef::html`], the result is well-formed XML as long as the element's tag
+    /// and attribute names are valid XML names: void elements are self-closing, e.g. `<br />`,
+    /// and the contents of raw text elements like `<script>` are escaped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment(r#"<p>a<br>b<img src="x.png"></p>"#);
+    /// let p = fragment.select(&Selector::parse("p").unwrap()).next().unwrap();
+    ///
+    /// assert_eq!(p.html(), r#"<p>a<br>b<img src="x.png"></p>"#);
+    /// assert_eq!(p.html_xhtml(), r#"<p>a<br />b<img src="x.png" /></p>"#);
+    /// ```
+    pub fn html_xhtml(&self) -> String {
+        let mut buf = Vec::new();
+        let mut serializer = XhtmlSerializer::new(&mut buf);
+        crate::node::serializable::serialize(
+            self.node,
+            &mut serializer,
+            TraversalScope::IncludeNode,
+        )
+        .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// Returns the inner HTML of this element.
+    pub fn inner_html(&self) -> String {
+        self.inner_html_with(SerializeOptions::default())
+    }
+
+    /// Appends the inner HTML of this element to `out`, like [`ElementRef::inner_html`].
+    pub fn write_inner_html(&self, out: &mut String) {
+        self.serialize_into(
+            out,
+            TraversalScope::ChildrenOnly(None),
+            SerializeOptions::default(),
+        )
+    }
+
+    /// Returns the inner HTML of this element like [`ElementRef::inner_html`], with the given
+    /// options.
+    pub fn inner_html_with(&self, options: SerializeOptions) -> String {
+        self.serialize(TraversalScope::ChildrenOnly(None), options)
+    }
+
+    /// Returns the inner HTML of this element without leading and trailing whitespace-only text
+    /// nodes.
+    ///
+    /// Only the boundary text nodes consisting entirely of ASCII whitespace are dropped;
+    /// whitespace inside kept nodes, including partially blank text nodes, is preserved. Like in
+    /// [`ElementRef::is_empty`], a non-breaking space is content.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment("<div>\n  <p>x</p>\n</div>");
+    /// let div = fragment.select(&Selector::parse("div").unwrap()).next().unwrap();
+    ///
+    /// assert_eq!(div.trimmed_inner_html(), "<p>x</p>");
+    /// ```
+    pub fn trimmed_inner_html(&self) -> String {
+        let is_blank = |node: &NodeRef<'a, Node>| {
+            node.value()
+                .as_text()
+                .is_some_and(|text| text.is_whitespace())
+        };
+        let children = self.children().collect::<Vec<_>>();
+        let start = children
+            .iter()
+            .position(|child| !is_blank(child))
+            .unwrap_or(children.len());
+        let end = children
+            .iter()
+            .rposition(|child| !is_blank(child))
+            .map_or(start, |last| last + 1);
+
+        let opts = SerializeOpts {
+            scripting_enabled: false, // It's not clear what this does.
+            traversal_scope: TraversalScope::ChildrenOnly(None),
             create_missing_parent: false,
         };
         let mut buf = Vec::new();
-        serialize(&mut buf, self, opts).unwrap();
+        let mut serializer = HtmlSerializer::new(&mut buf, opts);
+        for child in &children[start..end] {
+            crate::node::serializable::serialize(
+                *child,
+                &mut serializer,
+                TraversalScope::IncludeNode,
+            )
+            .unwrap();
+        }
         String::from_utf8(buf).unwrap()
     }
 
-    /// Returns the HTML of this element.
-    pub fn html(&self) -> String {
-        self.serialize(TraversalScope::IncludeNode)
-    }
+    /// Returns the value of an attribute.
+    pub fn attr(&self, attr: &str) -> Option<&'a str> {
+        self.value().attr(attr)
+    }
+
+    /// Returns true if the boolean attribute `name`, like `disabled` or `checked`, is set.
+    ///
+    /// See [`Element::has_bool_attr`].
+    pub fn has_bool_attr(&self, name: &str) -> bool {
+        self.value().has_bool_attr(name)
+    }
+
+    /// Returns the value of an attribute in the namespace with the URL `ns`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let fragment = Html::parse_fragment(r##"<svg><use xlink:href="#icon"></use></svg>"##);
+    /// let icon = fragment.select(&Selector::parse("[xlink|href]").unwrap()).next().unwrap();
+    ///
+    /// assert_eq!(icon.attr_ns("http://www.w3.org/1999/xlink", "href"), Some("#icon"));
+    /// assert_eq!(icon.attr("href"), None);
+    /// ```
+    pub fn attr_ns(&self, ns: &str, local: &str) -> Option<&'a str> {
+        self.value().attr_ns(ns, local)
+    }
+
+    /// Returns the value of the first attribute, in source order, whose local name is
+    /// `local_name`, whatever its namespace or prefix.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let fragment = Html::parse_fragment(r##"<svg><use xlink:href="#a" href="#b"></use></svg>"##);
+    /// let icon = fragment.select(&Selector::parse("use").unwrap()).next().unwrap();
+    ///
+    /// assert_eq!(icon.attr("href"), Some("#b"));
+    /// assert_eq!(icon.attr_local("href"), Some("#a"));
+    /// assert_eq!(icon.attrs_local("href").collect::<Vec<_>>(), ["#a", "#b"]);
+    /// ```
+    pub fn attr_local(&self, local_name: &str) -> Option<&'a str> {
+        self.value().attr_local(local_name)
+    }
+
+    /// Returns the values of all attributes whose local name is `local_name`, whatever their
+    /// namespace or prefix, in source order.
+    pub fn attrs_local(&self, local_name: &str) -> impl Iterator<Item = &'a str> {
+        self.value().attrs_local(local_name)
+    }
+
+    /// Returns the attributes which differ between this element and `other`, as triples of the
+    /// name, the value on this element and the value on `other`, sorted by name.
+    ///
+    /// An attribute only present on one of the elements has `None` as its value on the other.
+    /// Attribute order does not matter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let old = Html::parse_fragment(r#"<a href="/a" class="x" title="t">"#);
+    /// let new = Html::parse_fragment(r#"<a title="t" href="/b" rel="next">"#);
+    /// let old_a = old.root_element().child_elements().next().unwrap();
+    /// let new_a = new.root_element().child_elements().next().unwrap();
+    ///
+    /// assert_eq!(
+    ///     old_a.attr_diff(&new_a),
+    ///     [
+    ///         ("class", Some("x"), None),
+    ///         ("href", Some("/a"), Some("/b")),
+    ///         ("rel", None, Some("next")),
+    ///     ],
+    /// );
+    /// ```
+    pub fn attr_diff(
+        &self,
+        other: &ElementRef<'a>,
+    ) -> Vec<(&'a str, Option<&'a str>, Option<&'a str>)> {
+        let mut diff = self
+            .value()
+            .attrs()
+            .filter_map(|(name, value)| {
+                let other_value = other.attr(name);
+                (other_value != Some(value)).then_some((name, Some(value), other_value))
+            })
+            .chain(
+                other
+                    .value()
+                    .attrs()
+                    .filter(|(name, _)| self.attr(name).is_none())
+                    .map(|(name, value)| (name, None, Some(value))),
+            )
+            .collect::<Vec<_>>();
+        diff.sort_unstable_by_key(|(name, _, _)| *name);
+        diff
+    }
+
+    /// Returns the nearest inclusive ancestor having the attribute `name`, together with the
+    /// attribute's value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment(r#"<div data-product-id="42"><p><b>Buy</b></p></div>"#);
+    /// let b = html.select(&Selector::parse("b").unwrap()).next().unwrap();
+    ///
+    /// let (product, id) = b.closest_with_attr("data-product-id").unwrap();
+    /// assert_eq!(product.value().name(), "div");
+    /// assert_eq!(id, "42");
+    /// ```
+    pub fn closest_with_attr(&self, name: &str) -> Option<(ElementRef<'a>, &'a str)> {
+        std::iter::successors(Some(*self), |element| {
+            element.parent().and_then(ElementRef::wrap)
+        })
+        .find_map(|element| Some((element, element.attr(name)?)))
+    }
+
+    /// Returns an iterator over the tokens of a list-valued attribute such as `rel` or `accept`.
+    ///
+    /// The attribute value is split on `sep`, each token is trimmed of ASCII whitespace and empty
+    /// tokens are skipped. If `sep` is ASCII whitespace, the value is split on any ASCII
+    /// whitespace, as HTML does for space-separated tokens. Yields nothing if the attribute is
+    /// missing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment(r#"<a rel="nofollow  noopener" data-ids="1, 2,,3">"#);
+    /// let a = fragment.root_element().child_elements().next().unwrap();
+    ///
+    /// assert_eq!(a.attr_tokens("rel", ' ').collect::<Vec<_>>(), ["nofollow", "noopener"]);
+    /// assert_eq!(a.attr_tokens("data-ids", ',').collect::<Vec<_>>(), ["1", "2", "3"]);
+    /// ```
+    pub fn attr_tokens(&self, attr: &str, sep: char) -> impl Iterator<Item = &'a str> {
+        self.attr(attr)
+            .unwrap_or("")
+            .split(move |c: char| {
+                if sep.is_ascii_whitespace() {
+                    c.is_ascii_whitespace()
+                } else {
+                    c == sep
+                }
+            })
+            .map(|token| token.trim_matches(|c: char| c.is_ascii_whitespace()))
+            .filter(|token| !token.is_empty())
+    }
+
+    /// Parses the inline `style` attribute into a map from property names to values.
+    ///
+    /// Names are trimmed but otherwise kept as written, and a trailing `!important` is removed
+    /// from values. When a property is declared several times, the last value is kept.
+    /// Declarations without a colon, a name or a value are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment(
+    ///     r#"<div style="color: red; background-image: url('a;b.png'); color: blue; oops">"#,
+    /// );
+    /// let div = fragment.root_element().child_elements().next().unwrap();
+    ///
+    /// let style = div.style();
+    /// assert_eq!(style.len(), 2);
+    /// assert_eq!(style["color"], "blue");
+    /// assert_eq!(style["background-image"], "url('a;b.png')");
+    /// ```
+    pub fn style(&self) -> HashMap<&'a str, &'a str> {
+        self.attr("style")
+            .map(|style| style::declarations(style).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns an iterator over descendent text nodes.
+    pub fn text(&self) -> Text<'a> {
+        Text {
+            inner: self.traverse(),
+        }
+    }
+
+    /// Returns an iterator over descendent text nodes, each with its nearest element ancestor.
+    ///
+    /// This yields the same text as [`ElementRef::text`], attributing it to the element
+    /// containing it, e.g. to tell bold text apart from the text around it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment("<p>Plain <b>bold</b> plain</p>");
+    /// let p = fragment.root_element().child_elements().next().unwrap();
+    ///
+    /// let texts = p
+    ///     .text_nodes_with_parents()
+    ///     .map(|(text, parent)| (text, parent.value().name()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(texts, [("Plain ", "p"), ("bold", "b"), (" plain", "p")]);
+    /// ```
+    pub fn text_nodes_with_parents(&self) -> TextWithParents<'a> {
+        TextWithParents {
+            inner: self.traverse(),
+            open: Vec::new(),
+        }
+    }
+
+    /// Returns the descendent text, cut off after `max_chars` characters.
+    ///
+    /// Text nodes after the limit is reached are not visited, so this is cheap even for large
+    /// elements. If text was cut off and `ellipsis` is set, `…` is appended, in addition to the
+    /// `max_chars` characters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment("<p>Hello, <i>world!</i></p>");
+    /// let p = fragment.root_element().child_elements().next().unwrap();
+    ///
+    /// assert_eq!(p.text_truncated(8, false), "Hello, w");
+    /// assert_eq!(p.text_truncated(8, true), "Hello, w…");
+    /// assert_eq!(p.text_truncated(13, true), "Hello, world!");
+    /// ```
+    pub fn text_truncated(&self, max_chars: usize, ellipsis: bool) -> String {
+        let mut text = String::new();
+        let mut remaining = max_chars;
+
+        for t in self.text().filter(|t| !t.is_empty()) {
+            match t.char_indices().nth(remaining) {
+                Some((end, _)) => {
+                    text.push_str(&t[..end]);
+                    if ellipsis {
+                        text.push('…');
+                    }
+                    return text;
+                }
+                None => {
+                    text.push_str(t);
+                    remaining -= t.chars().count();
+                }
+            }
+        }
+
+        text
+    }
+
+    /// Returns the descendent text with leading and trailing whitespace removed and every other
+    /// run of whitespace replaced by a single space.
+    pub(crate) fn normalized_text(&self) -> String {
+        let text = self.text().collect::<String>();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Copies the name and attributes of this element, and its text if `include_text` is set,
+    /// into a value which does not borrow the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment(r#"<a href="/next">Next <b>page</b></a>"#);
+    /// let a = fragment.root_element().child_elements().next().unwrap().to_owned_value(true);
+    ///
+    /// assert_eq!(a.name, "a");
+    /// assert_eq!(a.attrs["href"], "/next");
+    /// assert_eq!(a.text.as_deref(), Some("Next page"));
+    /// ```
+    pub fn to_owned_value(&self, include_text: bool) -> OwnedElement {
+        let element = self.value();
+        OwnedElement {
+            name: element.name().to_owned(),
+            attrs: element
+                .attrs()
+                .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+            text: include_text.then(|| self.text().collect()),
+        }
+    }
+
+    /// Copies this element and its descendants into a new fragment.
+    ///
+    /// The copy is the root element of the returned fragment, and is matched by `:scope` when
+    /// selecting from it. Unlike `ElementRef`, the fragment does not borrow the original
+    /// document, so it can be kept around or, with the `atomic` feature, sent to another thread.
+    pub fn to_fragment(&self) -> Html {
+        let mut html = Html::new_fragment();
+        let root = html.tree.root().id();
+        html.copy_subtree(**self, Some(root));
+        html
+    }
+
+    /// Returns the text of the sibling node immediately preceding this element, if it is a text
+    /// node.
+    ///
+    /// This finds labels written as bare text next to an element, like `Name: <input>`. The text
+    /// is returned as is, including any whitespace.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment(r#"<label>Name: <input name="n"> (required)</label><br><input name="e">"#);
+    /// let inputs = fragment.select(&Selector::parse("input").unwrap()).collect::<Vec<_>>();
+    ///
+    /// assert_eq!(inputs[0].preceding_text(), Some("Name: "));
+    /// assert_eq!(inputs[0].following_text(), Some(" (required)"));
+    /// assert_eq!(inputs[1].preceding_text(), None);
+    /// ```
+    pub fn preceding_text(&self) -> Option<&'a str> {
+        self.prev_sibling()?
+            .value()
+            .as_text()
+            .map(|text| text.as_str())
+    }
+
+    /// Returns the text of the sibling node immediately following this element, if it is a text
+    /// node.
+    ///
+    /// See [`preceding_text`](Self::preceding_text).
+    pub fn following_text(&self) -> Option<&'a str> {
+        self.next_sibling()?
+            .value()
+            .as_text()
+            .map(|text| text.as_str())
+    }
+
+    /// Returns the text between the first descendent element matching `start` and the next
+    /// element matching `end`, in document order.
+    ///
+    /// The text of the `start` element itself is not included, but that of any elements between
+    /// the markers is. Returns an empty string if either marker is not found.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let fragment = Html::parse_fragment("<h2>Intro</h2>Some <em>text</em>.<h2>Details</h2>More.");
+    /// let h2 = Selector::parse("h2").unwrap();
+    ///
+    /// assert_eq!(fragment.root_element().text_between(&h2, &h2), "Some text.");
+    /// ```
+    pub fn text_between(&self, start: &Selector, end: &Selector) -> String {
+        let mut text = String::new();
+        let mut started = false;
+
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) if node != **self => match node.value() {
+                    Node::Element(_) if started && end.matches(&ElementRef::new(node)) => {
+                        return text;
+                    }
+                    Node::Text(t) if started => text.push_str(t),
+                    _ => {}
+                },
+                Edge::Close(node) if !started && node != **self => {
+                    started = ElementRef::wrap(node).is_some_and(|element| start.matches(&element));
+                }
+                _ => {}
+            }
+        }
 
-    /// Returns the inner HTML of this element.
-    pub fn inner_html(&self) -> String {
-        self.serialize(TraversalScope::ChildrenOnly(None))
+        String::new()
     }
 
-    /// Returns the value of an attribute.
-    pub fn attr(&self, attr: &str) -> Option<&'a str> {
-        self.value().attr(attr)
+    /// Returns true if this element has no child elements and contains only whitespace text.
+    ///
+    /// Unlike the `:empty` pseudo-class, this treats an element containing only whitespace, like
+    /// a table cell indented across lines, as empty. Whitespace is ASCII whitespace as in
+    /// [`Text::is_whitespace`](crate::node::Text::is_whitespace), so a cell holding a `&nbsp;`
+    /// is not empty. Comments are ignored. See
+    /// [`is_empty_strict`](Self::is_empty_strict) for the exact `:empty` semantics.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment("<table><tr><td> \n </td><td><!-- x --></td><td><br></td><td>-</td><td>&nbsp;</td></tr></table>");
+    /// let cells = fragment.select(&Selector::parse("td").unwrap()).map(|td| td.is_empty()).collect::<Vec<_>>();
+    /// assert_eq!(cells, [true, true, false, false, false]);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.children().all(|child| match child.value() {
+            Node::Element(_) => false,
+            Node::Text(text) => text.is_whitespace(),
+            _ => true,
+        })
     }
 
-    /// Returns an iterator over descendent text nodes.
-    pub fn text(&self) -> Text<'a> {
-        Text {
-            inner: self.traverse(),
-        }
+    /// Returns true if this element has no child elements and no text at all, exactly when the
+    /// `:empty` pseudo-class matches it.
+    ///
+    /// Whitespace counts as text, so `<td> </td>` is not empty, while comments are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let fragment = Html::parse_fragment("<table><tr><td> \n </td><td><!-- x --></td><td></td></tr></table>");
+    /// let cells = fragment.select(&Selector::parse("td").unwrap()).map(|td| td.is_empty_strict()).collect::<Vec<_>>();
+    /// assert_eq!(cells, [false, true, true]);
+    /// ```
+    pub fn is_empty_strict(&self) -> bool {
+        !self
+            .children()
+            .any(|child| child.value().is_element() || child.value().is_text())
     }
 
     /// Iterate over all child nodes which are elements
@@ -98,7 +902,12 @@ impl<'a> ElementRef<'a> {
     /// assert_eq!(children, ["span", "a"]);
     /// ```
     pub fn child_elements(&self) -> impl Iterator<Item = ElementRef<'a>> {
-        self.children().filter_map(ElementRef::wrap)
+        self.children().wrap_elements()
+    }
+
+    /// Returns a cursor on this element, for navigating the tree by moving between elements.
+    pub fn cursor(&self) -> Cursor<'a> {
+        Cursor::new(*self)
     }
 
     /// Iterate over all descendent nodes which are elements
@@ -113,10 +922,148 @@ impl<'a> ElementRef<'a> {
     /// assert_eq!(descendants, ["html", "span", "b", "a", "i"]);
     /// ```
     pub fn descendent_elements(&self) -> impl Iterator<Item = ElementRef<'a>> {
-        self.descendants().filter_map(ElementRef::wrap)
+        self.descendants().wrap_elements()
+    }
+
+    /// Returns an iterator over descendent elements having a class that starts with `prefix`.
+    ///
+    /// Unlike the `[class^=...]` attribute selector, this looks at each class separately, which
+    /// suits sites with hashed class names such as `Button_abc123`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::Html;
+    /// let fragment = Html::parse_fragment(r#"<div class="x Button_abc123">a</div><div class="Buttons">b</div>"#);
+    ///
+    /// let texts = fragment.root_element().select_class_prefix("Button_").map(|e| e.inner_html()).collect::<Vec<_>>();
+    /// assert_eq!(texts, ["a"]);
+    /// ```
+    pub fn select_class_prefix<'b>(
+        &self,
+        prefix: &'b str,
+    ) -> impl Iterator<Item = ElementRef<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        self.select_class_matching(move |class| class.starts_with(prefix))
+    }
+
+    /// Returns an iterator over descendent elements having a class that ends with `suffix`.
+    ///
+    /// Like [`select_class_prefix`](Self::select_class_prefix), each class is checked separately.
+    pub fn select_class_suffix<'b>(
+        &self,
+        suffix: &'b str,
+    ) -> impl Iterator<Item = ElementRef<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        self.select_class_matching(move |class| class.ends_with(suffix))
+    }
+
+    fn select_class_matching<'b, F>(
+        &self,
+        mut predicate: F,
+    ) -> impl Iterator<Item = ElementRef<'a>> + 'b
+    where
+        'a: 'b,
+        F: FnMut(&str) -> bool + 'b,
+    {
+        self.descendent_elements()
+            .skip(1) // Skip self.
+            .filter(move |element| element.value().classes().any(&mut predicate))
+    }
+
+    /// Compares the positions of this element and `other` in document order, i.e. the order of
+    /// their start tags.
+    ///
+    /// An element precedes its descendants. Returns `None` if the elements belong to different
+    /// trees. Like [`ElementRef::is_child_of`], this takes constant time once
+    /// [`Html::build_order_index`] has been called.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::cmp::Ordering;
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment("<div><p>1</p></div><p>2</p>");
+    /// let select = |css| html.select(&Selector::parse(css).unwrap()).next().unwrap();
+    ///
+    /// assert_eq!(select("div").cmp_document_order(&select("p")), Some(Ordering::Less));
+    /// assert_eq!(select("div + p").cmp_document_order(&select("p")), Some(Ordering::Greater));
+    /// ```
+    pub fn cmp_document_order(&self, other: &ElementRef<'a>) -> Option<Ordering> {
+        if !std::ptr::eq(self.tree(), other.tree()) {
+            return None;
+        }
+        if let Some(ordering) = self
+            .value()
+            .tree_order
+            .cmp_position(&other.value().tree_order)
+        {
+            return Some(ordering);
+        }
+
+        let path = |element: &ElementRef<'a>| {
+            let mut path = element.ancestors().collect::<Vec<_>>();
+            path.reverse();
+            path.push(element.node);
+            path
+        };
+        let (path, other_path) = (path(self), path(other));
+        let common = path
+            .iter()
+            .zip(&other_path)
+            .take_while(|(node, other)| node == other)
+            .count();
+
+        Some(match (path.get(common), other_path.get(common)) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(node), Some(other)) => {
+                if node.next_siblings().any(|sibling| sibling == *other) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            }
+        })
+    }
+
+    /// Returns true if `other` is this element or one of its descendants, like the DOM's
+    /// `Node.contains`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use scraper::{Html, Selector};
+    /// let html = Html::parse_fragment("<div><p>1</p></div><p>2</p>");
+    /// let div = html.select(&Selector::parse("div").unwrap()).next().unwrap();
+    /// let p = html.select(&Selector::parse("p").unwrap()).collect::<Vec<_>>();
+    ///
+    /// assert!(div.contains(&div));
+    /// assert!(div.contains(&p[0]));
+    /// assert!(!div.contains(&p[1]));
+    /// ```
+    pub fn contains(&self, other: &ElementRef) -> bool {
+        self == other || other.is_child_of(self)
     }
 
+    /// Returns true if `parent` is an ancestor of this element.
+    ///
+    /// This takes constant time once [`Html::build_order_index`] has been called, and time
+    /// proportional to the depth of this element otherwise.
     pub fn is_child_of(&self, parent: &ElementRef) -> bool {
+        if std::ptr::eq(self.tree(), parent.tree()) {
+            let order = &parent.value().tree_order;
+            if let Some(contains) = order.contains(&self.value().tree_order) {
+                return contains;
+            }
+        }
+
         let mut maybe_parent = self.parent();
         while let Some(current_parent) = maybe_parent {
             if current_parent == **parent {
@@ -128,6 +1075,12 @@ impl<'a> ElementRef<'a> {
     }
 }
 
+impl Hash for ElementRef<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.id().hash(state);
+    }
+}
+
 impl<'a> Deref for ElementRef<'a> {
     type Target = NodeRef<'a, Node>;
     fn deref(&self) -> &NodeRef<'a, Node> {
@@ -138,9 +1091,28 @@ impl<'a> Deref for ElementRef<'a> {
 /// Iterator over descendent elements matching a selector.
 pub struct Select<'a, 'b> {
     scope: ElementRef<'a>,
-    inner: Traverse<'a, Node>,
+    /// The traversal of the descendants, or `None` once it is finished.
+    inner: Option<Traverse<'a, Node>>,
     selector: &'b Selector,
-    nth_index_cache: NthIndexCache,
+    nth_index_cache: SelectCache<'b>,
+    /// The elements enclosing the current position of `inner`, if the selector requires
+    /// ancestors.
+    ancestor_filter: Option<AncestorFilter>,
+    /// If the selector can only match inside descendants with certain IDs, those of them whose
+    /// subtrees are left to traverse after `inner`, the next last.
+    anchors: Option<Vec<ElementRef<'a>>>,
+    following: Option<FollowingSiblings<'a>>,
+}
+
+/// The selectors of a group which match following siblings of the scope element, and the
+/// traversal of those siblings.
+#[derive(Debug, Clone)]
+struct FollowingSiblings<'a> {
+    selector: Selector,
+    /// The index in the whole group of each selector in `selector`.
+    indices: Vec<usize>,
+    /// Starts after the descendants of the scope element have been visited.
+    inner: Option<Fuse<Traverse<'a, Node>>>,
 }
 
 impl fmt::Debug for Select<'_, '_> {
@@ -150,34 +1122,128 @@ impl fmt::Debug for Select<'_, '_> {
             .field("inner", &self.inner)
             .field("selector", &self.selector)
             .field("nth_index_cache", &"..")
+            .field("ancestor_filter", &"..")
+            .field("anchors", &self.anchors)
+            .field("following", &self.following)
             .finish()
     }
 }
 
+/// The clone does not share the caches of the original iterator, even if it was created with a
+/// [`MatchContext`]. This does not change the elements it yields.
 impl Clone for Select<'_, '_> {
     fn clone(&self) -> Self {
         Self {
             scope: self.scope,
             inner: self.inner.clone(),
             selector: self.selector,
-            nth_index_cache: NthIndexCache::default(),
+            nth_index_cache: SelectCache::default(),
+            ancestor_filter: self.ancestor_filter.clone(),
+            anchors: self.anchors.clone(),
+            following: self.following.clone(),
         }
     }
 }
 
+impl<'a> Select<'a, '_> {
+    /// Returns the first matched element whose text satisfies `pred`.
+    ///
+    /// The text passed to `pred` is that of [`ElementRef::text`] with whitespace normalized:
+    /// leading and trailing whitespace is removed, and every other run of it is replaced by a
+    /// single space. No elements after the one returned are matched.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let fragment = Html::parse_fragment(
+    ///     "<table><tr><td>Tax</td><td>2</td></tr><tr><td> Total\n</td><td>12</td></tr></table>",
+    /// );
+    /// let table = fragment.root_element();
+    /// let tr = Selector::parse("tr").unwrap();
+    ///
+    /// let total = table.select(&tr).find_text(|text| text.starts_with("Total ")).unwrap();
+    /// assert_eq!(total.text().last(), Some("12"));
+    /// ```
+    pub fn find_text<F: Fn(&str) -> bool>(mut self, pred: F) -> Option<ElementRef<'a>> {
+        self.find(|element| pred(&element.normalized_text()))
+    }
+}
+
 impl<'a, 'b> Iterator for Select<'a, 'b> {
     type Item = ElementRef<'a>;
 
     fn next(&mut self) -> Option<ElementRef<'a>> {
-        for edge in &mut self.inner {
+        self.next_with_branch().map(|(element, _)| element)
+    }
+}
+
+impl FusedIterator for Select<'_, '_> {}
+
+impl<'a> Select<'a, '_> {
+    /// Starts traversing the subtree of the next anchor, or finishes the traversal of the
+    /// descendants if there is none.
+    fn enter_next_anchor(&mut self) {
+        self.inner = None;
+        let Some(anchor) = self.anchors.as_mut().and_then(Vec::pop) else {
+            return;
+        };
+        let mut inner = anchor.traverse();
+        inner.next(); // Skip Edge::Open(anchor).
+        self.inner = Some(inner);
+        self.ancestor_filter =
+            AncestorFilter::new(self.selector, once(*anchor).chain(anchor.ancestors()));
+    }
+
+    fn next_with_branch(&mut self) -> Option<(ElementRef<'a>, usize)> {
+        while let Some(inner) = &mut self.inner {
+            for edge in inner {
+                match edge {
+                    Edge::Open(node) => {
+                        if let Some(element) = ElementRef::wrap(node) {
+                            let branch = self.selector.matching_branch_filtered(
+                                &element,
+                                Some(self.scope),
+                                self.nth_index_cache.get(),
+                                self.ancestor_filter.as_ref(),
+                            );
+                            if let Some(filter) = &mut self.ancestor_filter {
+                                filter.push(element.value());
+                            }
+                            if let Some(branch) = branch {
+                                return Some((element, branch));
+                            }
+                        }
+                    }
+                    Edge::Close(node) => {
+                        if let Some(filter) = &mut self.ancestor_filter {
+                            if node.value().is_element() {
+                                filter.pop();
+                            }
+                        }
+                    }
+                }
+            }
+            self.enter_next_anchor();
+        }
+
+        let following = self.following.as_mut()?;
+        let scope = self.scope;
+        let inner = following.inner.get_or_insert_with(|| {
+            let mut inner = scope.parent().unwrap_or(*scope).traverse().fuse();
+            inner.find(|edge| matches!(edge, Edge::Close(node) if *node == *scope));
+            inner
+        });
+        for edge in inner {
             if let Edge::Open(node) = edge {
                 if let Some(element) = ElementRef::wrap(node) {
-                    if self.selector.matches_with_scope_and_cache(
+                    if let Some(branch) = following.selector.matching_branch(
                         &element,
                         Some(self.scope),
-                        &mut self.nth_index_cache,
+                        self.nth_index_cache.get(),
                     ) {
-                        return Some(element);
+                        return Some((element, following.indices[branch]));
                     }
                 }
             }
@@ -186,7 +1252,22 @@ impl<'a, 'b> Iterator for Select<'a, 'b> {
     }
 }
 
-impl FusedIterator for Select<'_, '_> {}
+/// Iterator over descendent elements matching a selector group, together with the index of the
+/// selector which matched each.
+#[derive(Debug, Clone)]
+pub struct SelectWithBranch<'a, 'b> {
+    inner: Select<'a, 'b>,
+}
+
+impl<'a> Iterator for SelectWithBranch<'a, '_> {
+    type Item = (ElementRef<'a>, usize);
+
+    fn next(&mut self) -> Option<(ElementRef<'a>, usize)> {
+        self.inner.next_with_branch()
+    }
+}
+
+impl FusedIterator for SelectWithBranch<'_, '_> {}
 
 /// Iterator over descendent text nodes.
 #[derive(Debug, Clone)]
@@ -211,11 +1292,84 @@ impl<'a> Iterator for Text<'a> {
 
 impl FusedIterator for Text<'_> {}
 
+/// Iterator over descendent text nodes and their nearest element ancestors, returned by
+/// [`ElementRef::text_nodes_with_parents`].
+#[derive(Debug, Clone)]
+pub struct TextWithParents<'a> {
+    inner: Traverse<'a, Node>,
+    /// The elements opened but not yet closed by the traversal, innermost last.
+    open: Vec<ElementRef<'a>>,
+}
+
+impl<'a> Iterator for TextWithParents<'a> {
+    type Item = (&'a str, ElementRef<'a>);
+
+    fn next(&mut self) -> Option<(&'a str, ElementRef<'a>)> {
+        for edge in &mut self.inner {
+            match edge {
+                Edge::Open(node) => match node.value() {
+                    Node::Element(_) => self.open.extend(ElementRef::wrap(node)),
+                    Node::Text(text) => {
+                        if let Some(&parent) = self.open.last() {
+                            return Some((&**text, parent));
+                        }
+                    }
+                    _ => {}
+                },
+                Edge::Close(node) => {
+                    if node.value().is_element() {
+                        self.open.pop();
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl FusedIterator for TextWithParents<'_> {}
+
+/// Adaptor turning an iterator over nodes, like those of the `ego_tree` traversal methods, into
+/// one over elements.
+///
+/// # Example
+///
+/// ```
+/// use scraper::element_ref::WrapElements;
+/// use scraper::Html;
+///
+/// let fragment = Html::parse_fragment("<p>a</p>b<!-- c --><br>");
+/// let names = fragment
+///     .root_element()
+///     .children()
+///     .wrap_elements()
+///     .map(|element| element.value().name())
+///     .collect::<Vec<_>>();
+/// assert_eq!(names, ["p", "br"]);
+/// ```
+pub trait WrapElements<'a>: Iterator<Item = NodeRef<'a, Node>> + Sized {
+    /// Returns an iterator over the nodes which are elements, skipping all others.
+    fn wrap_elements(self) -> WrappedElements<'a, Self> {
+        self.filter_map(ElementRef::wrap)
+    }
+}
+
+impl<'a, I: Iterator<Item = NodeRef<'a, Node>>> WrapElements<'a> for I {}
+
+/// Iterator over the elements among nodes, returned by [`WrapElements::wrap_elements`].
+pub type WrappedElements<'a, I> = FilterMap<I, fn(NodeRef<'a, Node>) -> Option<ElementRef<'a>>>;
+
+mod accessibility;
+mod cursor;
 mod element;
 mod serializable;
+pub(crate) mod style;
+
+pub use self::cursor::Cursor;
 
 #[cfg(test)]
 mod tests {
+    use super::ElementRef;
     use crate::html::Html;
     use crate::selector::Selector;
 
@@ -240,7 +1394,277 @@ mod tests {
     }
 
     #[test]
-    fn test_children(){
+    fn test_select_inclusive() {
+        let fragment = Html::parse_fragment("<div id=a><p id=b><span id=c></span></p></div>");
+        let p = fragment
+            .select(&Selector::parse("p").unwrap())
+            .next()
+            .unwrap();
+        let ids = |elements: super::Select| {
+            elements
+                .map(|element| element.value().id().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        for (css, inclusive, exclusive) in [
+            ("[id]", &["b", "c"][..], &["c"][..]),
+            ("p", &["b"], &[]),
+            (":scope", &["b"], &[]),
+            (":scope > *", &["c"], &["c"]),
+            ("div *", &["b", "c"], &["c"]),
+            ("div", &[], &[]),
+        ] {
+            let selector = Selector::parse(css).unwrap();
+            assert_eq!(ids(p.select_inclusive(&selector)), inclusive, "{css}");
+            assert_eq!(ids(p.select(&selector)), exclusive, "{css}");
+        }
+    }
+
+    #[test]
+    fn test_select_visible() {
+        let fragment = Html::parse_fragment(
+            r#"
+            <p id=a>a</p>
+            <p id=b hidden>b</p>
+            <input id=c type=HIDDEN><input id=d type=text>
+            <div style="color: red; DISPLAY : None !important"><p id=e>e</p></div>
+            <div style="display: none; display: block"><p id=f>f</p></div>
+            <div style="visibility:hidden">
+                <p id=g>g</p>
+                <p id=h style="visibility: visible">h</p>
+            </div>
+            <div style="visibility: collapse"><p id=i style="display: block">i</p></div>
+            <template><p id=j>j</p></template>
+            <section hidden><div><p id=k>k</p></div></section>
+            <p id=l style="content: 'display: none'">l</p>
+            "#,
+        );
+        let ids = fragment
+            .root_element()
+            .select_visible(&Selector::parse("[id]").unwrap())
+            .map(|element| element.value().id().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["a", "d", "f", "h", "l"]);
+    }
+
+    #[test]
+    fn test_select_visible_inside_hidden() {
+        let fragment = Html::parse_fragment("<div hidden><p>a</p></div>");
+        let div = fragment
+            .select(&Selector::parse("div").unwrap())
+            .next()
+            .unwrap();
+        assert!(div.is_hidden());
+        assert_eq!(
+            div.select_visible(&Selector::parse("p").unwrap()).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_write_html() {
+        let fragment = Html::parse_fragment(
+            "<p class=\"a&quot;\">Caf\u{e9} &amp; <b>\u{1F600}</b><!-- c --><br></p><script>1 < 2</script>",
+        );
+
+        let mut out = String::from("prefix:");
+        for element in fragment.root_element().descendent_elements() {
+            out.truncate("prefix:".len());
+            element.write_html(&mut out);
+            assert_eq!(out, format!("prefix:{}", element.html()));
+
+            out.truncate("prefix:".len());
+            element.write_inner_html(&mut out);
+            assert_eq!(out, format!("prefix:{}", element.inner_html()));
+        }
+    }
+
+    #[test]
+    fn test_trimmed_inner_html() {
+        let fragment = Html::parse_fragment(
+            "<div>\n  <p>x</p>\n  <!-- c -->  <b> y </b>\n</div><span> \n </span><i> a &amp; b </i><u>&nbsp;<p>x</p>\n</u>",
+        );
+        let select = |selector: &str| {
+            fragment
+                .select(&Selector::parse(selector).unwrap())
+                .next()
+                .unwrap()
+        };
+
+        assert_eq!(
+            select("div").trimmed_inner_html(),
+            "<p>x</p>\n  <!-- c -->  <b> y </b>"
+        );
+        assert_eq!(select("span").trimmed_inner_html(), "");
+        assert_eq!(select("i").trimmed_inner_html(), " a &amp; b ");
+        assert_eq!(select("u").trimmed_inner_html(), "&nbsp;<p>x</p>");
+    }
+
+    #[test]
+    fn test_text_truncated() {
+        let fragment = Html::parse_fragment("<p>Grüße, <b>Wörld</b><i></i>!</p>");
+        let p = fragment
+            .select(&Selector::parse("p").unwrap())
+            .next()
+            .unwrap();
+
+        assert_eq!(p.text_truncated(4, false), "Grüß");
+        assert_eq!(p.text_truncated(9, true), "Grüße, Wö…");
+        assert_eq!(p.text_truncated(12, true), "Grüße, Wörld…");
+        assert_eq!(p.text_truncated(13, true), "Grüße, Wörld!");
+        assert_eq!(p.text_truncated(100, true), "Grüße, Wörld!");
+        assert_eq!(p.text_truncated(0, true), "…");
+        assert_eq!(p.text_truncated(0, false), "");
+    }
+
+    #[test]
+    fn test_select_with_branch() {
+        let fragment = Html::parse_fragment(
+            r#"<h2 class="x">a</h2><div><h1>b</h1><h3 class="x">c</h3></div><p>d</p>"#,
+        );
+        let root = fragment.root_element();
+        let branches = |selector: &str| {
+            let selector = Selector::parse(selector).unwrap();
+            let with_branch = root.select_with_branch(&selector).collect::<Vec<_>>();
+            assert_eq!(
+                with_branch.iter().map(|&(e, _)| e).collect::<Vec<_>>(),
+                root.select(&selector).collect::<Vec<_>>()
+            );
+            with_branch
+                .into_iter()
+                .map(|(element, branch)| (element.inner_html(), branch))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            branches("h1, h2, h3"),
+            [("a".into(), 1), ("b".into(), 0), ("c".into(), 2)]
+        );
+        assert_eq!(
+            branches(".x, h1, h2"),
+            [("a".into(), 0), ("b".into(), 1), ("c".into(), 0)]
+        );
+        assert_eq!(branches("p"), [("d".into(), 0)]);
+        assert!(branches("table, ul").is_empty());
+    }
+
+    #[test]
+    fn test_select_find_text() {
+        let fragment = Html::parse_fragment(
+            "<ul><li>Sub<b>total</b>  </li><li>\n Total\t due </li><li>Total due</li></ul>",
+        );
+        let ul = fragment
+            .select(&Selector::parse("ul").unwrap())
+            .next()
+            .unwrap();
+        let li = Selector::parse("li").unwrap();
+
+        let position = |element: ElementRef| ul.child_elements().position(|e| e == element);
+        let total = ul.select(&li).find_text(|text| text == "Total due");
+        assert_eq!(total.and_then(position), Some(1));
+        let subtotal = ul.select(&li).find_text(|text| text == "Subtotal");
+        assert_eq!(subtotal.and_then(position), Some(0));
+        assert!(ul.select(&li).find_text(|text| text.is_empty()).is_none());
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let fragment = Html::parse_fragment(
+            "<table><tr><td></td><td>\n\t </td><td><!-- c --> </td><td> <br> </td><td>&nbsp;</td><td>x</td></tr></table>",
+        );
+        let cells = fragment
+            .select(&Selector::parse("td").unwrap())
+            .collect::<Vec<_>>();
+        let empty_selector = Selector::parse("td:empty").unwrap();
+
+        let loose = cells.iter().map(|td| td.is_empty()).collect::<Vec<_>>();
+        assert_eq!(loose, [true, true, true, false, false, false]);
+        let strict = cells
+            .iter()
+            .map(|td| td.is_empty_strict())
+            .collect::<Vec<_>>();
+        assert_eq!(strict, [true, false, false, false, false, false]);
+        let matched = cells
+            .iter()
+            .map(|td| empty_selector.matches(td))
+            .collect::<Vec<_>>();
+        assert_eq!(matched, strict);
+    }
+
+    #[test]
+    fn test_adjacent_text() {
+        let fragment = Html::parse_fragment(
+            "<form>Email:<input id=a><!-- c --><input id=b>\n  <input id=c><b>x</b>Phone <input id=d></form>",
+        );
+        let input = |id: &str| {
+            fragment
+                .select(&Selector::parse(&format!("#{id}")).unwrap())
+                .next()
+                .unwrap()
+        };
+
+        assert_eq!(input("a").preceding_text(), Some("Email:"));
+        assert_eq!(input("a").following_text(), None);
+        assert_eq!(input("b").preceding_text(), None);
+        assert_eq!(input("b").following_text(), Some("\n  "));
+        assert_eq!(input("c").preceding_text(), Some("\n  "));
+        assert_eq!(input("c").following_text(), None);
+        assert_eq!(input("d").preceding_text(), Some("Phone "));
+        assert_eq!(input("d").following_text(), None);
+    }
+
+    #[test]
+    fn test_attr_tokens() {
+        let fragment = Html::parse_fragment(
+            "<a rel=\" nofollow noopener \" aria-describedby=\"a\tb\nc\" rev=\"x\u{a0}y\" \
+             accept=\"image/png,, image/jpeg\t,\">x</a>",
+        );
+        let a = fragment
+            .select(&Selector::parse("a").unwrap())
+            .next()
+            .unwrap();
+
+        let tokens = |attr, sep| a.attr_tokens(attr, sep).collect::<Vec<_>>();
+        assert_eq!(tokens("rel", ' '), ["nofollow", "noopener"]);
+        assert_eq!(tokens("aria-describedby", ' '), ["a", "b", "c"]);
+        assert_eq!(tokens("aria-describedby", '\t'), ["a", "b", "c"]);
+        assert_eq!(tokens("rev", ' '), ["x\u{a0}y"]);
+        assert_eq!(tokens("accept", ','), ["image/png", "image/jpeg"]);
+        assert!(tokens("class", ' ').is_empty());
+    }
+
+    #[test]
+    fn test_select_class_prefix_suffix() {
+        let fragment = Html::parse_fragment(
+            r#"<div class="Card_x1">
+                <span class="title Button_abc123">a</span>
+                <span class="Buttons">b</span>
+                <span class="big-icon">c</span>
+                <span class="my-Button_">d</span>
+            </div>"#,
+        );
+        let card = fragment
+            .select(&Selector::parse("div").unwrap())
+            .next()
+            .unwrap();
+
+        let prefixed = card
+            .select_class_prefix("Button_")
+            .map(|e| e.inner_html())
+            .collect::<Vec<_>>();
+        assert_eq!(prefixed, ["a"]);
+
+        let suffixed = card
+            .select_class_suffix("-icon")
+            .map(|e| e.inner_html())
+            .collect::<Vec<_>>();
+        assert_eq!(suffixed, ["c"]);
+
+        assert_eq!(card.select_class_prefix("Card_").count(), 0);
+    }
+
+    #[test]
+    fn test_children() {
         let html = r"
             <form>
                 <input>1</input>
@@ -253,7 +1677,7 @@ mod tests {
 
         let html = Html::parse_document(html);
         let forms = html.forms();
-        let form = forms.get(0).unwrap();
+        let form = forms.first().unwrap();
         let input_sel = Selector::parse("input").unwrap();
         let input = html.select(&input_sel).next().unwrap();
         let button_sel = Selector::parse("button").unwrap();
@@ -262,4 +1686,328 @@ mod tests {
         assert!(input.is_child_of(form));
         assert!(!button.is_child_of(form));
     }
+
+    const VOID_ELEMENTS: &[&str] = &[
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+        "source", "track", "wbr",
+    ];
+
+    /// Parses each void element inside a parent which the HTML parser accepts it in, and returns
+    /// the parent.
+    fn void_element_parents() -> Vec<(&'static str, Html)> {
+        VOID_ELEMENTS
+            .iter()
+            .map(|name| {
+                let html = match *name {
+                    "col" => r#"<table><colgroup><col id="x"></colgroup></table>"#.to_owned(),
+                    _ => format!(r#"<div><{name} id="x"></div>"#),
+                };
+                (*name, Html::parse_fragment(&html))
+            })
+            .collect()
+    }
+
+    fn parent_of_void(html: &Html) -> ElementRef<'_> {
+        let selector = Selector::parse("#x").unwrap();
+        ElementRef::wrap(html.select(&selector).next().unwrap().parent().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_void_elements_html() {
+        for (name, html) in void_element_parents() {
+            let parent = parent_of_void(&html);
+            let tag = parent.value().name();
+            assert_eq!(parent.html(), format!(r#"<{tag}><{name} id="x"></{tag}>"#));
+        }
+    }
+
+    #[test]
+    fn test_void_elements_xhtml() {
+        for (name, html) in void_element_parents() {
+            let parent = parent_of_void(&html);
+            let tag = parent.value().name();
+            assert_eq!(
+                parent.html_xhtml(),
+                format!(r#"<{tag}><{name} id="x" /></{tag}>"#)
+            );
+        }
+    }
+
+    #[test]
+    fn test_html_xhtml_escaping() {
+        let html = Html::parse_fragment(
+            "<div title='a<b \"c\" &amp;'>x &lt; y\u{a0}<!--c--><script>if (a < b) {}</script></div>",
+        );
+        let div = html
+            .select(&Selector::parse("div").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(
+            div.html_xhtml(),
+            "<div title=\"a&lt;b &quot;c&quot; &amp;\">x &lt; y\u{a0}<!--c-->\
+             <script>if (a &lt; b) {}</script></div>"
+        );
+    }
+
+    #[test]
+    fn test_text_nodes_with_parents() {
+        let fragment = Html::parse_fragment(
+            "<div>a<p>b<i>c</i><!--x-->d</p><template>e<b>f</b></template>g</div>",
+        );
+        let div = fragment
+            .select(&Selector::parse("div").unwrap())
+            .next()
+            .unwrap();
+
+        let texts = div
+            .text_nodes_with_parents()
+            .map(|(text, parent)| (text, parent.value().name()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            [
+                ("a", "div"),
+                ("b", "p"),
+                ("c", "i"),
+                ("d", "p"),
+                ("e", "template"),
+                ("f", "b"),
+                ("g", "div"),
+            ]
+        );
+        assert!(div
+            .text_nodes_with_parents()
+            .map(|(text, _)| text)
+            .eq(div.text()));
+    }
+
+    #[test]
+    fn test_ancestor_filter_agrees() {
+        let html = Html::parse_document(
+            r#"<div id="main" class="results wide">
+                <section class="item"><a href="/1">1</a><p><a id="x">2</a></p></section>
+                <section class="other"><span class="item"><a>3</a></span></section>
+                <template><div class="item"><a>4</a></div></template>
+                <svg><foreignObject><p class="item"><a>5</a></p></foreignObject></svg>
+            </div>
+            <ul class="results"><li class="item">6</li><li><a class="item">7</a></li></ul>"#,
+        );
+        let selectors = [
+            ".results .item a",
+            "#main a",
+            "div > section a, ul .item",
+            "section + section a",
+            "section ~ * a",
+            ".results.wide .item",
+            ":not(section) > a",
+            "svg|foreignObject p",
+            "foreignobject p",
+            "DIV a",
+            "template a",
+            "body > div",
+            ".missing a",
+            "a",
+        ];
+
+        let root = html.root_element();
+        assert!(root
+            .select(&Selector::parse("a").unwrap())
+            .ancestor_filter
+            .is_none());
+        let selector = Selector::parse(".results .item a").unwrap();
+        let filtered = root.select(&selector);
+        assert!(filtered.ancestor_filter.is_some());
+        assert_eq!(filtered.count(), 4);
+
+        let scopes = html
+            .select(&Selector::parse("html, section, ul").unwrap())
+            .collect::<Vec<_>>();
+        for css in selectors {
+            let selector = Selector::parse(css).unwrap();
+            for scope in &scopes {
+                for mut select in [scope.select(&selector), scope.select_inclusive(&selector)] {
+                    let mut unfiltered = select.clone();
+                    unfiltered.ancestor_filter = None;
+                    let unfiltered = unfiltered.collect::<Vec<_>>();
+                    assert_eq!(select.by_ref().collect::<Vec<_>>(), unfiltered, "{css}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_anchored_select() {
+        let html = Html::parse_document(
+            r#"
+            <div id="a"><p class="x" n="1"></p><div id="a"><p class="x" n="2"></p></div></div>
+            <p class="x" n="3"></p>
+            <section id="b"><p class="x" n="4"></p><p class="y" n="5"></p></section>
+            <div id="a"><span><p class="x" n="6"></p></span></div>
+            <ul><li id="c" n="7"></li><li class="x" n="8"><b class="x" n="9"></b></li></ul>
+            "#,
+        );
+        // `[id=..]` selects the same elements as `#..`, but does not restrict traversal.
+        let selectors = [
+            ("#a .x", "[id=a] .x"),
+            ("#a > .x", "[id=a] > .x"),
+            ("#b .x, #a span > .x", "[id=b] .x, [id=a] span > .x"),
+            ("#b .x + .y", "[id=b] .x + .y"),
+            ("#a ~ section .x", "[id=a] ~ section .x"),
+            ("#c + .x", "[id=c] + .x"),
+            ("#c ~ * .x", "[id=c] ~ * .x"),
+            ("#a .x, .x", "[id=a] .x, .x"),
+            ("div#a", "div[id=a]"),
+            ("#missing .x", "[id=missing] .x"),
+        ];
+
+        let scopes = html
+            .select(&Selector::parse("html, #a, #b, ul, p").unwrap())
+            .collect::<Vec<_>>();
+        fn numbers<'a>(elements: impl Iterator<Item = ElementRef<'a>>) -> Vec<&'a str> {
+            elements
+                .map(|element| element.attr("n").unwrap_or("-"))
+                .collect()
+        }
+        for (anchored, plain) in selectors {
+            let anchored_selector = Selector::parse(anchored).unwrap();
+            let plain_selector = Selector::parse(plain).unwrap();
+            for scope in &scopes {
+                assert_eq!(
+                    numbers(scope.select(&anchored_selector)),
+                    numbers(scope.select(&plain_selector)),
+                    "{anchored}",
+                );
+                assert_eq!(
+                    numbers(scope.select_inclusive(&anchored_selector)),
+                    numbers(scope.select_inclusive(&plain_selector)),
+                    "{anchored}",
+                );
+            }
+        }
+
+        let root = html.root_element();
+        let selector = Selector::parse("#a .x").unwrap();
+        let select = root.select(&selector);
+        assert_eq!(select.anchors.as_ref().map(Vec::len), Some(1));
+        assert_eq!(numbers(select), ["1", "2", "6"]);
+        let selector = Selector::parse("#missing .x").unwrap();
+        assert!(root.select(&selector).inner.is_none());
+        assert!(root
+            .select(&Selector::parse("#c + .x").unwrap())
+            .anchors
+            .is_none());
+    }
+
+    #[test]
+    fn test_text_between() {
+        let fragment = Html::parse_fragment(
+            "<dl><dt>Name</dt><dd>Ada <b>Lovelace</b></dd><dt>Born</dt><dd>1815</dd><hr></dl>",
+        );
+        let dl = fragment
+            .select(&Selector::parse("dl").unwrap())
+            .next()
+            .unwrap();
+        let between = |start: &str, end: &str| {
+            dl.text_between(
+                &Selector::parse(start).unwrap(),
+                &Selector::parse(end).unwrap(),
+            )
+        };
+
+        assert_eq!(between("dt", "dt"), "Ada Lovelace");
+        assert_eq!(between("dt:nth-of-type(2)", "hr"), "1815");
+        assert_eq!(between("dd b", "hr"), "Born1815");
+        assert_eq!(between("dt", "table"), "");
+        assert_eq!(between("table", "dt"), "");
+        assert_eq!(between("dl", "dt"), "");
+    }
+
+    #[test]
+    fn test_closest_with_attr() {
+        let fragment = Html::parse_fragment(
+            r#"<div lang="de" id="outer"><p lang="" id="inner"><span id="leaf">x</span></p></div>"#,
+        );
+        let id = |selector: &str, attr: &str| {
+            let element = fragment
+                .select(&Selector::parse(selector).unwrap())
+                .next()
+                .unwrap();
+            element
+                .closest_with_attr(attr)
+                .map(|(element, value)| (element.value().id().unwrap(), value))
+        };
+
+        assert_eq!(id("span", "lang"), Some(("inner", "")));
+        assert_eq!(id("div", "lang"), Some(("outer", "de")));
+        assert_eq!(id("span", "id"), Some(("leaf", "leaf")));
+        assert_eq!(id("span", "data-missing"), None);
+    }
+
+    #[test]
+    fn test_attr_diff() {
+        let old = Html::parse_fragment(r#"<div id="x" class="a b" data-n="1" hidden>"#);
+        let new = Html::parse_fragment(r#"<div data-n="1" class="b a" id="x">"#);
+        fn div(html: &Html) -> ElementRef<'_> {
+            html.root_element().child_elements().next().unwrap()
+        }
+
+        assert_eq!(
+            div(&old).attr_diff(&div(&new)),
+            [
+                ("class", Some("a b"), Some("b a")),
+                ("hidden", Some(""), None)
+            ]
+        );
+        assert_eq!(
+            div(&new).attr_diff(&div(&old)),
+            [
+                ("class", Some("b a"), Some("a b")),
+                ("hidden", None, Some(""))
+            ]
+        );
+        assert!(div(&old).attr_diff(&div(&old)).is_empty());
+    }
+
+    #[test]
+    fn test_nth_selectors_in_scope() {
+        let fragment = Html::parse_fragment(
+            r#"
+            <ul id="outside"></ul>
+            <div id="scope">
+                <p></p>
+                <ul id="u1"><li n="1"></li><b></b><li n="2"></li><li n="3"></li></ul>
+                <ul id="u2"><li n="4"></li><li n="5"></li></ul>
+                <section><i></i><ul id="u3"><li n="6"></li></ul></section>
+            </div>
+            "#,
+        );
+        let scope = fragment
+            .select(&Selector::parse("#scope").unwrap())
+            .next()
+            .unwrap();
+        let attrs = |select: super::Select, attr: &str| {
+            select
+                .map(|element| element.attr(attr).unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        // Positions count all siblings, including those outside the scope.
+        let ul = Selector::parse("ul:nth-child(2)").unwrap();
+        assert_eq!(attrs(scope.select(&ul), "id"), ["u1", "u3"]);
+        let li = Selector::parse("li:nth-of-type(odd)").unwrap();
+        assert_eq!(attrs(scope.select(&li), "n"), ["1", "3", "4", "6"]);
+        let li = Selector::parse("ul:nth-of-type(2) > li:nth-child(2)").unwrap();
+        assert_eq!(attrs(scope.select(&li), "n"), ["5"]);
+
+        // Clones start with an empty cache, whether taken before or during iteration.
+        let li = Selector::parse("li:nth-of-type(odd), li:nth-last-child(2)").unwrap();
+        let expected = ["1", "2", "3", "4", "6"];
+        let mut select = scope.select(&li);
+        assert_eq!(attrs(select.clone(), "n"), expected);
+        select.next();
+        select.next();
+        let clone = select.clone();
+        assert_eq!(attrs(clone, "n"), expected[2..]);
+        assert_eq!(attrs(select, "n"), expected[2..]);
+    }
 }
@@ -2,6 +2,7 @@ use std::io::Error;
 
 use html5ever::serialize::{Serialize, Serializer, TraversalScope};
 
+use crate::node::serializable::{serialize, ByParent};
 use crate::ElementRef;
 
 impl<'a> Serialize for ElementRef<'a> {
@@ -10,6 +11,6 @@ impl<'a> Serialize for ElementRef<'a> {
         serializer: &mut S,
         traversal_scope: TraversalScope,
     ) -> Result<(), Error> {
-        crate::node::serializable::serialize(**self, serializer, traversal_scope)
+        serialize(**self, &mut ByParent(serializer), traversal_scope)
     }
 }
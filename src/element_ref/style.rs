@@ -0,0 +1,161 @@
+//! Parsing of inline `style` attributes.
+
+/// Returns an iterator over the `(property, value)` pairs of the declarations in a `style`
+/// attribute, in source order.
+///
+/// Declarations are separated by semicolons outside of quotes and parentheses, so values like
+/// `url("data:image/png;base64,...")` are kept whole. Both parts are trimmed and a trailing
+/// `!important` is dropped from the value. Declarations without a colon, a property or a value
+/// are skipped.
+pub(crate) fn declarations(style: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = style;
+    std::iter::from_fn(move || loop {
+        if rest.is_empty() {
+            return None;
+        }
+
+        let end = declaration_end(rest);
+        let declaration = &rest[..end];
+        rest = rest.get(end + 1..).unwrap_or_default();
+
+        if let Some((property, value)) = declaration.split_once(':') {
+            let property = property.trim();
+            let value = strip_important(value.trim());
+            if !property.is_empty() && !value.is_empty() {
+                return Some((property, value));
+            }
+        }
+    })
+}
+
+/// Returns the value of the last declaration of `property` in a `style` attribute, comparing
+/// property names ASCII case-insensitively.
+pub(crate) fn property<'s>(style: &'s str, property: &str) -> Option<&'s str> {
+    declarations(style)
+        .filter(|(name, _)| name.eq_ignore_ascii_case(property))
+        .map(|(_, value)| value)
+        .last()
+}
+
+/// Returns the byte index of the semicolon ending the first declaration of `style`, or its
+/// length if there is none.
+fn declaration_end(style: &str) -> usize {
+    let mut quote = None;
+    let mut depth = 0usize;
+    let mut chars = style.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ';') if depth == 0 => return i,
+            _ => {}
+        }
+    }
+
+    style.len()
+}
+
+/// Returns the URLs of the `url()` functions in a declaration value, in order.
+///
+/// URLs may be unquoted or enclosed in single or double quotes. Backslash escapes are resolved
+/// and empty URLs are skipped.
+pub(crate) fn urls(value: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut rest = value;
+
+    while let Some(start) = find_ascii_case_insensitive(rest, "url(") {
+        let arguments = rest[start + 4..].trim_start();
+        let quote = arguments.chars().next().filter(|c| matches!(c, '"' | '\''));
+        let arguments = &arguments[quote.map_or(0, char::len_utf8)..];
+
+        let mut url = String::new();
+        let mut chars = arguments.char_indices();
+        let mut end = arguments.len();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => url.extend(chars.next().map(|(_, c)| c)),
+                c if Some(c) == quote || (quote.is_none() && c == ')') => {
+                    end = i + c.len_utf8();
+                    break;
+                }
+                c => url.push(c),
+            }
+        }
+
+        let url = url.trim();
+        if !url.is_empty() {
+            urls.push(url.to_owned());
+        }
+        rest = &arguments[end..];
+    }
+
+    urls
+}
+
+fn find_ascii_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn strip_important(value: &str) -> &str {
+    let len = value.len();
+    match value.get(len.saturating_sub(10)..) {
+        Some(important) if important.eq_ignore_ascii_case("!important") => {
+            value[..len - 10].trim_end()
+        }
+        _ => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{declarations, property, urls};
+
+    #[test]
+    fn split_declarations() {
+        let parsed = declarations(
+            r#" color : red ; ; bogus; :x; y:; background-image: url("a;b.png") ;font-family:'A;B', serif;width:1px !IMPORTANT;content: "\";" "#,
+        )
+        .collect::<Vec<_>>();
+        assert_eq!(
+            parsed,
+            [
+                ("color", "red"),
+                ("background-image", r#"url("a;b.png")"#),
+                ("font-family", "'A;B', serif"),
+                ("width", "1px"),
+                ("content", r#""\";""#),
+            ]
+        );
+        assert_eq!(declarations("").count(), 0);
+        assert_eq!(declarations("background: url(a;b").count(), 1);
+    }
+
+    #[test]
+    fn last_property_wins() {
+        let style = "display: none; DISPLAY: block !important";
+        assert_eq!(property(style, "display"), Some("block"));
+        assert_eq!(property(style, "Display"), Some("block"));
+        assert_eq!(property(style, "color"), None);
+    }
+
+    #[test]
+    fn extract_urls() {
+        assert_eq!(
+            urls(r#"URL( a.png ), url("b c.png") no-repeat, url('d\'e.png'), url(f\).png)"#),
+            ["a.png", "b c.png", "d'e.png", "f).png"]
+        );
+        assert_eq!(urls("url() , url('')"), Vec::<String>::new());
+        assert_eq!(urls("none"), Vec::<String>::new());
+        assert_eq!(urls("url(unterminated"), ["unterminated"]);
+    }
+}
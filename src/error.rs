@@ -1,12 +1,329 @@
 //! Custom error types for diagnostics
 //! Includes re-exported error types from dependencies
 
-mod utils;
-
+use std::ops::Range;
 use std::{error::Error, fmt::Display};
 
-use cssparser::{BasicParseErrorKind, ParseErrorKind, Token};
-use selectors::parser::SelectorParseErrorKind;
+use cssparser::{BasicParseErrorKind, ParseErrorKind, SourceLocation, Token};
+use scraper_selector_parser::render as utils;
+use selectors::parser::SelectorParseErrorKind as CssSelectorErrorKind;
+
+/// Error type that is returned when calling `Selector::parse`
+///
+/// Unlike [`SelectorErrorKind`], this does not borrow from the parsed input, so it can be stored,
+/// cloned and sent across threads. Its `Display` implementation renders the offending line of the
+/// input with the failing location underlined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorParseError {
+    kind: SelectorParseErrorKind,
+    message: String,
+    token: Option<String>,
+    input: String,
+    line: usize,
+    column: usize,
+    offset: usize,
+}
+
+impl SelectorParseError {
+    pub(crate) fn new(
+        input: &str,
+        original: cssparser::ParseError<'_, CssSelectorErrorKind<'_>>,
+    ) -> Self {
+        let token = match &original.kind {
+            ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(token)) => {
+                Some(utils::render_token(token))
+            }
+            ParseErrorKind::Custom(err) => utils::render_selector_error_token(err),
+            ParseErrorKind::Basic(_) => None,
+        };
+        let kind = SelectorParseErrorKind::from(&original.kind);
+        let (line, column, offset) = locate(input, original.location);
+
+        Self {
+            kind,
+            message: SelectorErrorKind::from(original).to_string(),
+            token,
+            input: input.to_owned(),
+            line,
+            column,
+            offset,
+        }
+    }
+
+    /// Moves the error into `input`, which contains the selector that failed to parse, at byte
+    /// `offset`.
+    pub(crate) fn relocated(mut self, input: &str, offset: usize) -> Self {
+        let before = &input[..offset];
+        self.line = before.matches('\n').count() + 1;
+        self.column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1;
+        self.offset = offset;
+        self.input = input.to_owned();
+        self
+    }
+
+    /// Returns the category of the error.
+    pub fn kind(&self) -> SelectorParseErrorKind {
+        self.kind
+    }
+
+    /// Returns a description of what went wrong.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the text of the token at which parsing failed, if there was one.
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Returns the selector that failed to parse.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Returns the 1-based line at which parsing failed.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the 1-based column, counted in characters, at which parsing failed.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns the byte offset within the input at which parsing failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// The category of a [`SelectorParseError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SelectorParseErrorKind {
+    /// A token which is not valid at its position, e.g. the second `.` of `a..b`.
+    UnexpectedToken,
+    /// The selector ended too early, e.g. in the middle of an attribute selector.
+    UnexpectedEnd,
+    /// The selector, or one of the selectors of a comma-separated list, is empty.
+    EmptySelector,
+    /// A combinator is not followed by a selector, e.g. `div >`.
+    DanglingCombinator,
+    /// A pseudo-class or pseudo-element which is not supported, e.g. `:hover-ish`.
+    UnsupportedPseudo,
+    /// A pseudo-element is malformed or not allowed at its position.
+    InvalidPseudoElement,
+    /// An attribute selector is malformed, e.g. `[a=1]`.
+    InvalidAttributeSelector,
+    /// A namespace prefix which is not declared, e.g. `foo|a`.
+    UndeclaredNamespace,
+    /// Any other error.
+    Other,
+}
+
+impl From<&ParseErrorKind<'_, CssSelectorErrorKind<'_>>> for SelectorParseErrorKind {
+    fn from(kind: &ParseErrorKind<'_, CssSelectorErrorKind<'_>>) -> Self {
+        match kind {
+            ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(_)) => Self::UnexpectedToken,
+            ParseErrorKind::Basic(BasicParseErrorKind::EndOfInput) => Self::UnexpectedEnd,
+            ParseErrorKind::Basic(_) => Self::Other,
+            ParseErrorKind::Custom(err) => match err {
+                CssSelectorErrorKind::EmptySelector => Self::EmptySelector,
+                CssSelectorErrorKind::DanglingCombinator => Self::DanglingCombinator,
+                CssSelectorErrorKind::UnsupportedPseudoClassOrElement(_) => Self::UnsupportedPseudo,
+                CssSelectorErrorKind::PseudoElementExpectedColon(_)
+                | CssSelectorErrorKind::PseudoElementExpectedIdent(_)
+                | CssSelectorErrorKind::NonPseudoElementAfterSlotted
+                | CssSelectorErrorKind::InvalidPseudoElementAfterSlotted
+                | CssSelectorErrorKind::InvalidPseudoElementInsideWhere => {
+                    Self::InvalidPseudoElement
+                }
+                CssSelectorErrorKind::NoQualifiedNameInAttributeSelector(_)
+                | CssSelectorErrorKind::UnexpectedTokenInAttributeSelector(_)
+                | CssSelectorErrorKind::ExpectedBarInAttr(_)
+                | CssSelectorErrorKind::BadValueInAttr(_)
+                | CssSelectorErrorKind::InvalidQualNameInAttr(_) => Self::InvalidAttributeSelector,
+                CssSelectorErrorKind::ExpectedNamespace(_) => Self::UndeclaredNamespace,
+                CssSelectorErrorKind::UnexpectedIdent(_)
+                | CssSelectorErrorKind::NoIdentForPseudo(_)
+                | CssSelectorErrorKind::ExplicitNamespaceUnexpectedToken(_)
+                | CssSelectorErrorKind::ClassNeedsIdent(_)
+                | CssSelectorErrorKind::NonCompoundSelector => Self::UnexpectedToken,
+                CssSelectorErrorKind::InvalidState => Self::Other,
+            },
+        }
+    }
+}
+
+/// Converts a cssparser location (0-based line, 1-based UTF-16 column) into a 1-based line, a
+/// 1-based character column and a byte offset into `input`.
+fn locate(input: &str, location: SourceLocation) -> (usize, usize, usize) {
+    let line = location.line as usize;
+    let line_start = input
+        .split_inclusive('\n')
+        .take(line)
+        .map(str::len)
+        .sum::<usize>();
+
+    let mut utf16_column = 1;
+    let mut column = 1;
+    let mut offset = line_start;
+    for c in input[line_start..].chars() {
+        if utf16_column >= location.column as usize || c == '\n' {
+            break;
+        }
+        utf16_column += c.len_utf16();
+        column += 1;
+        offset += c.len_utf8();
+    }
+
+    (line + 1, column, offset)
+}
+
+impl Display for SelectorParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )?;
+
+        let source_line = self.input.lines().nth(self.line - 1).unwrap_or("");
+        let width = self
+            .token
+            .as_ref()
+            .map_or(1, |token| token.chars().count().max(1));
+        writeln!(f, "{}", source_line)?;
+        write!(f, "{:>2$}{}", "", "^".repeat(width), self.column - 1)
+    }
+}
+
+impl Error for SelectorParseError {}
+
+/// The error returned by [`Html::select_required`] when no element matches the selector.
+///
+/// [`Html::select_required`]: crate::Html::select_required
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingElement {
+    selector: String,
+}
+
+impl MissingElement {
+    pub(crate) fn new(selector: &crate::Selector) -> Self {
+        Self {
+            selector: selector.css(),
+        }
+    }
+
+    /// Returns the CSS text of the selector which matched no element.
+    pub fn selector(&self) -> &str {
+        &self.selector
+    }
+}
+
+impl Display for MissingElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No element matches the selector {:?}", self.selector)
+    }
+}
+
+impl Error for MissingElement {}
+
+/// An invalid selector reported by [`Selector::parse_many`], together with its name.
+///
+/// [`Selector::parse_many`]: crate::Selector::parse_many
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedSelectorError {
+    name: String,
+    error: SelectorParseError,
+}
+
+impl NamedSelectorError {
+    pub(crate) fn new(name: &str, error: SelectorParseError) -> Self {
+        Self {
+            name: name.to_owned(),
+            error,
+        }
+    }
+
+    /// Returns the name of the invalid selector.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the text of the invalid selector.
+    pub fn selector(&self) -> &str {
+        self.error.input()
+    }
+
+    /// Returns the error which occurred while parsing the selector.
+    pub fn error(&self) -> &SelectorParseError {
+        &self.error
+    }
+}
+
+impl Display for NamedSelectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid selector {:?}: {}", self.name, self.error)
+    }
+}
+
+impl Error for NamedSelectorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A selector dropped from a selector list by [`Selector::parse_forgiving`], or a part of one
+/// dropped by [`Selector::parse_lenient`].
+///
+/// [`Selector::parse_forgiving`]: crate::Selector::parse_forgiving
+/// [`Selector::parse_lenient`]: crate::Selector::parse_lenient
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorParseWarning {
+    span: Range<usize>,
+    error: SelectorParseError,
+}
+
+impl SelectorParseWarning {
+    pub(crate) fn new(span: Range<usize>, error: SelectorParseError) -> Self {
+        Self { span, error }
+    }
+
+    /// Returns the byte range of the dropped selector within the input, excluding surrounding
+    /// whitespace.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Returns the text of the dropped selector.
+    pub fn selector(&self) -> &str {
+        &self.error.input()[self.span()]
+    }
+
+    /// Returns the error which caused the selector to be dropped.
+    pub fn error(&self) -> &SelectorParseError {
+        &self.error
+    }
+}
+
+impl Display for SelectorParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ignored selector {:?}: {}", self.selector(), self.error)
+    }
+}
+
+impl Error for SelectorParseWarning {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
 
 /// Error type that is returned when calling `Selector::parse`
 #[derive(Debug, Clone)]
@@ -33,11 +350,11 @@ pub enum SelectorErrorKind<'a> {
     ExpectedIdentityOnPseudoElement(Token<'a>),
 
     /// A `SelectorParseErrorKind` error that isn't really supposed to happen did
-    UnexpectedSelectorParseError(SelectorParseErrorKind<'a>),
+    UnexpectedSelectorParseError(CssSelectorErrorKind<'a>),
 }
 
-impl<'a> From<cssparser::ParseError<'a, SelectorParseErrorKind<'a>>> for SelectorErrorKind<'a> {
-    fn from(original: cssparser::ParseError<'a, SelectorParseErrorKind<'a>>) -> Self {
+impl<'a> From<cssparser::ParseError<'a, CssSelectorErrorKind<'a>>> for SelectorErrorKind<'a> {
+    fn from(original: cssparser::ParseError<'a, CssSelectorErrorKind<'a>>) -> Self {
         // NOTE: This could be improved, but I dont
         // exactly know how
         match original.kind {
@@ -59,13 +376,13 @@ impl<'a> From<BasicParseErrorKind<'a>> for SelectorErrorKind<'a> {
     }
 }
 
-impl<'a> From<SelectorParseErrorKind<'a>> for SelectorErrorKind<'a> {
-    fn from(err: SelectorParseErrorKind<'a>) -> Self {
+impl<'a> From<CssSelectorErrorKind<'a>> for SelectorErrorKind<'a> {
+    fn from(err: CssSelectorErrorKind<'a>) -> Self {
         match err {
-            SelectorParseErrorKind::PseudoElementExpectedColon(token) => {
+            CssSelectorErrorKind::PseudoElementExpectedColon(token) => {
                 Self::ExpectedColonOnPseudoElement(token)
             }
-            SelectorParseErrorKind::PseudoElementExpectedIdent(token) => {
+            CssSelectorErrorKind::PseudoElementExpectedIdent(token) => {
                 Self::ExpectedIdentityOnPseudoElement(token)
             }
             other => Self::UnexpectedSelectorParseError(other),
@@ -94,10 +411,11 @@ impl<'a> Display for SelectorErrorKind<'a> {
                     "Expected identity for pseudoelement, got {:?} instead",
                     utils::render_token(token)
                 ),
-                Self::UnexpectedSelectorParseError(err) => format!(
-                    "Unexpected error occurred. Please report this to the developer\n{:#?}",
-                    err
-                ),
+                Self::UnexpectedSelectorParseError(err) => utils::describe_selector_error(err)
+                    .unwrap_or_else(|| format!(
+                        "Unexpected error occurred. Please report this to the developer\n{:#?}",
+                        err
+                    )),
             }
         )
     }
@@ -117,3 +435,98 @@ impl<'a> Error for SelectorErrorKind<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{SelectorParseError, SelectorParseErrorKind};
+    use crate::Selector;
+
+    fn parse_error(selector: &str) -> SelectorParseError {
+        Selector::parse(selector).unwrap_err()
+    }
+
+    #[test]
+    fn dangling_combinator_position() {
+        let err = parse_error("div >");
+        assert_eq!((err.line(), err.column(), err.offset()), (1, 6, 5));
+        assert_eq!(err.token(), None);
+        assert_eq!(
+            err.to_string(),
+            "Combinator is not followed by a selector at line 1, column 6\ndiv >\n     ^"
+        );
+    }
+
+    #[test]
+    fn unterminated_attribute_position() {
+        let err = parse_error("[attr=");
+        assert_eq!((err.line(), err.column(), err.offset()), (1, 7, 6));
+        assert_eq!(err.message(), "Unexpected EOL");
+    }
+
+    #[test]
+    fn unknown_pseudo_element_token() {
+        let err = parse_error("::unknown");
+        assert_eq!((err.line(), err.column(), err.offset()), (1, 2, 1));
+        assert_eq!(err.token(), Some("unknown"));
+        assert!(err.to_string().ends_with("::unknown\n ^^^^^^^"));
+    }
+
+    #[test]
+    fn position_on_later_line() {
+        let err = parse_error("div\n  > ~ p");
+        assert_eq!((err.line(), err.column(), err.offset()), (2, 5, 8));
+        assert!(err.to_string().ends_with("\n  > ~ p\n    ^"));
+    }
+
+    #[test]
+    fn offset_counts_bytes() {
+        let err = parse_error("é[");
+        assert_eq!((err.column(), err.offset()), (3, 3));
+    }
+
+    #[test]
+    fn delim_token_is_rendered() {
+        let err = parse_error("a..b");
+        assert_eq!(err.token(), Some("."));
+    }
+
+    #[test]
+    fn error_kinds() {
+        for (selector, kind) in [
+            ("a..b", SelectorParseErrorKind::UnexpectedToken),
+            ("[attr=", SelectorParseErrorKind::UnexpectedEnd),
+            ("", SelectorParseErrorKind::EmptySelector),
+            ("a, , b", SelectorParseErrorKind::EmptySelector),
+            ("div >", SelectorParseErrorKind::DanglingCombinator),
+            ("p:unknown", SelectorParseErrorKind::UnsupportedPseudo),
+            ("a::5", SelectorParseErrorKind::InvalidPseudoElement),
+            ("[a=1]", SelectorParseErrorKind::InvalidAttributeSelector),
+            ("foo|a", SelectorParseErrorKind::UndeclaredNamespace),
+        ] {
+            assert_eq!(parse_error(selector).kind(), kind, "{:?}", selector);
+        }
+    }
+
+    #[test]
+    fn error_into_anyhow() {
+        fn parse(selector: &str) -> anyhow::Result<Selector> {
+            Ok(Selector::parse(selector)?)
+        }
+
+        let err = parse("div >").unwrap_err();
+        let err = err.downcast_ref::<SelectorParseError>().unwrap();
+        assert_eq!(err.kind(), SelectorParseErrorKind::DanglingCombinator);
+        assert_eq!(err.input(), "div >");
+    }
+
+    #[test]
+    fn error_is_owned() {
+        fn assert_traits<E: std::error::Error + Clone + Send + Sync + 'static>(_: &E) {}
+
+        let err = {
+            let input = String::from("div >");
+            parse_error(&input)
+        };
+        assert_traits(&err);
+    }
+}
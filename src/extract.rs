@@ -0,0 +1,298 @@
+//! Scrapy-style extraction of text and attributes.
+
+use cssparser::{Delimiter, ParseError, Parser, ParserInput, Token};
+use selectors::NthIndexCache;
+
+use crate::error::SelectorParseError;
+use crate::{ElementRef, Selector};
+
+/// A value extracted by [`Html::extract`] or [`ElementRef::extract`].
+///
+/// [`Html::extract`]: crate::Html::extract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractedValue<'a> {
+    /// An element matched by a selector without a pseudo-element.
+    Element(ElementRef<'a>),
+    /// A text node child of an element matched by a selector ending in `::text`.
+    Text(&'a str),
+    /// The value of the attribute named by a selector ending in `::attr(name)`.
+    Attr(&'a str),
+}
+
+impl<'a> ExtractedValue<'a> {
+    /// Returns the element, if this is an `Element`.
+    pub fn as_element(&self) -> Option<ElementRef<'a>> {
+        match *self {
+            Self::Element(element) => Some(element),
+            _ => None,
+        }
+    }
+
+    /// Returns the text or attribute value, if this is not an `Element`.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            Self::Text(value) | Self::Attr(value) => Some(value),
+            Self::Element(_) => None,
+        }
+    }
+}
+
+/// What to extract from the elements matched by one selector of an extraction expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Element,
+    Text,
+    Attr(String),
+}
+
+/// A parsed extraction expression, i.e. a selector list whose selectors may end in `::text` or
+/// `::attr(name)`.
+#[derive(Debug)]
+pub(crate) struct Extraction {
+    /// The selectors without their pseudo-elements. `None` stands for a bare pseudo-element,
+    /// which applies to the element extraction starts from.
+    components: Vec<(Option<Selector>, Mode)>,
+}
+
+impl Extraction {
+    pub(crate) fn parse(css: &str) -> Result<Self, SelectorParseError> {
+        split_list(css)
+            .into_iter()
+            .map(|component| {
+                let (selector, mode) = split_pseudo_element(component);
+                let selector = match selector.trim() {
+                    "" if mode != Mode::Element => None,
+                    selector => Some(Selector::parse(selector)?),
+                };
+                Ok((selector, mode))
+            })
+            .collect::<Result<_, _>>()
+            .map(|components| Self { components })
+    }
+
+    /// Extracts values from `context`, and from its descendants matching the selectors with
+    /// `scope` as `:scope`.
+    pub(crate) fn run<'a>(
+        &self,
+        context: ElementRef<'a>,
+        include_context: bool,
+        scope: Option<ElementRef<'a>>,
+    ) -> Vec<ExtractedValue<'a>> {
+        let mut nth_index_cache = NthIndexCache::default();
+        let mut values = Vec::new();
+        let mut modes = Vec::new();
+
+        for element in context.descendent_elements() {
+            let is_context = element == context;
+
+            modes.clear();
+            for (selector, mode) in &self.components {
+                let matches = match selector {
+                    None => is_context,
+                    Some(_) if is_context && !include_context => false,
+                    Some(selector) => {
+                        selector.matches_with_scope_and_cache(&element, scope, &mut nth_index_cache)
+                    }
+                };
+                if matches && !modes.contains(&mode) {
+                    modes.push(mode);
+                }
+            }
+
+            for mode in &modes {
+                match mode {
+                    Mode::Element => values.push(ExtractedValue::Element(element)),
+                    Mode::Text => values.extend(
+                        element
+                            .children()
+                            .filter_map(|child| child.value().as_text())
+                            .map(|text| ExtractedValue::Text(text)),
+                    ),
+                    Mode::Attr(name) => values.extend(element.attr(name).map(ExtractedValue::Attr)),
+                }
+            }
+        }
+
+        values
+    }
+}
+
+/// Splits a selector list at its top-level commas.
+fn split_list(css: &str) -> Vec<&str> {
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    let mut components = Vec::new();
+
+    loop {
+        let start = parser.position();
+        let _ = parser.parse_until_before(Delimiter::Comma, |parser| {
+            while parser.next().is_ok() {}
+            Ok::<_, ParseError<'_, ()>>(())
+        });
+        components.push(parser.slice_from(start));
+
+        if parser.next().is_err() {
+            return components;
+        }
+    }
+}
+
+/// Splits a trailing `::text` or `::attr(name)` pseudo-element off a selector.
+fn split_pseudo_element(selector: &str) -> (&str, Mode) {
+    let mut input = ParserInput::new(selector);
+    let mut parser = Parser::new(&mut input);
+
+    // The last three tokens, with the start of the first one.
+    let mut tail: Vec<(usize, Token<'_>, Option<String>)> = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        let start = parser.position().byte_index();
+        let token = match parser.next() {
+            Ok(token) => token.clone(),
+            Err(_) => break,
+        };
+        let argument = match token {
+            Token::Function(_) => parser
+                .parse_nested_block(|parser| {
+                    let argument = parser.expect_ident_or_string()?.to_string();
+                    parser.expect_exhausted()?;
+                    Ok::<_, ParseError<'_, ()>>(argument)
+                })
+                .ok(),
+            _ => None,
+        };
+        tail.push((start, token, argument));
+        if tail.len() > 3 {
+            tail.remove(0);
+        }
+    }
+
+    match tail.as_slice() {
+        [(start, Token::Colon, _), (_, Token::Colon, _), (_, last, argument)] => match last {
+            Token::Ident(name) if name.eq_ignore_ascii_case("text") => {
+                (&selector[..*start], Mode::Text)
+            }
+            Token::Function(name) if name.eq_ignore_ascii_case("attr") => match argument {
+                Some(argument) => (&selector[..*start], Mode::Attr(argument.clone())),
+                None => (selector, Mode::Element),
+            },
+            _ => (selector, Mode::Element),
+        },
+        _ => (selector, Mode::Element),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExtractedValue::{self, Attr, Text};
+    use crate::{Html, Selector};
+
+    const PAGE: &str = r#"
+        <html lang="en">
+        <body>
+            <h1>Quotes <small>to scrape</small></h1>
+            <div class="quote"><span class="text">To be</span> <a href="/author/1">Author 1</a></div>
+            <div class="quote"><span class="text">or not</span> <a href="/author/2">Author 2</a></div>
+            <a class="next" href="/page/2">Next</a>
+            <img src="logo.png" alt="">
+        </body>
+        </html>
+    "#;
+
+    fn strings<'a>(values: &[ExtractedValue<'a>]) -> Vec<&'a str> {
+        values.iter().filter_map(|value| value.as_str()).collect()
+    }
+
+    #[test]
+    fn text() {
+        let html = Html::parse_document(PAGE);
+
+        assert_eq!(html.extract("h1::text").unwrap(), [Text("Quotes ")]);
+        assert_eq!(
+            strings(&html.extract("div.quote span.text::text").unwrap()),
+            ["To be", "or not"]
+        );
+        assert_eq!(html.extract("h1 small::TEXT").unwrap(), [Text("to scrape")]);
+    }
+
+    #[test]
+    fn attr() {
+        let html = Html::parse_document(PAGE);
+
+        assert_eq!(
+            html.extract("a::attr(href)").unwrap(),
+            [Attr("/author/1"), Attr("/author/2"), Attr("/page/2")]
+        );
+        assert_eq!(html.extract("html::attr(lang)").unwrap(), [Attr("en")]);
+        assert_eq!(html.extract("img::attr(alt)").unwrap(), [Attr("")]);
+        assert_eq!(html.extract("h1::attr(id)").unwrap(), []);
+    }
+
+    #[test]
+    fn attr_on_selector_list() {
+        let html = Html::parse_document(PAGE);
+
+        assert_eq!(
+            html.extract("img::attr(src), .quote a::attr(href), a.next::attr(\"href\")")
+                .unwrap(),
+            [
+                Attr("/author/1"),
+                Attr("/author/2"),
+                Attr("/page/2"),
+                Attr("logo.png")
+            ]
+        );
+        assert_eq!(
+            html.extract("a::attr(href), a.next::attr(href), a.next::text")
+                .unwrap(),
+            [
+                Attr("/author/1"),
+                Attr("/author/2"),
+                Attr("/page/2"),
+                Text("Next")
+            ]
+        );
+    }
+
+    #[test]
+    fn elements() {
+        let html = Html::parse_document(PAGE);
+        let selector = Selector::parse("h1, a.next").unwrap();
+        let expected = html
+            .select(&selector)
+            .map(ExtractedValue::Element)
+            .collect::<Vec<_>>();
+
+        assert_eq!(html.extract("h1, a.next").unwrap(), expected);
+    }
+
+    #[test]
+    fn element_ref() {
+        let html = Html::parse_document(PAGE);
+        let quotes = html
+            .select(&Selector::parse(".quote").unwrap())
+            .map(|quote| quote.extract(":scope > a::attr(href), span::text").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            quotes,
+            [
+                [Text("To be"), Attr("/author/1")],
+                [Text("or not"), Attr("/author/2")]
+            ]
+        );
+
+        let small = html.select(&Selector::parse("h1").unwrap()).next().unwrap();
+        assert_eq!(small.extract("::text").unwrap(), [Text("Quotes ")]);
+        assert_eq!(small.extract("h1::text").unwrap(), []);
+    }
+
+    #[test]
+    fn invalid() {
+        let html = Html::parse_document(PAGE);
+
+        assert!(html.extract("a::attr()").is_err());
+        assert!(html.extract("a::bogus").is_err());
+        assert!(html.extract("a >::text").is_err());
+        assert!(html.extract("").is_err());
+    }
+}
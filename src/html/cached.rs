@@ -0,0 +1,191 @@
+//! Memoized selection over a document.
+
+use ahash::{HashMap, HashMapExt};
+
+use crate::error::SelectorParseError;
+use crate::{ElementRef, Html, Selector};
+
+/// Memoizes the elements matching each selector in a document, built by [`Html::cached`] or
+/// [`Html::cached_with_limit`].
+///
+/// Extracting many fields from a page often runs the same selector several times. Since the
+/// document cannot change while it is borrowed, the elements found by the first run can be
+/// returned by every later one without traversing the document again.
+///
+/// Results are keyed by the selector text as given, so `"a,b"` and `"a, b"` are cached
+/// separately. If the number of cached selectors is limited, the least recently used one is
+/// evicted to make room for a new one.
+///
+/// # Example
+///
+/// ```
+/// use scraper::Html;
+///
+/// let html = Html::parse_fragment("<ul><li>a</li><li>b</li></ul><p>c</p>");
+/// let mut cached = html.cached_with_limit(2);
+///
+/// assert_eq!(cached.select("li").unwrap().len(), 2);
+/// assert_eq!(cached.select("p").unwrap()[0].inner_html(), "c");
+/// assert_eq!(cached.select("li").unwrap().len(), 2);
+/// assert_eq!(cached.len(), 2);
+///
+/// // Evicts "p", which was used least recently.
+/// cached.select("ul").unwrap();
+/// assert!(cached.contains("li") && !cached.contains("p"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CachedHtml<'a> {
+    html: &'a Html,
+    entries: HashMap<String, Entry<'a>>,
+    max_entries: usize,
+    clock: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<'a> {
+    elements: Vec<ElementRef<'a>>,
+    last_used: u64,
+}
+
+impl<'a> CachedHtml<'a> {
+    pub(crate) fn new(html: &'a Html, max_entries: usize) -> Self {
+        Self {
+            html,
+            entries: HashMap::new(),
+            max_entries: max_entries.max(1),
+            clock: 0,
+        }
+    }
+
+    /// Returns the document whose selections are cached.
+    pub fn html(&self) -> &'a Html {
+        self.html
+    }
+
+    /// Returns the elements matching `css` in document order, selecting them only if the
+    /// selector is not cached yet.
+    ///
+    /// Invalid selectors are not cached, so every call for one parses it again.
+    pub fn select(&mut self, css: &str) -> Result<&[ElementRef<'a>], SelectorParseError> {
+        self.clock += 1;
+
+        if !self.entries.contains_key(css) {
+            let selector = Selector::parse(css)?;
+            let elements = self.html.select(&selector).collect();
+
+            if self.entries.len() >= self.max_entries {
+                self.evict();
+            }
+            self.entries.insert(
+                css.to_owned(),
+                Entry {
+                    elements,
+                    last_used: 0,
+                },
+            );
+        }
+
+        let entry = self.entries.get_mut(css).unwrap();
+        entry.last_used = self.clock;
+        Ok(&entry.elements)
+    }
+
+    /// Returns true if the elements matching `css` are cached.
+    pub fn contains(&self, css: &str) -> bool {
+        self.entries.contains_key(css)
+    }
+
+    /// Returns the number of cached selectors.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no selectors are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the maximum number of cached selectors.
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Removes all cached selections.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn evict(&mut self) {
+        let least_recently_used = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(css, _)| css.clone());
+        if let Some(css) = least_recently_used {
+            self.entries.remove(&css);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Html, Selector};
+
+    #[test]
+    fn select_matches_uncached() {
+        let html = Html::parse_document(
+            r#"<div class="a"><p>1</p><p class="b">2</p></div><p class="b">3</p>"#,
+        );
+        let mut cached = html.cached();
+
+        for css in ["p", ".b", "div > p", "p, div", "table"] {
+            let expected = html
+                .select(&Selector::parse(css).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(cached.select(css).unwrap(), expected, "{css}");
+            assert_eq!(cached.select(css).unwrap(), expected, "{css}");
+        }
+        assert_eq!(cached.len(), 5);
+        assert_eq!(cached.max_entries(), usize::MAX);
+
+        cached.clear();
+        assert!(cached.is_empty());
+        assert_eq!(cached.select("p").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let html = Html::parse_fragment("<a></a><b></b><i></i>");
+        let mut cached = html.cached_with_limit(2);
+
+        cached.select("a").unwrap();
+        cached.select("b").unwrap();
+        cached.select("a").unwrap();
+        cached.select("i").unwrap();
+        assert!(cached.contains("a") && !cached.contains("b") && cached.contains("i"));
+
+        cached.select("b").unwrap();
+        assert!(!cached.contains("a") && cached.contains("b") && cached.contains("i"));
+        assert_eq!(cached.len(), 2);
+    }
+
+    #[test]
+    fn zero_limit_keeps_latest() {
+        let html = Html::parse_fragment("<a></a><b></b>");
+        let mut cached = html.cached_with_limit(0);
+
+        assert_eq!(cached.select("a").unwrap().len(), 1);
+        assert_eq!(cached.select("b").unwrap().len(), 1);
+        assert!(!cached.contains("a") && cached.contains("b"));
+        assert_eq!(cached.max_entries(), 1);
+    }
+
+    #[test]
+    fn invalid_not_cached() {
+        let html = Html::parse_fragment("<a></a>");
+        let mut cached = html.cached();
+
+        assert_eq!(cached.select("a >").unwrap_err().input(), "a >");
+        assert!(cached.is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use std::iter::once;
+
+use ego_tree::NodeId;
+
+use super::hooked_sink::ParseHook;
+use super::Html;
+use crate::node::Element;
+
+/// Limits how deeply the nodes of a document parsed by
+/// [`Html::fuzz_safe_parse`](super::Html::fuzz_safe_parse) are nested.
+///
+/// Like Chromium's parser, nodes which would be nested deeper than the limit are appended to the
+/// ancestor of their parent at the limit instead, so they become siblings of the deepest nodes.
+pub(crate) struct DepthLimit {
+    /// The greatest depth of a node, where the document is at depth 0.
+    pub(crate) max_depth: usize,
+}
+
+impl ParseHook for DepthLimit {
+    fn element_created(&mut self, _id: NodeId, _element: &mut Element) {}
+
+    fn append_target(&mut self, html: &Html, parent: NodeId) -> NodeId {
+        let node = html.tree.get(parent).unwrap();
+        let depth = node.ancestors().count();
+        if depth < self.max_depth {
+            return parent;
+        }
+        // Children of the ancestor at `max_depth - 1` are at `max_depth`.
+        once(node)
+            .chain(node.ancestors())
+            .nth(depth + 1 - self.max_depth)
+            .unwrap()
+            .id()
+    }
+}
@@ -0,0 +1,290 @@
+use std::mem;
+
+use ego_tree::NodeId;
+use html5ever::buffer_queue::BufferQueue;
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::{Tag, Token, TokenSink, TokenSinkResult, Tokenizer};
+
+use super::hooked_sink::ParseHook;
+use super::source_location::SourceLocator;
+use super::Html;
+use crate::node::Element;
+
+/// An attribute repeated in the start tag of an element, as reported by
+/// [`Html::duplicate_attributes`].
+///
+/// Like browsers, the parser keeps the first occurrence of an attribute and discards the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateAttr {
+    /// The element whose start tag repeats the attribute.
+    pub element: NodeId,
+    /// The name of the attribute, lowercased like by the parser.
+    pub name: String,
+    /// The value of the first occurrence, which the element keeps.
+    pub kept: String,
+    /// The values of the later occurrences, which were discarded, in source order.
+    pub discarded: Vec<String>,
+}
+
+/// Records the attributes repeated in start tags.
+///
+/// The tokenizer drops repeated attributes before the tree builder sees them, only reporting a
+/// parse error. The start tag following such an error is located like by [`SourceLocator`] and
+/// its attributes are scanned again to recover the discarded values.
+pub(crate) struct DuplicateFinder<'s> {
+    locator: SourceLocator<'s>,
+    /// Whether a duplicate attribute was reported since the last start tag.
+    pending: bool,
+    /// The byte offsets of the start tags repeating attributes, in ascending order.
+    repeating: Vec<usize>,
+}
+
+impl<'s> DuplicateFinder<'s> {
+    pub(crate) fn new(source: &'s str) -> Self {
+        Self {
+            locator: SourceLocator::new(source),
+            pending: false,
+            repeating: Vec::new(),
+        }
+    }
+}
+
+impl ParseHook for DuplicateFinder<'_> {
+    fn start_tag(&mut self, offset: usize) {
+        self.locator.start_tag(offset);
+        if mem::take(&mut self.pending) {
+            self.repeating.push(offset);
+        }
+    }
+
+    fn element_created(&mut self, id: NodeId, element: &mut Element) {
+        self.locator.element_created(id, element);
+    }
+
+    fn parse_error(&mut self, msg: &str) {
+        if msg == "Duplicate attribute" {
+            self.pending = true;
+        }
+    }
+
+    fn finish(&mut self, html: &mut Html) {
+        html.duplicate_attributes = self
+            .locator
+            .located()
+            .iter()
+            .filter(|(_, start)| self.repeating.binary_search(start).is_ok())
+            .flat_map(|&(id, start)| duplicates(id, &self.locator.source()[start..]))
+            .collect();
+    }
+}
+
+/// Returns the attributes repeated in the start tag at the beginning of `tag`.
+fn duplicates(element: NodeId, tag: &str) -> Vec<DuplicateAttr> {
+    let attributes = scan_attributes(tag);
+    let mut names = Vec::new();
+    for (name, _) in &attributes {
+        if !names.contains(name) && attributes.iter().filter(|(n, _)| n == name).count() > 1 {
+            names.push(name.clone());
+        }
+    }
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    // Let the tokenizer decode the values, renaming the occurrences so none is dropped.
+    let repeated = attributes
+        .iter()
+        .filter(|(name, _)| names.contains(name))
+        .collect::<Vec<_>>();
+    let mut synthetic = String::from("<x");
+    for (i, (_, value)) in repeated.iter().enumerate() {
+        synthetic.push_str(&format!(" a{i}"));
+        if let Some(value) = value {
+            synthetic.push('=');
+            synthetic.push_str(value);
+        }
+    }
+    synthetic.push('>');
+    let values = decode_attributes(&synthetic);
+
+    names
+        .into_iter()
+        .map(|name| {
+            let mut values = repeated
+                .iter()
+                .zip(&values)
+                .filter(|((n, _), _)| *n == name)
+                .map(|(_, value)| value.clone());
+            DuplicateAttr {
+                element,
+                kept: values.next().unwrap_or_default(),
+                discarded: values.collect(),
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Returns the lowercased names and the source text of the values, including any quotes, of
+/// the attributes of the start tag at the beginning of `tag`, following the tokenizer's rules.
+fn scan_attributes(tag: &str) -> Vec<(String, Option<&str>)> {
+    let bytes = tag.as_bytes();
+    let is_space = |i: usize| matches!(bytes.get(i), Some(b'\t' | b'\n' | b'\x0c' | b'\r' | b' '));
+    let skip_spaces = |mut i: usize| {
+        while is_space(i) {
+            i += 1;
+        }
+        i
+    };
+    let ends_name = |i: usize| is_space(i) || matches!(bytes.get(i), None | Some(b'/' | b'>'));
+
+    let mut attributes = Vec::new();
+    let mut i = 1;
+    while !ends_name(i) {
+        i += 1;
+    }
+
+    loop {
+        while is_space(i) || bytes.get(i) == Some(&b'/') {
+            i += 1;
+        }
+        if matches!(bytes.get(i), None | Some(b'>')) {
+            return attributes;
+        }
+
+        // An equals sign starting the name is part of it.
+        let name_start = i;
+        i += 1;
+        while !ends_name(i) && bytes[i] != b'=' {
+            i += 1;
+        }
+        let name = tag[name_start..i].to_ascii_lowercase();
+
+        let after_name = skip_spaces(i);
+        if bytes.get(after_name) != Some(&b'=') {
+            attributes.push((name, None));
+            i = after_name;
+            continue;
+        }
+
+        let value_start = skip_spaces(after_name + 1);
+        i = match bytes.get(value_start) {
+            Some(&quote @ (b'"' | b'\'')) => bytes[value_start + 1..]
+                .iter()
+                .position(|&byte| byte == quote)
+                .map_or(bytes.len(), |end| value_start + end + 2),
+            _ => {
+                let mut end = value_start;
+                while !is_space(end) && !matches!(bytes.get(end), None | Some(b'>')) {
+                    end += 1;
+                }
+                end
+            }
+        };
+        attributes.push((name, Some(&tag[value_start..i])));
+    }
+}
+
+/// Returns the decoded values of the attributes of the start tag `tag`, in order.
+fn decode_attributes(tag: &str) -> Vec<String> {
+    struct FirstTag(Option<Tag>);
+
+    impl TokenSink for FirstTag {
+        type Handle = ();
+
+        fn process_token(&mut self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+            if let Token::TagToken(tag) = token {
+                self.0.get_or_insert(tag);
+            }
+            TokenSinkResult::Continue
+        }
+    }
+
+    let mut tokenizer = Tokenizer::new(FirstTag(None), Default::default());
+    let mut input = BufferQueue::default();
+    input.push_back(StrTendril::from(tag));
+    let _ = tokenizer.feed(&mut input);
+    tokenizer.end();
+
+    tokenizer
+        .sink
+        .0
+        .map(|tag| tag.attrs.into_iter().map(|a| a.value.into()).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scan_attributes, DuplicateAttr};
+    use crate::{Html, Selector};
+
+    #[test]
+    fn three_occurrences() {
+        let html = Html::parse_document_with_duplicate_attributes(
+            "<p>\n<a class=x href=\"/a\"\n href='/b?x=1&amp;y=2' class=y href=/c title>a</a></p></a x=1 x=2><i id=i id=j></i>",
+        );
+        let a = html.select(&Selector::parse("a").unwrap()).next().unwrap();
+        let i = html.select(&Selector::parse("i").unwrap()).next().unwrap();
+        assert_eq!(a.attr("href"), Some("/a"));
+        assert_eq!(a.attr("class"), Some("x"));
+
+        assert_eq!(
+            html.duplicate_attributes(),
+            [
+                DuplicateAttr {
+                    element: a.id(),
+                    name: "class".to_owned(),
+                    kept: "x".to_owned(),
+                    discarded: vec!["y".to_owned()],
+                },
+                DuplicateAttr {
+                    element: a.id(),
+                    name: "href".to_owned(),
+                    kept: "/a".to_owned(),
+                    discarded: vec!["/b?x=1&y=2".to_owned(), "/c".to_owned()],
+                },
+                DuplicateAttr {
+                    element: i.id(),
+                    name: "id".to_owned(),
+                    kept: "i".to_owned(),
+                    discarded: vec!["j".to_owned()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn not_recorded_by_default() {
+        let src = "<a href=1 href=2></a>";
+        assert!(Html::parse_document(src).duplicate_attributes().is_empty());
+        assert_eq!(
+            Html::parse_fragment_with_duplicate_attributes(src)
+                .duplicate_attributes()
+                .len(),
+            1
+        );
+        assert!(
+            Html::parse_fragment_with_duplicate_attributes("<a href=1 rel=2>")
+                .duplicate_attributes()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn scan() {
+        assert_eq!(
+            scan_attributes(r#"<a b c = "d e" =f/g='h' i=j>k=l"#),
+            [
+                ("b".to_owned(), None),
+                ("c".to_owned(), Some(r#""d e""#)),
+                ("=f".to_owned(), None),
+                ("g".to_owned(), Some("'h'")),
+                ("i".to_owned(), Some("j")),
+            ]
+        );
+        assert_eq!(
+            scan_attributes("<a B='unterminated"),
+            [("b".to_owned(), Some("'unterminated"))]
+        );
+    }
+}
@@ -1,64 +1,545 @@
+//! HTML forms.
+
+use std::error::Error;
+use std::fmt;
+use std::iter::once;
 use std::ops::Deref;
 
 use ahash::{HashMap, HashMapExt};
+use ego_tree::NodeId;
 
 use crate::{ElementRef, Selector};
 
+/// A `<form>` element together with the values of its controls.
+///
+/// Controls are associated with the form either by being its descendants or by naming the form's
+/// ID in their `form` attribute.
+///
+/// The values start out as parsed from the document and can be changed with [`Form::set`]; the
+/// document itself is never modified. [`Form::reset`] restores the parsed values.
+#[derive(Debug, Clone)]
 pub struct Form<'a> {
     root: ElementRef<'a>,
     form_element: ElementRef<'a>,
-    values: HashMap<ElementRef<'a>, String>,
+    values: HashMap<NodeId, Option<String>>,
+    defaults: HashMap<NodeId, Option<String>>,
 }
 
 impl<'a> Form<'a> {
     pub(crate) fn wrap(root: ElementRef<'a>, form_element: ElementRef<'a>) -> Form<'a> {
         let mut map = HashMap::new();
 
-        for input in root.select(&Selector::parse("button, fieldset, input, keygen, object, output, select, textarea").unwrap())
-            .filter(|element| element.is_child_of(&form_element) || belongs_to_form(element, &form_element))
+        for input in root
+            .select(
+                &Selector::parse(
+                    "button, fieldset, input, keygen, object, output, select, textarea",
+                )
+                .unwrap(),
+            )
+            .filter(|element| {
+                element.is_child_of(&form_element) || belongs_to_form(element, &form_element)
+            })
         {
-            map.insert(input, get_value(input))
+            map.insert(input.id(), get_value(input));
         }
 
         Form {
             root,
             form_element,
+            defaults: map.clone(),
             values: map,
         }
     }
 
-    pub(crate) fn inputs(&self) -> Vec<ElementRef> {
-        self.root.select(&Selector::parse("button, fieldset, input, keygen, object, output, select, textarea").unwrap())
-            .filter(|element| element.is_child_of(&self.form_element) || belongs_to_form(element, &self.form_element))
+    /// Returns the controls associated with this form, in document order.
+    pub fn inputs(&self) -> Vec<ElementRef<'a>> {
+        self.root
+            .select(
+                &Selector::parse(
+                    "button, fieldset, input, keygen, object, output, select, textarea",
+                )
+                .unwrap(),
+            )
+            .filter(|element| {
+                element.is_child_of(&self.form_element)
+                    || belongs_to_form(element, &self.form_element)
+            })
             .collect()
     }
+
+    /// Returns the current value of a control associated with this form.
+    pub fn value(&self, control: &ElementRef) -> Option<&str> {
+        self.values.get(&control.id())?.as_deref()
+    }
+
+    /// Sets the current value of a control associated with this form.
+    ///
+    /// Passing `None` clears the value, e.g. unchecks a checkbox. Returns false, without changing
+    /// anything, if the control is not associated with this form.
+    pub fn set(&mut self, control: &ElementRef, value: Option<&str>) -> bool {
+        match self.values.get_mut(&control.id()) {
+            Some(current) => {
+                *current = value.map(str::to_owned);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Restores every control's value to the one parsed from the document, like a form reset.
+    pub fn reset(&mut self) {
+        self.values.clone_from(&self.defaults);
+    }
+
+    /// Checks the current values against the constraints declared on the controls, like a
+    /// browser does before submitting the form.
+    ///
+    /// The `required`, `minlength`, `maxlength`, `min` and `max` attributes and the formats of
+    /// `email` and `url` inputs are checked. The `pattern` attribute is only checked if the
+    /// `pattern` feature is enabled. Disabled and read-only controls are not validated.
+    ///
+    /// Returns one error per violated constraint, in document order.
+    pub fn validate(&self) -> Vec<ValidationError<'a>> {
+        let mut errors = Vec::new();
+        let inputs = self.inputs();
+
+        // Whether each radio button group has a checked button, removed once the group has been
+        // validated so that a missing value is reported only for its first required button.
+        let mut radio_groups = HashMap::new();
+        for other in &inputs {
+            if input_type(other).as_deref() == Some("radio") {
+                *radio_groups.entry(other.attr("name")).or_insert(false) |=
+                    self.value(other).is_some();
+            }
+        }
+
+        for control in inputs {
+            if !is_validated(&control) {
+                continue;
+            }
+
+            let value = self.value(&control).unwrap_or("");
+            let mut error = |kind| errors.push(ValidationError { control, kind });
+
+            if control.has_bool_attr("required") {
+                let missing = match input_type(&control).as_deref() {
+                    Some("radio") if control.value().name_is(&local_name!("input")) => radio_groups
+                        .remove(&control.attr("name"))
+                        .is_some_and(|checked| !checked),
+                    Some("checkbox") => self.value(&control).is_none(),
+                    _ => value.is_empty(),
+                };
+                if missing {
+                    error(ValidationErrorKind::ValueMissing);
+                }
+            }
+
+            if value.is_empty() {
+                continue;
+            }
+
+            let input_type = match *control.value().local_name() {
+                local_name!("input") => input_type(&control).unwrap_or_else(|| "text".to_owned()),
+                local_name!("textarea") => "textarea".to_owned(),
+                _ => continue,
+            };
+            let input_type = input_type.as_str();
+
+            if matches!(
+                input_type,
+                "text" | "search" | "tel" | "url" | "email" | "password" | "textarea" | ""
+            ) {
+                let length = value.encode_utf16().count();
+                if let Some(max) = parse_attr::<usize>(&control, "maxlength") {
+                    if length > max {
+                        error(ValidationErrorKind::TooLong { max });
+                    }
+                }
+                if let Some(min) = parse_attr::<usize>(&control, "minlength") {
+                    if length < min {
+                        error(ValidationErrorKind::TooShort { min });
+                    }
+                }
+            }
+
+            match input_type {
+                "email" => {
+                    let valid = if control.has_bool_attr("multiple") {
+                        value.split(',').all(|email| is_valid_email(email.trim()))
+                    } else {
+                        is_valid_email(value)
+                    };
+                    if !valid {
+                        error(ValidationErrorKind::TypeMismatch);
+                    }
+                }
+                "url" if !is_valid_url(value) => error(ValidationErrorKind::TypeMismatch),
+                "number" | "range" => match value.parse::<f64>() {
+                    Ok(number) if number.is_finite() => {
+                        if let Some(min) = parse_attr::<f64>(&control, "min") {
+                            if number < min {
+                                error(ValidationErrorKind::RangeUnderflow {
+                                    min: control.attr("min").unwrap().to_owned(),
+                                });
+                            }
+                        }
+                        if let Some(max) = parse_attr::<f64>(&control, "max") {
+                            if number > max {
+                                error(ValidationErrorKind::RangeOverflow {
+                                    max: control.attr("max").unwrap().to_owned(),
+                                });
+                            }
+                        }
+                    }
+                    _ => error(ValidationErrorKind::TypeMismatch),
+                },
+                // The values of these types have a fixed-width format, so they can be compared
+                // as strings.
+                "date" | "month" | "week" | "time" | "datetime-local" => {
+                    if let Some(min) = control.attr("min").filter(|min| !min.is_empty()) {
+                        if value < min {
+                            error(ValidationErrorKind::RangeUnderflow {
+                                min: min.to_owned(),
+                            });
+                        }
+                    }
+                    if let Some(max) = control.attr("max").filter(|max| !max.is_empty()) {
+                        if value > max {
+                            error(ValidationErrorKind::RangeOverflow {
+                                max: max.to_owned(),
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            #[cfg(feature = "pattern")]
+            if matches!(
+                input_type,
+                "text" | "search" | "tel" | "url" | "email" | "password" | ""
+            ) {
+                if let Some(pattern) = control.attr("pattern") {
+                    // Like browsers, ignore patterns which fail to compile.
+                    if let Ok(regex) = regex::Regex::new(&format!("^(?:{pattern})$")) {
+                        if !regex.is_match(value) {
+                            error(ValidationErrorKind::PatternMismatch {
+                                pattern: pattern.to_owned(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Returns the current values of the named controls as a JSON object mapping each name to
+    /// its value, e.g. for logging the state of the form or submitting it to an API as JSON.
+    ///
+    /// Like when submitting the form, disabled controls, buttons and controls without a value,
+    /// like unchecked checkboxes, are left out. The values of a `<select multiple>` and of a
+    /// group of checkboxes sharing a name are arrays, even if fewer than two are selected; so
+    /// are the values of other names shared by several controls with values.
+    ///
+    /// The options of a `<select>` are selected like by a browser, e.g. a drop-down without a
+    /// `selected` option has its first option selected, and an option without a `value` has its
+    /// text as value. A `<select multiple>` whose value was changed with [`Form::set`] has that
+    /// single value selected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    /// use serde_json::json;
+    ///
+    /// let html = Html::parse_document(
+    ///     r#"<form>
+    ///         <input name="user" value="alice">
+    ///         <input type="checkbox" name="tags" value="a" checked>
+    ///         <input type="checkbox" name="tags" value="b">
+    ///         <select name="langs" multiple>
+    ///             <option value="en" selected>English</option>
+    ///             <option value="fr" selected>French</option>
+    ///         </select>
+    ///     </form>"#,
+    /// );
+    /// let forms = html.forms();
+    ///
+    /// assert_eq!(
+    ///     forms[0].to_json(),
+    ///     json!({"user": "alice", "tags": ["a"], "langs": ["en", "fr"]})
+    /// );
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn to_json(&self) -> serde_json::Value {
+        let controls = self
+            .inputs()
+            .into_iter()
+            .filter(|control| !control.has_bool_attr("disabled"))
+            .filter_map(|control| Some((control.attr("name")?, control)))
+            .collect::<Vec<_>>();
+        let is_checkbox = |control: &ElementRef| {
+            control.value().name_is(&local_name!("input"))
+                && input_type(control).as_deref() == Some("checkbox")
+        };
+        let mut checkboxes = HashMap::<&str, usize>::new();
+        for (name, control) in &controls {
+            if is_checkbox(control) {
+                *checkboxes.entry(name).or_default() += 1;
+            }
+        }
+
+        let mut fields = indexmap::IndexMap::<&str, (Vec<String>, bool)>::new();
+        for (name, control) in &controls {
+            let multiple_select = control.value().name_is(&local_name!("select"))
+                && control.has_bool_attr("multiple");
+            let values: Vec<_> = match self.value(control) {
+                Some(_)
+                    if multiple_select
+                        && self.defaults.get(&control.id()) == self.values.get(&control.id()) =>
+                {
+                    selected_options(*control)
+                        .into_iter()
+                        .map(option_value)
+                        .collect()
+                }
+                value => value.map(str::to_owned).into_iter().collect(),
+            };
+            let checkbox_group = is_checkbox(control) && checkboxes[name] > 1;
+
+            let (field, array) = fields.entry(name).or_default();
+            field.extend(values);
+            *array |= multiple_select || checkbox_group;
+        }
+
+        let fields = fields
+            .into_iter()
+            .filter_map(|(name, (values, array))| {
+                let value = match values.as_slice() {
+                    [] if !array => return None,
+                    [value] if !array => serde_json::Value::from(value.as_str()),
+                    values => serde_json::Value::from(values.to_vec()),
+                };
+                Some((name.to_owned(), value))
+            })
+            .collect();
+        serde_json::Value::Object(fields)
+    }
 }
 
-fn get_value(element: ElementRef) -> Option<String> {
-    match element.value().name() {
-        "input" => {
-            match element.attr("type") {
-                None => None,
-                Some("checkbox") | Some("radio") => element.attr("checked").map(str::to_string),
-                Some("color") | Some("date") | Some("datetime-local") | Some("email") | Some("hidden") | Some("month") | Some("number") | Some("password") | Some("range") | Some("")=> element.attr("value").map(str::to_string),
-                Some(_) => None,
+/// A constraint violated by the value of a form control, as returned by [`Form::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError<'a> {
+    control: ElementRef<'a>,
+    kind: ValidationErrorKind,
+}
+
+impl<'a> ValidationError<'a> {
+    /// Returns the control whose value is invalid.
+    pub fn control(&self) -> ElementRef<'a> {
+        self.control
+    }
+
+    /// Returns the `name` attribute of the control whose value is invalid.
+    pub fn name(&self) -> Option<&'a str> {
+        self.control.attr("name")
+    }
+
+    /// Returns the violated constraint.
+    pub fn kind(&self) -> &ValidationErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ValidationError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name}: {}", self.kind),
+            None => write!(f, "<{}>: {}", self.control.value().name(), self.kind),
+        }
+    }
+}
+
+impl Error for ValidationError<'_> {}
+
+/// The constraints checked by [`Form::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationErrorKind {
+    /// A `required` control has no value.
+    ValueMissing,
+    /// The value is not a valid e-mail address, URL or number, as required by the input type.
+    TypeMismatch,
+    /// The value does not match the `pattern` attribute.
+    PatternMismatch {
+        /// The pattern.
+        pattern: String,
+    },
+    /// The value is longer than the `maxlength` attribute.
+    TooLong {
+        /// The maximum length, in UTF-16 code units.
+        max: usize,
+    },
+    /// The value is shorter than the `minlength` attribute.
+    TooShort {
+        /// The minimum length, in UTF-16 code units.
+        min: usize,
+    },
+    /// The value is less than the `min` attribute.
+    RangeUnderflow {
+        /// The minimum.
+        min: String,
+    },
+    /// The value is greater than the `max` attribute.
+    RangeOverflow {
+        /// The maximum.
+        max: String,
+    },
+}
+
+impl fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ValueMissing => write!(f, "value is required"),
+            Self::TypeMismatch => write!(f, "value does not match the input type"),
+            Self::PatternMismatch { pattern } => {
+                write!(f, "value does not match the pattern {pattern:?}")
             }
+            Self::TooLong { max } => write!(f, "value is longer than {max} characters"),
+            Self::TooShort { min } => write!(f, "value is shorter than {min} characters"),
+            Self::RangeUnderflow { min } => write!(f, "value is less than {min}"),
+            Self::RangeOverflow { max } => write!(f, "value is greater than {max}"),
         }
+    }
+}
+
+fn is_validated(control: &ElementRef) -> bool {
+    let validated_element = match *control.value().local_name() {
+        local_name!("input") => !matches!(
+            input_type(control).as_deref(),
+            Some("hidden" | "submit" | "reset" | "button" | "image")
+        ),
+        local_name!("select") | local_name!("textarea") => true,
+        _ => false,
+    };
+    validated_element && !control.has_bool_attr("disabled") && !control.has_bool_attr("readonly")
+}
+
+/// Returns the `type` attribute of an `<input>`, which is ASCII case-insensitive, in lowercase.
+fn input_type(control: &ElementRef) -> Option<String> {
+    control.attr("type").map(str::to_ascii_lowercase)
+}
+
+fn parse_attr<T: std::str::FromStr>(control: &ElementRef, attr: &str) -> Option<T> {
+    control.attr(attr)?.trim().parse().ok()
+}
+
+/// Checks the "valid e-mail address" syntax of the HTML standard.
+fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+
+    let valid_local = !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c));
+    let valid_domain = domain.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+
+    valid_local && valid_domain
+}
+
+/// Checks that `url` is an absolute URL, i.e. starts with a scheme, and contains no whitespace.
+fn is_valid_url(url: &str) -> bool {
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return false;
+    };
+
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        && !rest.is_empty()
+        && !url.chars().any(char::is_whitespace)
+}
+
+fn get_value(element: ElementRef) -> Option<String> {
+    match *element.value().local_name() {
+        local_name!("input") => match input_type(&element).as_deref() {
+            Some("checkbox") | Some("radio") => element
+                .has_bool_attr("checked")
+                .then(|| element.attr("value").unwrap_or("on").to_string()),
+            // Buttons are only submitted when used to submit the form, and files are not read.
+            Some("submit" | "reset" | "button" | "image" | "file") => None,
+            // Like browsers, treat unknown types as text and submit a missing value as empty.
+            _ => Some(element.attr("value").unwrap_or("").to_string()),
+        },
         // "button" => element.attr("value").unwrap_or("").to_string(),
-        "select" => find_selected_child(element).map(str::to_string),
-        "datalist" => find_selected_child(element).map(str::to_string),
-        "textarea" => Some(element.inner_html()),
-        _ => unimplemented!("Tag not known"),
+        local_name!("select") => selected_options(element).first().map(|&o| option_value(o)),
+        local_name!("textarea") => Some(element.text().collect()),
+        _ => None,
     }
 }
 
-fn find_selected_child(element: ElementRef) -> Option<&str> {
-    for child in element.child_elements() {
-        if child.attr("selected").is_some() {
-            return child.attr("value");
-        }
+/// Returns the options of a `<select>`, including those in its `<optgroup>`s, in tree order.
+fn options(select: ElementRef<'_>) -> impl Iterator<Item = ElementRef<'_>> {
+    select
+        .child_elements()
+        .flat_map(|child| {
+            let group = child.value().name_is(&local_name!("optgroup"));
+            once(child).chain(child.child_elements().filter(move |_| group))
+        })
+        .filter(|element| element.value().name_is(&local_name!("option")))
+}
+
+/// Returns the value of an `<option>`, which is its `value` attribute or else its text with
+/// ASCII whitespace stripped and collapsed.
+fn option_value(option: ElementRef<'_>) -> String {
+    match option.attr("value") {
+        Some(value) => value.to_owned(),
+        None => option
+            .text()
+            .collect::<String>()
+            .split_ascii_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
     }
-    None
+}
+
+/// Returns the selected options of a `<select>`, following the selectedness rules of HTML.
+///
+/// Without `multiple`, only the last option marked `selected` is selected. If there is none and
+/// the select shows a single row, as a drop-down does, its first enabled option is selected.
+fn selected_options(select: ElementRef<'_>) -> Vec<ElementRef<'_>> {
+    let options = options(select).collect::<Vec<_>>();
+    if select.has_bool_attr("multiple") {
+        return options
+            .into_iter()
+            .filter(|option| option.has_bool_attr("selected"))
+            .collect();
+    }
+
+    let rows = parse_attr::<usize>(&select, "size")
+        .filter(|&size| size > 0)
+        .unwrap_or(1);
+    let selected = options
+        .iter()
+        .rev()
+        .find(|option| option.has_bool_attr("selected"))
+        .or_else(|| {
+            options
+                .iter()
+                .find(|option| rows == 1 && !option.has_bool_attr("disabled"))
+        });
+    selected.into_iter().copied().collect()
 }
 
 fn get_ids<'a>(element: &'a ElementRef, form: &'a ElementRef) -> Option<(&'a str, &'a str)> {
@@ -84,8 +565,9 @@ impl<'a> Deref for Form<'a> {
 
 #[cfg(test)]
 mod test {
-    use crate::{Html, Selector};
+    use super::ValidationErrorKind;
     use crate::html::form::belongs_to_form;
+    use crate::{Html, Selector};
 
     #[test]
     fn abc() {
@@ -104,7 +586,7 @@ mod test {
         let i = vec.len();
         assert_eq!(i, 1, "expected to see one form");
 
-        let form = vec.get(0).unwrap();
+        let form = vec.first().unwrap();
         for input in form.inputs() {
             println!("{:?}", input);
         }
@@ -112,7 +594,6 @@ mod test {
         // println!("{:?}",form.inputs());
     }
 
-
     #[test]
     fn test_belongs_to_form() {
         let html = r#"
@@ -130,7 +611,7 @@ mod test {
 
         let html = Html::parse_document(html);
         let forms = html.forms();
-        let form = forms.get(0).unwrap();
+        let form = forms.first().unwrap();
         let input_sel = Selector::parse("input").unwrap();
         let input = html.select(&input_sel).next().unwrap();
         let button_sel = Selector::parse("button").unwrap();
@@ -147,4 +628,309 @@ mod test {
         assert!(form_inputs.contains(&input));
         assert!(form_inputs.contains(&select));
     }
+
+    #[test]
+    fn test_set_and_reset() {
+        let html = r#"
+            <form id="f">
+                <input name="user" value="alice">
+                <input type="email" name="mail">
+                <textarea name="bio">hello</textarea>
+                <button>Send</button>
+            </form>
+            <input name="outside" value="x">
+        "#;
+        let html = Html::parse_document(html);
+        let mut forms = html.forms();
+        let form = forms.first_mut().unwrap();
+
+        let select = |selector: &str| {
+            html.select(&Selector::parse(selector).unwrap())
+                .next()
+                .unwrap()
+        };
+        let user = select("input[name=user]");
+        let mail = select("input[name=mail]");
+        let bio = select("textarea");
+        let outside = select("input[name=outside]");
+
+        assert_eq!(form.value(&user), Some("alice"));
+        assert_eq!(form.value(&mail), Some(""));
+        assert_eq!(form.value(&bio), Some("hello"));
+
+        assert!(form.set(&user, Some("bob")));
+        assert!(form.set(&mail, Some("bob@example.com")));
+        assert!(form.set(&bio, None));
+        assert!(!form.set(&outside, Some("y")));
+
+        assert_eq!(form.value(&user), Some("bob"));
+        assert_eq!(form.value(&mail), Some("bob@example.com"));
+        assert_eq!(form.value(&bio), None);
+        assert_eq!(form.value(&outside), None);
+
+        form.reset();
+
+        assert_eq!(form.value(&user), Some("alice"));
+        assert_eq!(form.value(&mail), Some(""));
+        assert_eq!(form.value(&bio), Some("hello"));
+    }
+
+    #[test]
+    fn test_bool_attrs() {
+        let html = Html::parse_document(
+            r#"
+            <form>
+                <input type="checkbox" name="a" value="yes" checked>
+                <input type="checkbox" name="b" checked="">
+                <input type="checkbox" name="c" checked="false">
+                <input type="checkbox" name="d">
+                <input type="radio" name="e" value="x" checked="checked">
+                <select name="f"><option value="1">1</option><option value="2" selected>2</option></select>
+                <input name="g" required disabled="false">
+            </form>
+            "#,
+        );
+        let forms = html.forms();
+        let form = &forms[0];
+        let values = form
+            .inputs()
+            .iter()
+            .map(|control| form.value(control))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                Some("yes"),
+                Some("on"),
+                Some("on"),
+                None,
+                Some("x"),
+                Some("2"),
+                Some("")
+            ]
+        );
+        assert!(form.validate().is_empty());
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_to_json() {
+        let html = Html::parse_document(
+            r#"
+            <form>
+                <input name="q" value="rust">
+                <input name="q" value="html">
+                <input name="empty">
+                <input type="Text" name="upper" value="x">
+                <textarea name="t">a&amp;b</textarea>
+                <input name="off" value="x" disabled>
+                <input type="checkbox" name="agree" value="yes">
+                <input type="checkbox" name="opts" value="a">
+                <input type="checkbox" name="opts" value="b">
+                <select name="s" multiple><option value="1" selected>1</option><option value="2">2</option></select>
+                <input type="submit" name="go" value="Go">
+            </form>
+            "#,
+        );
+        let mut forms = html.forms();
+        let form = &mut forms[0];
+        assert_eq!(
+            form.to_json(),
+            serde_json::json!({
+                "q": ["rust", "html"],
+                "empty": "",
+                "upper": "x",
+                "t": "a&b",
+                "opts": [],
+                "s": ["1"]
+            })
+        );
+
+        let select = html
+            .select(&Selector::parse("select").unwrap())
+            .next()
+            .unwrap();
+        form.set(&select, Some("2"));
+        assert_eq!(form.to_json()["s"], serde_json::json!(["2"]));
+        form.set(&select, None);
+        assert_eq!(form.to_json()["s"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_select_values() {
+        let html = Html::parse_document(
+            r#"
+            <form>
+                <select name="a"><option>First</option><option> Second
+                    option </option></select>
+                <select name="b"><option disabled>-</option><optgroup label="g"><option value="1">1</option></optgroup></select>
+                <select name="c"><option selected>x</option><optgroup><option selected>y</option></optgroup></select>
+                <select name="d" size="3"><option>x</option></select>
+                <select name="e" multiple><option selected>x</option><optgroup><option selected> y </option></optgroup><option>z</option></select>
+                <select name="f"></select>
+            </form>
+            "#,
+        );
+        let forms = html.forms();
+        let form = &forms[0];
+        let values = form
+            .inputs()
+            .iter()
+            .map(|control| form.value(control))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [Some("First"), Some("1"), Some("y"), None, Some("x"), None]
+        );
+
+        #[cfg(feature = "serde_json")]
+        assert_eq!(
+            form.to_json(),
+            serde_json::json!({"a": "First", "b": "1", "c": "y", "e": ["x", "y"]})
+        );
+    }
+
+    fn validation_errors(html: &str) -> Vec<(String, ValidationErrorKind)> {
+        let html = Html::parse_document(html);
+        let forms = html.forms();
+        forms[0]
+            .validate()
+            .into_iter()
+            .map(|error| (error.name().unwrap().to_owned(), error.kind().clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate() {
+        let errors = validation_errors(
+            r#"
+            <form>
+                <input name="user" required>
+                <input name="nick" value="nick" required>
+                <input type="checkbox" name="terms" required>
+                <input type="radio" name="size" value="s" required>
+                <input type="radio" name="size" value="m">
+                <input type="radio" name="color" value="red" required checked>
+                <select name="country" required><option value="">-</option></select>
+                <textarea name="bio" maxlength="3">hello</textarea>
+                <input name="code" value="ab" minlength="3">
+                <input type="number" name="age" value="12" min="18" max="99">
+                <input type="number" name="count" value="many">
+                <input type="date" name="day" value="2024-05-01" max="2024-04-30">
+                <input type="email" name="mail" value="not-an-email">
+                <input type="email" name="mails" value="a@b.c, d@e.f" multiple>
+                <input type="url" name="home" value="example.com">
+                <input type="url" name="site" value="https://example.com/">
+                <input name="off" required disabled>
+                <input type="hidden" name="token" required>
+            </form>
+            "#,
+        );
+
+        assert_eq!(
+            errors,
+            [
+                ("user".to_owned(), ValidationErrorKind::ValueMissing),
+                ("terms".to_owned(), ValidationErrorKind::ValueMissing),
+                ("size".to_owned(), ValidationErrorKind::ValueMissing),
+                ("country".to_owned(), ValidationErrorKind::ValueMissing),
+                ("bio".to_owned(), ValidationErrorKind::TooLong { max: 3 }),
+                ("code".to_owned(), ValidationErrorKind::TooShort { min: 3 }),
+                (
+                    "age".to_owned(),
+                    ValidationErrorKind::RangeUnderflow {
+                        min: "18".to_owned()
+                    }
+                ),
+                ("count".to_owned(), ValidationErrorKind::TypeMismatch),
+                (
+                    "day".to_owned(),
+                    ValidationErrorKind::RangeOverflow {
+                        max: "2024-04-30".to_owned()
+                    }
+                ),
+                ("mail".to_owned(), ValidationErrorKind::TypeMismatch),
+                ("home".to_owned(), ValidationErrorKind::TypeMismatch),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_textarea_text() {
+        let html = Html::parse_document(
+            r#"<form><textarea name="t" maxlength="3">a&amp;b</textarea><textarea name="u" minlength="3">&lt;p</textarea></form>"#,
+        );
+        let forms = html.forms();
+        let form = &forms[0];
+        let values = form
+            .inputs()
+            .iter()
+            .map(|control| form.value(control))
+            .collect::<Vec<_>>();
+        assert_eq!(values, [Some("a&b"), Some("<p")]);
+
+        let errors = form
+            .validate()
+            .into_iter()
+            .map(|error| (error.name().unwrap().to_owned(), error.kind().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [("u".to_owned(), ValidationErrorKind::TooShort { min: 3 })]
+        );
+    }
+
+    #[test]
+    fn test_validate_after_set() {
+        let html = Html::parse_document(
+            r#"<form><input name="user" required><input name="zip" maxlength="5"></form>"#,
+        );
+        let mut forms = html.forms();
+        let form = forms.first_mut().unwrap();
+        let inputs = form.inputs();
+
+        assert_eq!(form.validate().len(), 1);
+        assert_eq!(form.validate()[0].to_string(), "user: value is required");
+
+        form.set(&inputs[0], Some("alice"));
+        form.set(&inputs[1], Some("123456"));
+
+        let errors = form.validate();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].name(), Some("zip"));
+        assert_eq!(errors[0].control(), inputs[1]);
+    }
+
+    #[cfg(feature = "pattern")]
+    #[test]
+    fn test_validate_pattern() {
+        let errors = validation_errors(
+            r#"
+            <form>
+                <input name="zip" value="1234a" pattern="[0-9]{5}">
+                <input name="code" value="ABC" pattern="[A-Z]+">
+                <input name="partial" value="x12" pattern="[0-9]+">
+                <input name="broken" value="x" pattern="(">
+            </form>
+            "#,
+        );
+
+        assert_eq!(
+            errors,
+            [
+                (
+                    "zip".to_owned(),
+                    ValidationErrorKind::PatternMismatch {
+                        pattern: "[0-9]{5}".to_owned()
+                    }
+                ),
+                (
+                    "partial".to_owned(),
+                    ValidationErrorKind::PatternMismatch {
+                        pattern: "[0-9]+".to_owned()
+                    }
+                ),
+            ]
+        );
+    }
 }
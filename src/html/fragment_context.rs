@@ -0,0 +1,51 @@
+use html5ever::QualName;
+
+/// The element which a fragment is parsed as the contents of.
+///
+/// Many elements are only allowed inside certain parents, and the parser drops them elsewhere.
+/// Parsing a fragment in the default [`Body`](FragmentContext::Body) context discards the table
+/// markup of `<tr><td>a</td></tr>` and keeps only its text, for example. Snippets returned by
+/// AJAX endpoints, which are meant to be inserted into a particular element of a page, should be
+/// parsed in the context of that element:
+///
+/// - table sections, i.e. `<caption>`, `<colgroup>`, `<thead>`, `<tbody>` and `<tfoot>`, need
+///   [`Table`](FragmentContext::Table),
+/// - table rows, `<tr>`, need [`TableBody`](FragmentContext::TableBody),
+/// - table cells, `<td>` and `<th>`, need [`TableRow`](FragmentContext::TableRow),
+/// - options, `<option>` and `<optgroup>`, need [`Select`](FragmentContext::Select),
+/// - a mix of these, e.g. rows and cells, needs [`Template`](FragmentContext::Template), and
+/// - everything else, e.g. list items or paragraphs, needs [`Body`](FragmentContext::Body).
+///
+/// See [`Html::parse_fragment_in`](super::Html::parse_fragment_in).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FragmentContext {
+    /// The `<body>` element, as used by [`Html::parse_fragment`](super::Html::parse_fragment).
+    #[default]
+    Body,
+    /// A `<table>` element.
+    Table,
+    /// A `<tbody>` element.
+    TableBody,
+    /// A `<tr>` element.
+    TableRow,
+    /// A `<select>` element.
+    Select,
+    /// A `<template>` element, which accepts the contents of all the other contexts.
+    Template,
+}
+
+impl FragmentContext {
+    /// Returns the name of the context element.
+    pub(crate) fn name(self) -> QualName {
+        let local = match self {
+            Self::Body => local_name!("body"),
+            Self::Table => local_name!("table"),
+            Self::TableBody => local_name!("tbody"),
+            Self::TableRow => local_name!("tr"),
+            Self::Select => local_name!("select"),
+            Self::Template => local_name!("template"),
+        };
+        QualName::new(None, ns!(html), local)
+    }
+}
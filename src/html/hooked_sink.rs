@@ -0,0 +1,146 @@
+use std::borrow::Cow;
+
+use ego_tree::NodeId;
+use html5ever::tendril::StrTendril;
+use html5ever::tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink};
+use html5ever::{Attribute, ExpandedName, QualName};
+
+use super::Html;
+use crate::node::Element;
+use crate::Node;
+
+/// Additional processing while parsing into an [`Html`].
+pub(crate) trait ParseHook {
+    /// Called with the byte offset of every start tag before the tree builder processes it,
+    /// when parsing with [`source_location::parse_document`](super::source_location::parse_document)
+    /// or [`source_location::parse_fragment`](super::source_location::parse_fragment).
+    fn start_tag(&mut self, _offset: usize) {}
+
+    /// Called for every element created by the parser, before it is inserted into the tree.
+    fn element_created(&mut self, id: NodeId, element: &mut Element);
+
+    /// Called for every parse error, before it is recorded.
+    fn parse_error(&mut self, _msg: &str) {}
+
+    /// Returns the node to which children of `parent` are appended instead of `parent`.
+    fn append_target(&mut self, _html: &Html, parent: NodeId) -> NodeId {
+        parent
+    }
+
+    /// Called once parsing is done, to store what the hook collected in the document.
+    fn finish(&mut self, _html: &mut Html) {}
+}
+
+/// Wraps the `TreeSink` of [`Html`] to apply a [`ParseHook`].
+pub(crate) struct HookedSink<H> {
+    html: Html,
+    hook: H,
+}
+
+impl<H: ParseHook> HookedSink<H> {
+    pub(crate) fn new(html: Html, hook: H) -> Self {
+        Self { html, hook }
+    }
+
+    pub(crate) fn hook_mut(&mut self) -> &mut H {
+        &mut self.hook
+    }
+}
+
+impl<H: ParseHook> TreeSink for HookedSink<H> {
+    type Output = Html;
+    type Handle = NodeId;
+
+    fn finish(mut self) -> Html {
+        self.hook.finish(&mut self.html);
+        self.html
+    }
+
+    fn create_element(
+        &mut self,
+        name: QualName,
+        attrs: Vec<Attribute>,
+        flags: ElementFlags,
+    ) -> NodeId {
+        let id = TreeSink::create_element(&mut self.html, name, attrs, flags);
+        if let Node::Element(element) = self.html.tree.get_mut(id).unwrap().value() {
+            self.hook.element_created(id, element);
+        }
+        id
+    }
+
+    fn parse_error(&mut self, msg: Cow<'static, str>) {
+        self.hook.parse_error(&msg);
+        self.html.parse_error(msg)
+    }
+
+    fn set_quirks_mode(&mut self, mode: QuirksMode) {
+        self.html.set_quirks_mode(mode)
+    }
+
+    fn get_document(&mut self) -> NodeId {
+        self.html.get_document()
+    }
+
+    fn same_node(&self, x: &NodeId, y: &NodeId) -> bool {
+        self.html.same_node(x, y)
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a NodeId) -> ExpandedName<'a> {
+        self.html.elem_name(target)
+    }
+
+    fn create_comment(&mut self, text: StrTendril) -> NodeId {
+        self.html.create_comment(text)
+    }
+
+    fn append_doctype_to_document(
+        &mut self,
+        name: StrTendril,
+        public_id: StrTendril,
+        system_id: StrTendril,
+    ) {
+        self.html
+            .append_doctype_to_document(name, public_id, system_id)
+    }
+
+    fn append(&mut self, parent: &NodeId, child: NodeOrText<NodeId>) {
+        let parent = self.hook.append_target(&self.html, *parent);
+        self.html.append(&parent, child)
+    }
+
+    fn append_before_sibling(&mut self, sibling: &NodeId, new_node: NodeOrText<NodeId>) {
+        self.html.append_before_sibling(sibling, new_node)
+    }
+
+    fn remove_from_parent(&mut self, target: &NodeId) {
+        self.html.remove_from_parent(target)
+    }
+
+    fn reparent_children(&mut self, node: &NodeId, new_parent: &NodeId) {
+        self.html.reparent_children(node, new_parent)
+    }
+
+    fn add_attrs_if_missing(&mut self, target: &NodeId, attrs: Vec<Attribute>) {
+        self.html.add_attrs_if_missing(target, attrs)
+    }
+
+    fn get_template_contents(&mut self, target: &NodeId) -> NodeId {
+        self.html.get_template_contents(target)
+    }
+
+    fn create_pi(&mut self, target: StrTendril, data: StrTendril) -> NodeId {
+        self.html.create_pi(target, data)
+    }
+
+    fn append_based_on_parent_node(
+        &mut self,
+        element: &NodeId,
+        prev_element: &NodeId,
+        child: NodeOrText<NodeId>,
+    ) {
+        let prev_element = self.hook.append_target(&self.html, *prev_element);
+        self.html
+            .append_based_on_parent_node(element, &prev_element, child)
+    }
+}
@@ -0,0 +1,286 @@
+//! Precomputed lookup tables over a document.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use ahash::{HashMap, HashMapExt};
+use ego_tree::{NodeId, Tree};
+use html5ever::LocalName;
+
+use crate::element_ref::WrapElements;
+use crate::selector::SubjectKey;
+use crate::{ElementRef, Html, Node, Selector};
+
+/// Index from `id` attribute values to elements, built by [`Html::build_id_index`].
+#[derive(Debug, Clone)]
+pub struct IdIndex<'a> {
+    ids: HashMap<&'a str, ElementRef<'a>>,
+}
+
+impl<'a> IdIndex<'a> {
+    pub(crate) fn new(html: &'a Html) -> Self {
+        let mut ids = HashMap::new();
+
+        for element in html.tree.root().descendants().wrap_elements() {
+            if let Some(id) = element.value().id() {
+                ids.entry(id).or_insert(element);
+            }
+        }
+
+        Self { ids }
+    }
+
+    /// Returns the element with the given ID.
+    ///
+    /// If several elements share the ID, the first one in document order is returned.
+    pub fn get(&self, id: &str) -> Option<ElementRef<'a>> {
+        self.ids.get(id).copied()
+    }
+
+    /// Returns the number of distinct IDs in the index.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns true if the document has no elements with an ID.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// Index from class names to elements, built by [`Html::build_class_index`].
+#[derive(Debug, Clone)]
+pub struct ClassIndex<'a> {
+    classes: HashMap<&'a str, Vec<ElementRef<'a>>>,
+}
+
+impl<'a> ClassIndex<'a> {
+    pub(crate) fn new(html: &'a Html) -> Self {
+        let mut classes = HashMap::<_, Vec<_>>::new();
+
+        for element in html.tree.root().descendants().wrap_elements() {
+            for class in element.value().classes() {
+                classes.entry(class).or_default().push(element);
+            }
+        }
+
+        Self { classes }
+    }
+
+    /// Returns the elements having the given class, in document order.
+    ///
+    /// Class names are matched case-sensitively.
+    pub fn get(&self, class: &str) -> &[ElementRef<'a>] {
+        self.classes.get(class).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the number of distinct classes in the index.
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    /// Returns true if the document has no elements with a class.
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+}
+
+/// An element and its position in the arena of the tree, which is the order in which
+/// [`Html::select`] visits elements.
+type Entry = (usize, NodeId);
+
+/// Lookup tables from tag names, IDs and classes to elements, used by [`Html::select_indexed`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ElementIndex {
+    names: HashMap<LocalName, Vec<Entry>>,
+    ids: HashMap<LocalName, Vec<Entry>>,
+    classes: HashMap<LocalName, Vec<Entry>>,
+}
+
+impl ElementIndex {
+    pub(crate) fn new(tree: &Tree<Node>) -> Self {
+        let mut index = Self::default();
+
+        for (position, node) in tree.nodes().enumerate() {
+            let Node::Element(element) = node.value() else {
+                continue;
+            };
+            let entry = (position, node.id());
+
+            index
+                .names
+                .entry(element.name.local.clone())
+                .or_default()
+                .push(entry);
+            if let Some(id) = element.id() {
+                index.ids.entry(id.into()).or_default().push(entry);
+            }
+            for class in element.class_names() {
+                let entries = index.classes.entry(class.clone()).or_default();
+                // Elements may repeat a class.
+                if entries.last() != Some(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        index
+    }
+
+    /// Returns the elements which may match `selector` in arena order, or `None` if the index
+    /// cannot narrow them down because some selector of the group requires no tag name, ID or
+    /// class of its subject.
+    pub(crate) fn candidates(&self, selector: &Selector) -> Option<Vec<NodeId>> {
+        let mut candidates = Vec::new();
+        for keys in selector.subject_keys() {
+            let shortest = keys
+                .into_iter()
+                .map(|key| {
+                    let (map, key) = match key {
+                        SubjectKey::LocalName(name) => (&self.names, name),
+                        SubjectKey::Id(id) => (&self.ids, id),
+                        SubjectKey::Class(class) => (&self.classes, class),
+                    };
+                    map.get(key).map_or(&[][..], Vec::as_slice)
+                })
+                .min_by_key(|entries| entries.len())?;
+            candidates.extend_from_slice(shortest);
+        }
+
+        candidates.sort_unstable_by_key(|(position, _)| *position);
+        candidates.dedup_by_key(|(position, _)| *position);
+        Some(candidates.into_iter().map(|(_, id)| id).collect())
+    }
+}
+
+/// The index of a document, built when first needed.
+///
+/// Documents are compared without their indexes, which are only a cache.
+#[derive(Clone, Default)]
+pub(crate) struct LazyIndex(OnceLock<ElementIndex>);
+
+impl LazyIndex {
+    /// Returns the index of `tree`, building it if needed.
+    pub(crate) fn get(&self, tree: &Tree<Node>) -> &ElementIndex {
+        self.0.get_or_init(|| ElementIndex::new(tree))
+    }
+
+    /// Drops the index, so that it is rebuilt when needed next.
+    pub(crate) fn invalidate(&mut self) {
+        self.0.take();
+    }
+}
+
+impl PartialEq for LazyIndex {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for LazyIndex {}
+
+impl fmt::Debug for LazyIndex {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.0.get().is_some() {
+            "built"
+        } else {
+            "not built"
+        };
+        fmt.debug_tuple("LazyIndex").field(&state).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Html, Selector};
+
+    #[test]
+    fn id_index() {
+        let html = Html::parse_document(
+            r#"<div id="a">1</div><p id="b">2<span id="c">3</span></p><div id="a">4</div><p>5</p>"#,
+        );
+        let index = html.build_id_index();
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.get("a").unwrap().inner_html(), "1");
+        assert_eq!(index.get("c").unwrap().inner_html(), "3");
+        assert_eq!(index.get("b").unwrap().value().name(), "p");
+        assert!(index.get("d").is_none());
+    }
+
+    #[test]
+    fn class_index() {
+        let html = Html::parse_document(
+            r#"<div class="field name">1</div><p class="field">2<span class="price field field">3</span></p><p class="Field">4</p>"#,
+        );
+        let index = html.build_class_index();
+
+        assert_eq!(index.len(), 4);
+        let texts = |class| {
+            index
+                .get(class)
+                .iter()
+                .map(|element| element.text().next().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(texts("field"), ["1", "2", "3"]);
+        assert_eq!(texts("price"), ["3"]);
+        assert_eq!(texts("Field"), ["4"]);
+        assert!(index.get("missing").is_empty());
+    }
+
+    #[test]
+    fn select_indexed() {
+        let html = Html::parse_document(
+            r#"
+            <div id="a" class="x y"><p class="x x">1</p><p id="b">2</p></div>
+            <div id="a"><span class="y">3</span><svg><foreignObject class="x"/></svg></div>
+            <ul><li class="x">4</li><li>5</li></ul>
+            "#,
+        );
+        let selectors = [
+            "p",
+            ".x",
+            "#a",
+            "#a > .x",
+            "div .y, li, p.x",
+            "li:nth-child(2), #b",
+            "p.y",
+            "#missing, .missing",
+            "*",
+            ".x, *",
+            "foreignObject, foreignobject",
+            "[class]",
+            ":not(p)",
+            ":root",
+            "div:has(> span) span",
+        ];
+
+        for css in selectors {
+            let selector = Selector::parse(css).unwrap();
+            let expected = html.select(&selector).collect::<Vec<_>>();
+            assert_eq!(html.select_indexed(&selector), expected, "{css}");
+        }
+    }
+
+    #[test]
+    fn select_indexed_after_changes() {
+        let mut html = Html::parse_fragment(r#"<p class="a">1</p>"#);
+        let selector = Selector::parse("p, .b").unwrap();
+        assert_eq!(html.select_indexed(&selector).len(), 1);
+
+        let root = html.root_element().id();
+        let p = html.create_element("p");
+        html.append_child(root, p);
+        assert_eq!(html.select_indexed(&selector).len(), 2);
+
+        let first = html.select(&selector).next().unwrap().id();
+        let mut first = html.tree.get_mut(first).unwrap();
+        if let crate::Node::Element(element) = first.value() {
+            element.set_attr("class", "b");
+        }
+        html.build_index();
+        let indexed = html.select_indexed(&Selector::parse(".b").unwrap());
+        assert_eq!(indexed.len(), 1);
+    }
+}
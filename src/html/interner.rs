@@ -0,0 +1,129 @@
+use ahash::AHashSet as HashSet;
+use ego_tree::NodeId;
+
+use super::hooked_sink::ParseHook;
+use crate::node::Element;
+use crate::StrTendril;
+
+/// Strings shared between documents parsed with [`Html::parse_with_interner`].
+///
+/// Tag and attribute names are always interned by the parser, so a repeated `div` or `href`
+/// costs nothing beyond the element itself. Attribute values are stored per element though, and
+/// pages from the same site tend to repeat many of them, e.g. `class` lists, navigation `href`s
+/// or `rel` values. An interner keeps one copy of each distinct value and makes later elements
+/// share its buffer instead of allocating their own.
+///
+/// Values of 8 bytes or fewer are stored inline and values longer than [`Interner::max_len`]
+/// are unlikely to repeat, so neither is interned.
+///
+/// The interner only grows. To bound its memory, replace it with a new one once
+/// [`Interner::len`] becomes too large.
+///
+/// [`Html::parse_with_interner`]: super::Html::parse_with_interner
+#[derive(Debug)]
+pub struct Interner {
+    values: HashSet<StrTendril>,
+    max_len: usize,
+}
+
+/// Length of the longest value stored inline by `Tendril`.
+const INLINE_LEN: usize = 8;
+
+impl Interner {
+    /// Creates an empty interner for values up to 256 bytes.
+    pub fn new() -> Self {
+        Self::with_max_len(256)
+    }
+
+    /// Creates an empty interner for values up to `max_len` bytes.
+    pub fn with_max_len(max_len: usize) -> Self {
+        Interner {
+            values: HashSet::new(),
+            max_len,
+        }
+    }
+
+    /// Returns the length in bytes of the longest value that is interned.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Returns the number of distinct values held.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether no value is held.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn intern(&mut self, value: &mut StrTendril) {
+        if value.len() <= INLINE_LEN || value.len() > self.max_len {
+            return;
+        }
+
+        match self.values.get(value) {
+            Some(interned) => *value = interned.clone(),
+            None => {
+                // Cloning turns the value into a shared buffer, which later clones point to.
+                self.values.insert(value.clone());
+            }
+        }
+    }
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ParseHook for &mut Interner {
+    fn element_created(&mut self, _id: NodeId, element: &mut Element) {
+        for value in element.attrs.values_mut() {
+            self.intern(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+    use crate::html::Html;
+    use crate::selector::Selector;
+
+    fn attr_ptr(html: &Html, selector: &str, attr: &str) -> *const u8 {
+        let selector = Selector::parse(selector).unwrap();
+        let element = html.select(&selector).next().unwrap();
+        element.attr(attr).unwrap().as_ptr()
+    }
+
+    #[test]
+    fn shares_values_across_documents() {
+        let page = r#"<a class="navigation-link" href="/index.html">Home</a><p class="short">"#;
+
+        let mut interner = Interner::new();
+        let first = Html::parse_with_interner(page, &mut interner);
+        let second = Html::parse_with_interner(page, &mut interner);
+        assert_eq!(interner.len(), 2);
+
+        assert_eq!(
+            attr_ptr(&first, "a", "class"),
+            attr_ptr(&second, "a", "class")
+        );
+        assert_eq!(
+            attr_ptr(&first, "a", "href"),
+            attr_ptr(&second, "a", "href")
+        );
+        assert_eq!(first, Html::parse_document(page));
+    }
+
+    #[test]
+    fn max_len() {
+        let mut interner = Interner::with_max_len(10);
+        let page = r#"<a href="/short.html" title="exactly 10">"#;
+        Html::parse_with_interner(page, &mut interner);
+        assert_eq!(interner.len(), 1);
+    }
+}
@@ -0,0 +1,366 @@
+//! HTML microdata.
+
+use std::mem;
+
+use ahash::{HashMap, HashMapExt, HashSet, HashSetExt};
+use ego_tree::NodeId;
+
+use super::{Html, IdIndex};
+use crate::element_ref::WrapElements;
+use crate::ElementRef;
+
+/// An item described by microdata, i.e. an element with an `itemscope` attribute.
+///
+/// See [`Html::microdata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MicrodataItem<'a> {
+    element: ElementRef<'a>,
+    properties: Vec<(&'a str, MicrodataValue<'a>)>,
+}
+
+impl<'a> MicrodataItem<'a> {
+    /// Returns the element with the `itemscope` attribute.
+    pub fn element(&self) -> ElementRef<'a> {
+        self.element
+    }
+
+    /// Returns the item types given by the `itemtype` attribute.
+    pub fn types(&self) -> impl Iterator<Item = &'a str> {
+        self.element.attr_tokens("itemtype", ' ')
+    }
+
+    /// Returns the global identifier given by the `itemid` attribute.
+    pub fn id(&self) -> Option<&'a str> {
+        self.element.attr("itemid")
+    }
+
+    /// Returns the names and values of all properties, ordered by the document order of the
+    /// elements defining them.
+    ///
+    /// An element with several names in its `itemprop` attribute defines a property for each.
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &MicrodataValue<'a>)> {
+        self.properties.iter().map(|(name, value)| (*name, value))
+    }
+
+    /// Returns the values of the properties named `name`.
+    pub fn values<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'b MicrodataValue<'a>> {
+        self.properties
+            .iter()
+            .filter(move |(property, _)| *property == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the value of the first property named `name`.
+    pub fn value(&self, name: &str) -> Option<&MicrodataValue<'a>> {
+        self.properties
+            .iter()
+            .find(|(property, _)| *property == name)
+            .map(|(_, value)| value)
+    }
+}
+
+impl Drop for MicrodataItem<'_> {
+    // Flatten nested items so that dropping deeply nested microdata cannot overflow the stack.
+    fn drop(&mut self) {
+        let mut pending = mem::take(&mut self.properties);
+        while let Some((_, value)) = pending.pop() {
+            if let MicrodataValue::Item(mut item) = value {
+                pending.append(&mut item.properties);
+            }
+        }
+    }
+}
+
+/// The value of a microdata property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MicrodataValue<'a> {
+    /// Text, e.g. the `content` of a `<meta>` element or the text of a `<span>`.
+    Text(String),
+    /// A URL, e.g. the `href` of an `<a>` element, as written in the document.
+    Url(&'a str),
+    /// A nested item.
+    Item(MicrodataItem<'a>),
+}
+
+impl<'a> MicrodataValue<'a> {
+    /// Returns the text or URL, or `None` for a nested item.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            Self::Url(url) => Some(url),
+            Self::Item(_) => None,
+        }
+    }
+
+    /// Returns the nested item.
+    pub fn as_item(&self) -> Option<&MicrodataItem<'a>> {
+        match self {
+            Self::Item(item) => Some(item),
+            _ => None,
+        }
+    }
+}
+
+/// Extracts the top-level items of `html`, following the HTML microdata processing model.
+pub(crate) fn items(html: &Html) -> Vec<MicrodataItem<'_>> {
+    let extractor = Extractor::new(html);
+    html.tree
+        .root()
+        .descendants()
+        .wrap_elements()
+        .filter(|element| is_item(element) && element.attr("itemprop").is_none())
+        .map(|element| extractor.item(element))
+        .collect()
+}
+
+struct Extractor<'a> {
+    ids: IdIndex<'a>,
+    /// The position of every node in document order.
+    order: HashMap<NodeId, usize>,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(html: &'a Html) -> Self {
+        let mut order = HashMap::new();
+        for (position, node) in html.tree.root().descendants().enumerate() {
+            order.insert(node.id(), position);
+        }
+
+        Extractor {
+            ids: html.build_id_index(),
+            order,
+        }
+    }
+
+    /// Builds the item of `element` and the items nested in it.
+    ///
+    /// Nested items are built using a stack rather than recursion, so that deeply nested items
+    /// cannot overflow the call stack. Items referencing one they are nested in through
+    /// `itemref` skip that property, so that they do not nest endlessly.
+    fn item(&self, element: ElementRef<'a>) -> MicrodataItem<'a> {
+        let mut stack = vec![self.partial_item(element)];
+        loop {
+            let partial = stack.last_mut().unwrap();
+            match partial.pending.next() {
+                Some(property) if is_item(&property) => {
+                    if !stack.iter().any(|partial| partial.element == property) {
+                        stack.push(self.partial_item(property));
+                    }
+                }
+                Some(property) => {
+                    add_property(&mut partial.properties, property, property_value(property));
+                }
+                None => {
+                    let partial = stack.pop().unwrap();
+                    let item = MicrodataItem {
+                        element: partial.element,
+                        properties: partial.properties,
+                    };
+                    match stack.last_mut() {
+                        Some(parent) => add_property(
+                            &mut parent.properties,
+                            partial.element,
+                            MicrodataValue::Item(item),
+                        ),
+                        None => return item,
+                    }
+                }
+            }
+        }
+    }
+
+    fn partial_item(&self, element: ElementRef<'a>) -> PartialItem<'a> {
+        PartialItem {
+            element,
+            properties: Vec::new(),
+            pending: self.property_elements(element).into_iter(),
+        }
+    }
+
+    /// Returns the elements defining properties of the item `root`, in document order.
+    fn property_elements(&self, root: ElementRef<'a>) -> Vec<ElementRef<'a>> {
+        let mut results = Vec::new();
+        let mut visited = HashSet::new();
+        visited.insert(root.id());
+
+        let mut pending = root.child_elements().collect::<Vec<_>>();
+        pending.extend(
+            root.attr_tokens("itemref", ' ')
+                .filter_map(|id| self.ids.get(id)),
+        );
+
+        while let Some(current) = pending.pop() {
+            if !visited.insert(current.id()) {
+                continue;
+            }
+            if !is_item(&current) {
+                pending.extend(current.child_elements());
+            }
+            if current.attr_tokens("itemprop", ' ').next().is_some() {
+                results.push(current);
+            }
+        }
+
+        results.sort_by_key(|element| self.order[&element.id()]);
+        results
+    }
+}
+
+/// An item whose properties are being built by [`Extractor::item`].
+struct PartialItem<'a> {
+    element: ElementRef<'a>,
+    properties: Vec<(&'a str, MicrodataValue<'a>)>,
+    /// The elements defining the properties not built yet.
+    pending: std::vec::IntoIter<ElementRef<'a>>,
+}
+
+/// Adds the properties defined by `element` with `value`, one for each distinct name.
+fn add_property<'a>(
+    properties: &mut Vec<(&'a str, MicrodataValue<'a>)>,
+    element: ElementRef<'a>,
+    value: MicrodataValue<'a>,
+) {
+    let mut names = Vec::new();
+    for name in element.attr_tokens("itemprop", ' ') {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    // Move the value into the last property rather than cloning nested items once more.
+    if let Some((last, names)) = names.split_last() {
+        for name in names {
+            properties.push((name, value.clone()));
+        }
+        properties.push((last, value));
+    }
+}
+
+fn is_item(element: &ElementRef) -> bool {
+    element.attr("itemscope").is_some()
+}
+
+/// Returns the value of a property element which is not an item.
+fn property_value(element: ElementRef<'_>) -> MicrodataValue<'_> {
+    let url = |attr| MicrodataValue::Url(element.attr(attr).unwrap_or_default());
+    let attr = |attr| MicrodataValue::Text(element.attr(attr).unwrap_or_default().to_owned());
+
+    match element.value().name() {
+        "meta" => attr("content"),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => url("src"),
+        "a" | "area" | "link" => url("href"),
+        "object" => url("data"),
+        "data" | "meter" => attr("value"),
+        "time" if element.attr("datetime").is_some() => attr("datetime"),
+        _ => MicrodataValue::Text(element.text().collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MicrodataValue;
+    use crate::Html;
+
+    #[test]
+    fn nested_items() {
+        let html = Html::parse_document(
+            r#"
+            <div itemscope itemtype="https://schema.org/Product" itemid="urn:isbn:123">
+                <h1 itemprop="name">Widget</h1>
+                <img itemprop="image" src="/widget.png">
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <meta itemprop="priceCurrency" content="EUR">
+                    <span itemprop="price">9.99</span>
+                    <time itemprop="validFrom priceValidFrom" datetime="2024-01-01">New year</time>
+                </div>
+                <span itemprop="keyword">a</span><span itemprop="keyword">b</span>
+            </div>
+            <p itemscope><data itemprop="count" value="3">three</data></p>
+            "#,
+        );
+
+        let items = html.microdata();
+        assert_eq!(items.len(), 2);
+
+        let product = &items[0];
+        assert_eq!(
+            product.types().collect::<Vec<_>>(),
+            ["https://schema.org/Product"]
+        );
+        assert_eq!(product.id(), Some("urn:isbn:123"));
+        assert_eq!(
+            product
+                .properties()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["name", "image", "offers", "keyword", "keyword"]
+        );
+        assert_eq!(product.value("name").unwrap().as_str(), Some("Widget"));
+        assert_eq!(
+            product.value("image"),
+            Some(&MicrodataValue::Url("/widget.png"))
+        );
+        assert_eq!(
+            product
+                .values("keyword")
+                .filter_map(MicrodataValue::as_str)
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+
+        let offer = product.value("offers").unwrap().as_item().unwrap();
+        let text = |name| offer.value(name).and_then(MicrodataValue::as_str);
+        assert_eq!(text("priceCurrency"), Some("EUR"));
+        assert_eq!(text("price"), Some("9.99"));
+        assert_eq!(text("validFrom"), Some("2024-01-01"));
+        assert_eq!(text("priceValidFrom"), Some("2024-01-01"));
+
+        assert_eq!(items[1].value("count").unwrap().as_str(), Some("3"));
+    }
+
+    #[test]
+    fn itemref() {
+        let html = Html::parse_document(
+            r#"
+            <div itemscope id="band" itemref="name members"></div>
+            <p id="name" itemprop="name">The Band</p>
+            <div id="members">
+                <span itemprop="member" itemscope itemref="band">
+                    <span itemprop="name">Alice</span>
+                </span>
+            </div>
+            "#,
+        );
+
+        let items = html.microdata();
+        assert_eq!(items.len(), 1);
+
+        let band = &items[0];
+        assert_eq!(band.value("name").unwrap().as_str(), Some("The Band"));
+
+        let member = band.value("member").unwrap().as_item().unwrap();
+        assert_eq!(
+            member
+                .properties()
+                .map(|(name, value)| (name, value.as_str()))
+                .collect::<Vec<_>>(),
+            [("name", Some("Alice"))]
+        );
+    }
+
+    #[test]
+    fn itemref_cycle() {
+        let html = Html::parse_document(
+            r#"
+            <div itemscope><div id="p" itemprop="p" itemscope itemref="q"></div></div>
+            <div id="q" itemprop="q" itemscope itemref="p"></div>
+            "#,
+        );
+
+        let items = html.microdata();
+        assert_eq!(items.len(), 1);
+
+        let p = items[0].value("p").unwrap().as_item().unwrap();
+        let q = p.value("q").unwrap().as_item().unwrap();
+        assert_eq!(q.properties().count(), 0);
+    }
+}
@@ -3,19 +3,26 @@
 #[cfg(feature = "errors")]
 use std::borrow::Cow;
 use std::fmt;
+use std::io;
 use std::iter::FusedIterator;
 
-use ego_tree::iter::Nodes;
-use ego_tree::Tree;
-use html5ever::serialize::SerializeOpts;
+use ahash::{HashSet, HashSetExt};
+use ego_tree::iter::{Edge, Nodes};
+use ego_tree::{NodeId, NodeRef, Tree};
+use html5ever::serialize::{HtmlSerializer, SerializeOpts};
 use html5ever::tree_builder::QuirksMode;
-use html5ever::{driver, serialize, QualName};
+use html5ever::{driver, serialize, LocalName, QualName};
 use selectors::NthIndexCache;
 use tendril::TendrilSink;
 
-use crate::selector::Selector;
-use crate::{ElementRef, Node};
+use crate::element_ref::style;
+use crate::error::{MissingElement, SelectorParseError};
+use crate::extract::{ExtractedValue, Extraction};
 use crate::html::form::Form;
+use crate::html::microdata::MicrodataItem;
+use crate::node::{Doctype, Element, ProcessingInstruction, Text};
+use crate::selector::{MatchContext, MatchExplanation, MatchProfile, SelectCache, Selector};
+use crate::{CaseSensitivity, ElementRef, Node};
 
 /// An HTML tree.
 ///
@@ -34,6 +41,16 @@ pub struct Html {
 
     /// The node tree.
     pub tree: Tree<Node>,
+
+    pub(crate) duplicate_attributes: Vec<DuplicateAttr>,
+
+    /// Whether the document is parsed with scripting enabled, in which case the tokenizer
+    /// leaves the contents of `<noscript>` undecoded.
+    pub(crate) scripting_enabled: bool,
+
+    index: LazyIndex,
+
+    order: OrderIndex,
 }
 
 impl Html {
@@ -44,6 +61,10 @@ impl Html {
             errors: Vec::new(),
             quirks_mode: QuirksMode::NoQuirks,
             tree: Tree::new(Node::Document),
+            duplicate_attributes: Vec::new(),
+            scripting_enabled: true,
+            index: LazyIndex::default(),
+            order: OrderIndex::default(),
         }
     }
 
@@ -54,6 +75,10 @@ impl Html {
             errors: Vec::new(),
             quirks_mode: QuirksMode::NoQuirks,
             tree: Tree::new(Node::Fragment),
+            duplicate_attributes: Vec::new(),
+            scripting_enabled: true,
+            index: LazyIndex::default(),
+            order: OrderIndex::default(),
         }
     }
 
@@ -81,193 +106,2478 @@ impl Html {
     }
 
     /// Parses a string of HTML as a fragment.
+    ///
+    /// The fragment is parsed as the contents of a `<body>` element, which drops table rows and
+    /// cells, for example. Use [`Html::parse_fragment_in`] for those.
     pub fn parse_fragment(fragment: &str) -> Self {
+        Self::parse_fragment_in(fragment, FragmentContext::Body)
+    }
+
+    /// Parses a string of HTML as a fragment in the given context.
+    ///
+    /// See [`FragmentContext`] for which context suits which kind of snippet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::html::{FragmentContext, Html};
+    /// use scraper::Selector;
+    ///
+    /// let rows = "<tr><td>1</td><td>One</td></tr><tr><td>2</td><td>Two</td></tr>";
+    /// let td = Selector::parse("tr > td:last-child").unwrap();
+    ///
+    /// let fragment = Html::parse_fragment_in(rows, FragmentContext::TableBody);
+    /// let names = fragment.select(&td).map(|td| td.inner_html()).collect::<Vec<_>>();
+    /// assert_eq!(names, ["One", "Two"]);
+    ///
+    /// assert_eq!(Html::parse_fragment(rows).select(&td).count(), 0);
+    /// ```
+    pub fn parse_fragment_in(fragment: &str, context: FragmentContext) -> Self {
         let parser = driver::parse_fragment(
             Self::new_fragment(),
             Default::default(),
-            QualName::new(None, ns!(html), local_name!("body")),
+            context.name(),
             Vec::new(),
         );
         parser.one(fragment)
     }
 
+    /// Parses a string of HTML as a document with the given options.
+    ///
+    /// Unlike passing the options to html5ever's driver, this also records whether scripting
+    /// is enabled, which determines whether the text in `<noscript>` elements is raw.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use html5ever::driver::ParseOpts;
+    /// use html5ever::tree_builder::TreeBuilderOpts;
+    /// use scraper::Html;
+    ///
+    /// let opts = ParseOpts {
+    ///     tree_builder: TreeBuilderOpts {
+    ///         scripting_enabled: false,
+    ///         ..Default::default()
+    ///     },
+    ///     ..Default::default()
+    /// };
+    /// let html = Html::parse_document_with_opts("<body><noscript>&lt;img&gt;</noscript>", opts);
+    /// assert_eq!(
+    ///     html.root_element().inner_html(),
+    ///     "<head></head><body><noscript>&lt;img&gt;</noscript></body>"
+    /// );
+    /// ```
+    pub fn parse_document_with_opts(document: &str, opts: driver::ParseOpts) -> Self {
+        let mut sink = Self::new_document();
+        sink.scripting_enabled = opts.tree_builder.scripting_enabled;
+        let parser = driver::parse_document(sink, opts);
+        parser.one(document)
+    }
+
+    /// Parses a string of HTML as a fragment in the given context with the given options.
+    ///
+    /// See [`Html::parse_document_with_opts`].
+    pub fn parse_fragment_with_opts(
+        fragment: &str,
+        context: FragmentContext,
+        opts: driver::ParseOpts,
+    ) -> Self {
+        let mut sink = Self::new_fragment();
+        sink.scripting_enabled = opts.tree_builder.scripting_enabled;
+        let parser = driver::parse_fragment(sink, opts, context.name(), Vec::new());
+        parser.one(fragment)
+    }
+
+    /// The depth to which [`Html::fuzz_safe_parse`] limits the nesting of nodes, counting the
+    /// `html` element as depth 1. This is the limit of Chromium's parser.
+    pub const MAX_DEPTH: usize = 512;
+
+    /// Parses a string of HTML as a document, limiting how deeply its nodes are nested.
+    ///
+    /// Adversarial input like thousands of unclosed `<span>` tags yields a tree about as deep
+    /// as the input is long. The operations of this crate walk trees iteratively, but code
+    /// processing them recursively, e.g. to convert a document into nested structures, may
+    /// overflow the stack. Like Chromium's parser, this appends nodes which would be nested
+    /// deeper than [`Html::MAX_DEPTH`] to the ancestor of their parent at that depth, so that
+    /// they become siblings of the deepest nodes. No content is lost.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::fuzz_safe_parse(&"<span>".repeat(10_000));
+    /// let depth = html.tree.nodes().map(|node| node.ancestors().count()).max();
+    /// assert_eq!(depth, Some(Html::MAX_DEPTH));
+    /// assert_eq!(html.tree.values().filter(|node| node.is_element()).count(), 10_003);
+    /// ```
+    pub fn fuzz_safe_parse(document: &str) -> Self {
+        ParseOptions::new().fuzz_safe(true).parse_document(document)
+    }
+
+    /// Parses a string of HTML as a document, recording where each element starts in it.
+    ///
+    /// The locations are available through [`ElementRef::source_location`]. Since finding them
+    /// takes additional time, they are not recorded by [`Html::parse_document`].
+    pub fn parse_document_with_source_locations(document: &str) -> Self {
+        ParseOptions::new()
+            .source_locations(true)
+            .parse_document(document)
+    }
+
+    /// Parses a string of HTML as a fragment, recording where each element starts in it.
+    ///
+    /// The locations are available through [`ElementRef::source_location`]. Since finding them
+    /// takes additional time, they are not recorded by [`Html::parse_fragment`].
+    pub fn parse_fragment_with_source_locations(fragment: &str) -> Self {
+        ParseOptions::new()
+            .source_locations(true)
+            .parse_fragment(fragment)
+    }
+
+    /// Parses a string of HTML as a document, recording the attributes repeated in start tags.
+    ///
+    /// The parser keeps the first occurrence of each attribute and discards the others. They
+    /// are available through [`Html::duplicate_attributes`]. Since finding them takes additional
+    /// time, they are not recorded by [`Html::parse_document`].
+    pub fn parse_document_with_duplicate_attributes(document: &str) -> Self {
+        ParseOptions::new()
+            .duplicate_attributes(true)
+            .parse_document(document)
+    }
+
+    /// Parses a string of HTML as a fragment, recording the attributes repeated in start tags.
+    ///
+    /// See [`Html::parse_document_with_duplicate_attributes`].
+    pub fn parse_fragment_with_duplicate_attributes(fragment: &str) -> Self {
+        ParseOptions::new()
+            .duplicate_attributes(true)
+            .parse_fragment(fragment)
+    }
+
+    /// Parses a string of HTML as a document, sharing attribute values with other documents
+    /// parsed using the same `interner`.
+    ///
+    /// This reduces the memory used by many similar documents kept at once, e.g. while crawling
+    /// a site. See [`Interner`] for which values are shared.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::html::{Html, Interner};
+    ///
+    /// let mut interner = Interner::new();
+    /// let pages = [
+    ///     r#"<a class="navigation" href="/">Home</a>"#,
+    ///     r#"<a class="navigation" href="/">About</a>"#,
+    /// ];
+    ///
+    /// let documents = pages
+    ///     .iter()
+    ///     .map(|page| Html::parse_with_interner(page, &mut interner))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(documents.len(), 2);
+    /// assert_eq!(interner.len(), 1);
+    /// ```
+    pub fn parse_with_interner(document: &str, interner: &mut Interner) -> Self {
+        ParseOptions::new()
+            .interner(interner)
+            .parse_document(document)
+    }
+
+    /// Parses a string of XML as a document.
+    ///
+    /// Unlike HTML, XML is case-sensitive, so selectors only match elements whose names have the
+    /// same case, e.g. `Item` does not match `<item>`. This also applies to elements in the
+    /// XHTML namespace, which are matched case-insensitively in documents parsed as HTML.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let feed = Html::parse_xml("<rss><channel><Item>First</Item></channel></rss>");
+    /// assert_eq!(feed.select(&Selector::parse("Item").unwrap()).count(), 1);
+    /// assert_eq!(feed.select(&Selector::parse("item").unwrap()).count(), 0);
+    /// ```
+    #[cfg(feature = "xml")]
+    pub fn parse_xml(document: &str) -> Self {
+        ParseOptions::new().parse_xml(document)
+    }
+
     /// Returns an iterator over elements matching a selector.
+    ///
+    /// `:scope` matches the root element, like in `document.querySelectorAll`. For documents,
+    /// this is the `html` element, so `:scope > head` selects the document's `head`. For
+    /// fragments, it is the element wrapping the fragment's contents, so that `:scope > span`
+    /// selects only the top-level `span` elements, regardless of any text surrounding them.
     pub fn select<'a, 'b>(&'a self, selector: &'b Selector) -> Select<'a, 'b> {
+        let inner = match self.anchored_subtrees(selector) {
+            Some(subtrees) => Candidates::Anchored(self.tree.nodes(), subtrees),
+            None => Candidates::All(self.tree.nodes()),
+        };
         Select {
-            inner: self.tree.nodes(),
+            inner,
+            scope: self.scope(),
             selector,
-            nth_index_cache: NthIndexCache::default(),
+            nth_index_cache: SelectCache::default(),
+            profile: None,
         }
     }
 
-    /// Returns an iterator over forms.
-    pub fn forms(&self) -> Vec<Form> {
-        self.select(&Selector::parse("form").unwrap())
-            .map(|form|Form::wrap(self.root_element(), form))
-            .collect()
+    /// Returns the descendants of the elements having one of the IDs returned by
+    /// `Selector::anchor_ids`, outside of which `selector` cannot match, or `None` if the
+    /// selector does not require such an ancestor.
+    ///
+    /// The elements with the IDs are found by scanning the tree rather than through the index of
+    /// [`Html::select_indexed`], which is not updated when the tree changes. This is still much
+    /// faster than matching every element.
+    fn anchored_subtrees(&self, selector: &Selector) -> Option<HashSet<NodeId>> {
+        let ids = selector.anchor_ids()?;
+        let mut nodes = HashSet::new();
+        for node in self.tree.nodes() {
+            let id = node.value().as_element().and_then(Element::id);
+            // The subtree of a nested anchor is part of that of its ancestor.
+            if id.is_some_and(|id| ids.iter().any(|anchor| **anchor == *id))
+                && !nodes.contains(&node.id())
+            {
+                nodes.extend(node.descendants().skip(1).map(|node| node.id()));
+            }
+        }
+        Some(nodes)
     }
 
-    /// Returns the root `<html>` element.
-    pub fn root_element(&self) -> ElementRef {
-        let root_node = self
-            .tree
-            .root()
-            .children()
-            .find(|child| child.value().is_element())
-            .expect("html node missing");
-        ElementRef::wrap(root_node).unwrap()
+    /// Returns an iterator over elements matching a selector, reusing the caches of `context`.
+    ///
+    /// This yields the same elements as [`Html::select`], but is faster when running many
+    /// selectors using structural pseudo-classes like `:nth-child` against the same document.
+    pub fn select_with_context<'a, 'b>(
+        &'a self,
+        selector: &'a Selector,
+        context: &'b mut MatchContext<'a>,
+    ) -> Select<'a, 'b> {
+        Select {
+            nth_index_cache: SelectCache::shared(context),
+            ..self.select(selector)
+        }
     }
 
-    /// Serialize entire document into HTML.
-    pub fn html(&self) -> String {
-        let opts = SerializeOpts {
-            scripting_enabled: false, // It's not clear what this does.
-            traversal_scope: html5ever::serialize::TraversalScope::IncludeNode,
-            create_missing_parent: false,
-        };
-        let mut buf = Vec::new();
-        serialize(&mut buf, self, opts).unwrap();
-        String::from_utf8(buf).unwrap()
+    /// Returns the elements matching a selector together with statistics about matching it.
+    ///
+    /// This finds the same elements as [`Html::select`], visiting the same candidates, but records
+    /// how often each selector of the group was matched and how long that took, see
+    /// [`MatchProfile`]. Since this slows down matching, it is meant for debugging selectors, not
+    /// for regular use.
+    pub fn select_profiled(&self, selector: &Selector) -> (Vec<ElementRef<'_>>, MatchProfile) {
+        let mut profile = selector.profile();
+        let elements = Select {
+            profile: Some(&mut profile),
+            ..self.select(selector)
+        }
+        .collect();
+        (elements, profile)
     }
-}
 
-/// Iterator over elements matching a selector.
-pub struct Select<'a, 'b> {
-    inner: Nodes<'a, Node>,
-    selector: &'b Selector,
-    nth_index_cache: NthIndexCache,
-}
+    /// Explains how matching a selector against `element` goes, for finding out why it does
+    /// not match.
+    ///
+    /// For each selector of the group, this reports which compound selectors matched which
+    /// elements, and which of their simple selectors failed, see [`MatchExplanation`]. This is
+    /// much slower than [`Selector::matches`] and is meant for debugging selectors, not for
+    /// regular use.
+    pub fn explain_match<'a>(
+        &'a self,
+        selector: &Selector,
+        element: ElementRef<'a>,
+    ) -> MatchExplanation<'a> {
+        selector.explain(element, self.scope())
+    }
 
-impl fmt::Debug for Select<'_, '_> {
-    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.debug_struct("Select")
-            .field("inner", &self.inner)
-            .field("selector", &self.selector)
-            .field("nth_index_cache", &"..")
-            .finish()
+    /// Returns the first element matching a selector, if any.
+    pub fn select_first(&self, selector: &Selector) -> Option<ElementRef<'_>> {
+        self.select(selector).next()
     }
-}
 
-impl Clone for Select<'_, '_> {
-    fn clone(&self) -> Self {
-        Self {
-            inner: self.inner.clone(),
-            selector: self.selector,
-            nth_index_cache: NthIndexCache::default(),
-        }
+    /// Returns the first element matching a selector, or an error naming the selector if there
+    /// is none.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment("<h1>Title</h1>");
+    ///
+    /// let title = html.select_required(&Selector::parse("h1").unwrap()).unwrap();
+    /// assert_eq!(title.inner_html(), "Title");
+    ///
+    /// let err = html.select_required(&Selector::parse("h2.subtitle").unwrap()).unwrap_err();
+    /// assert_eq!(err.selector(), "h2.subtitle");
+    /// ```
+    pub fn select_required(&self, selector: &Selector) -> Result<ElementRef<'_>, MissingElement> {
+        self.select_first(selector)
+            .ok_or_else(|| MissingElement::new(selector))
     }
-}
 
-impl<'a, 'b> Iterator for Select<'a, 'b> {
-    type Item = ElementRef<'a>;
+    /// Returns an iterator over copies of the elements matching a selector, each as the root
+    /// element of its own fragment.
+    ///
+    /// Since the copies do not borrow this document, they can be sent to other threads for
+    /// processing when the `atomic` feature is enabled. See [`ElementRef::to_fragment`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment("<ul><li>a</li><li>b</li></ul>");
+    /// let items = html
+    ///     .select_owned_subtrees(&Selector::parse("li").unwrap())
+    ///     .collect::<Vec<Html>>();
+    /// drop(html);
+    ///
+    /// assert_eq!(items[1].root_element().html(), "<li>b</li>");
+    /// ```
+    pub fn select_owned_subtrees<'a, 'b>(
+        &'a self,
+        selector: &'b Selector,
+    ) -> impl Iterator<Item = Html> + 'b
+    where
+        'a: 'b,
+    {
+        self.select(selector).map(|element| element.to_fragment())
+    }
 
-    fn next(&mut self) -> Option<ElementRef<'a>> {
-        for node in self.inner.by_ref() {
-            if let Some(element) = ElementRef::wrap(node) {
-                if element.parent().is_some()
-                    && self.selector.matches_with_scope_and_cache(
-                    &element,
-                    None,
-                    &mut self.nth_index_cache,
-                )
-                {
-                    return Some(element);
-                }
-            }
-        }
-        None
+    /// Returns an iterator over elements matching a selector which lie between `start` and
+    /// `end`, in document order.
+    ///
+    /// The elements yielded are those entirely after `start` and before `end`, so neither the
+    /// descendants of `start` nor the ancestors of `end` are included. This allows treating
+    /// the content between two marker elements, e.g. `<hr>`s, as a virtual container.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` and `end` do not belong to this document, if `start` does not precede
+    /// `end`, or if `end` is a descendant of `start`, so that no element lies between them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment("<p>1</p><hr><p>2</p><p>3</p><hr><p>4</p>");
+    /// let rules = html.select(&Selector::parse("hr").unwrap()).collect::<Vec<_>>();
+    ///
+    /// let section = html
+    ///     .select_between(rules[0], rules[1], &Selector::parse("p").unwrap())
+    ///     .map(|p| p.inner_html())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(section, ["2", "3"]);
+    /// ```
+    pub fn select_between<'a, 'b>(
+        &'a self,
+        start: ElementRef<'a>,
+        end: ElementRef<'a>,
+        selector: &'b Selector,
+    ) -> impl Iterator<Item = ElementRef<'a>> + 'b
+    where
+        'a: 'b,
+    {
+        assert!(
+            std::ptr::eq(start.tree(), &self.tree) && std::ptr::eq(end.tree(), &self.tree),
+            "start and end must belong to this document"
+        );
+        assert!(
+            start.cmp_document_order(&end) == Some(std::cmp::Ordering::Less),
+            "start must precede end"
+        );
+        assert!(!start.contains(&end), "end must not be inside start");
+
+        let end_ancestors = end.ancestors().map(|node| node.id()).collect::<Vec<_>>();
+        let scope = self.scope();
+        let mut nth_index_cache = NthIndexCache::default();
+
+        self.tree
+            .root()
+            .traverse()
+            .skip_while(move |edge| *edge != Edge::Close(*start))
+            .skip(1)
+            .take_while(move |edge| *edge != Edge::Open(*end))
+            .filter_map(|edge| match edge {
+                Edge::Open(node) => ElementRef::wrap(node),
+                Edge::Close(_) => None,
+            })
+            .filter(move |element| !end_ancestors.contains(&element.id()))
+            .filter(move |element| {
+                selector.matches_with_scope_and_cache(element, scope, &mut nth_index_cache)
+            })
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let (_lower, upper) = self.inner.size_hint();
+    /// Returns an iterator over elements matching a selector, grouped into batches of
+    /// `chunk_size` elements.
+    ///
+    /// Every batch but the last holds exactly `chunk_size` elements; the last one may be smaller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn select_chunks<'a, 'b>(
+        &'a self,
+        selector: &'b Selector,
+        chunk_size: usize,
+    ) -> impl Iterator<Item = Vec<ElementRef<'a>>> + 'b
+    where
+        'a: 'b,
+    {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
 
-        (0, upper)
+        let mut select = self.select(selector);
+        std::iter::from_fn(move || {
+            let chunk = select.by_ref().take(chunk_size).collect::<Vec<_>>();
+            (!chunk.is_empty()).then_some(chunk)
+        })
     }
-}
 
-impl<'a, 'b> DoubleEndedIterator for Select<'a, 'b> {
-    fn next_back(&mut self) -> Option<Self::Item> {
-        for node in self.inner.by_ref().rev() {
-            if let Some(element) = ElementRef::wrap(node) {
-                if element.parent().is_some()
-                    && self.selector.matches_with_scope_and_cache(
-                    &element,
-                    None,
-                    &mut self.nth_index_cache,
-                )
-                {
-                    return Some(element);
-                }
-            }
-        }
-        None
+    /// Builds an index for looking up elements by ID in constant time.
+    ///
+    /// Building the index traverses the whole document once, which pays off when looking up
+    /// many IDs compared to selecting each of them with an `#id` selector. As in browsers, only
+    /// the first element in document order is indexed for duplicate IDs.
+    pub fn build_id_index(&self) -> IdIndex<'_> {
+        IdIndex::new(self)
     }
-}
 
-impl FusedIterator for Select<'_, '_> {}
+    /// Builds an index for looking up elements by class name.
+    ///
+    /// Building the index traverses the whole document once and allocates a list per class, so
+    /// it is only worthwhile when looking up many classes in the same document.
+    pub fn build_class_index(&self) -> ClassIndex<'_> {
+        ClassIndex::new(self)
+    }
 
-mod serializable;
-mod tree_sink;
-mod form;
+    /// Returns the elements matching a selector in the same order as [`Html::select`], looking
+    /// up candidates in an index of the document instead of traversing all of it.
+    ///
+    /// The index maps tag names, IDs and classes to elements. It is built by the first call,
+    /// which traverses the whole document once, and reused by later ones, so this pays off when
+    /// running many selectors against the same document. For each selector of the group, only
+    /// the elements having the rarest tag name, ID or class required by its rightmost compound
+    /// selector are matched, e.g. the elements with class `price` for `.product span.price`.
+    /// Selectors like `*` or `[href]` requiring none of them are matched against every element.
+    ///
+    /// Elements created with [`Html::create_element`] or [`Html::import`] are indexed again
+    /// when needed. After changing names, IDs or classes or adding elements through
+    /// [`Html::tree`](Self::tree) directly, call [`Html::build_index`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment(r#"<ul><li class="a">1</li><li>2</li><li class="a">3</li></ul>"#);
+    /// let selector = Selector::parse("ul > .a, #none").unwrap();
+    ///
+    /// let indexed = html.select_indexed(&selector);
+    /// assert_eq!(indexed, html.select(&selector).collect::<Vec<_>>());
+    /// assert_eq!(indexed.len(), 2);
+    /// ```
+    pub fn select_indexed(&self, selector: &Selector) -> Vec<ElementRef<'_>> {
+        let Some(candidates) = self.index.get(&self.tree).candidates(selector) else {
+            return self.select(selector).collect();
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::Html;
-    use super::Selector;
+        let scope = self.scope();
+        let mut nth_index_cache = NthIndexCache::default();
+        candidates
+            .into_iter()
+            .filter_map(|id| self.tree.get(id).and_then(ElementRef::wrap))
+            .filter(|element| {
+                element.parent().is_some()
+                    && selector.matches_with_scope_and_cache(element, scope, &mut nth_index_cache)
+            })
+            .collect()
+    }
 
-    #[test]
-    fn root_element_fragment() {
-        let html = Html::parse_fragment(r#"<a href="http://github.com">1</a>"#);
-        let root_ref = html.root_element();
-        let href = root_ref
-            .select(&Selector::parse("a").unwrap())
-            .next()
-            .unwrap();
-        assert_eq!(href.inner_html(), "1");
-        assert_eq!(href.value().attr("href").unwrap(), "http://github.com");
+    /// Builds the index used by [`Html::select_indexed`] again, after changing the tree directly.
+    pub fn build_index(&mut self) {
+        self.index.invalidate();
+        self.index.get(&self.tree);
     }
 
-    #[test]
-    fn root_element_document_doctype() {
-        let html = Html::parse_document("<!DOCTYPE html>\n<title>abc</title>");
-        let root_ref = html.root_element();
-        let title = root_ref
-            .select(&Selector::parse("title").unwrap())
-            .next()
-            .unwrap();
-        assert_eq!(title.inner_html(), "abc");
+    /// Numbers the elements of the document, so that [`ElementRef::contains`],
+    /// [`ElementRef::is_child_of`] and [`ElementRef::cmp_document_order`] compare two numbers
+    /// instead of walking up the tree.
+    ///
+    /// This pays off when checking many elements against each other in a deep document. Moving
+    /// nodes with [`Html::detach`], [`Html::append_child`], [`Html::insert_before`] or
+    /// [`Html::insert_after`] drops the numbers, and the checks walk the tree again until this is
+    /// called again. Nodes added afterwards are not numbered, so checks involving them walk the
+    /// tree too. After rearranging the tree directly, this must be called again, since the
+    /// numbers would give wrong answers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let mut html = Html::parse_fragment(r#"<form><p><input></p></form><input>"#);
+    /// html.build_order_index();
+    ///
+    /// let form = html.select(&Selector::parse("form").unwrap()).next().unwrap();
+    /// let inputs = html.select(&Selector::parse("input").unwrap()).collect::<Vec<_>>();
+    /// assert!(inputs[0].is_child_of(&form));
+    /// assert!(!inputs[1].is_child_of(&form));
+    /// ```
+    pub fn build_order_index(&mut self) {
+        self.order.build(&mut self.tree);
     }
 
-    #[test]
-    fn root_element_document_comment() {
-        let html = Html::parse_document("<!-- comment --><title>abc</title>");
-        let root_ref = html.root_element();
-        let title = root_ref
-            .select(&Selector::parse("title").unwrap())
-            .next()
-            .unwrap();
-        assert_eq!(title.inner_html(), "abc");
+    /// Returns a wrapper memoizing the elements matching each selector, for running the same
+    /// selectors repeatedly without traversing the document each time.
+    ///
+    /// The number of cached selectors is unlimited; see
+    /// [`cached_with_limit`](Self::cached_with_limit) to bound it.
+    pub fn cached(&self) -> CachedHtml<'_> {
+        CachedHtml::new(self, usize::MAX)
     }
 
-    #[test]
-    fn select_is_reversible() {
-        let html = Html::parse_document("<p>element1</p><p>element2</p><p>element3</p>");
-        let selector = Selector::parse("p").unwrap();
-        let result: Vec<_> = html
-            .select(&selector)
-            .rev()
-            .map(|e| e.inner_html())
-            .collect();
-        assert_eq!(result, vec!["element3", "element2", "element1"]);
+    /// Like [`cached`](Self::cached), but caching at most `max_entries` selectors, evicting the
+    /// least recently used one when full.
+    ///
+    /// The elements matching the latest selector are always kept, so a limit of zero behaves
+    /// like a limit of one.
+    pub fn cached_with_limit(&self, max_entries: usize) -> CachedHtml<'_> {
+        CachedHtml::new(self, max_entries)
     }
 
-    #[test]
-    fn select_has_a_size_hint() {
-        let html = Html::parse_document("<p>element1</p><p>element2</p><p>element3</p>");
-        let selector = Selector::parse("p").unwrap();
-        let (lower, upper) = html.select(&selector).size_hint();
-        assert_eq!(lower, 0);
-        assert_eq!(upper, Some(10));
+    /// Returns an iterator over forms.
+    pub fn forms(&self) -> Vec<Form<'_>> {
+        self.select(&Selector::parse("form").unwrap())
+            .map(|form| Form::wrap(self.root_element(), form))
+            .collect()
+    }
+
+    /// Returns the values of the JSON-LD scripts, i.e. `<script type="application/ld+json">`, in
+    /// document order.
+    ///
+    /// Scripts whose contents are not valid JSON are skipped. Use [`Html::json_ld_with_errors`]
+    /// to find out about them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_document(r#"
+    ///     <script type="application/ld+json">{"@type": "Product", "name": "Widget"}</script>
+    /// "#);
+    ///
+    /// let values = html.json_ld();
+    /// assert_eq!(values[0]["name"], "Widget");
+    /// ```
+    #[cfg(feature = "serde_json")]
+    pub fn json_ld(&self) -> Vec<serde_json::Value> {
+        self.json_ld_with_errors(&mut Vec::new())
+    }
+
+    /// Returns the values of the JSON-LD scripts like [`Html::json_ld`], adding an error to
+    /// `errors` for every script which was skipped because it is not valid JSON.
+    #[cfg(feature = "serde_json")]
+    pub fn json_ld_with_errors(
+        &self,
+        errors: &mut Vec<serde_json::Error>,
+    ) -> Vec<serde_json::Value> {
+        self.select(&Selector::parse("script[type]").unwrap())
+            .filter(|script| {
+                script
+                    .attr("type")
+                    .is_some_and(|ty| ty.trim().eq_ignore_ascii_case("application/ld+json"))
+            })
+            .filter_map(|script| {
+                serde_json::from_str(&script.text().collect::<String>())
+                    .map_err(|err| errors.push(err))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Returns the top-level microdata items, i.e. the elements with an `itemscope` but without
+    /// an `itemprop` attribute, in document order.
+    ///
+    /// Properties are collected following the HTML microdata processing model, including those
+    /// of elements referenced by `itemref`. URL values are returned as written, without
+    /// resolving them against the document's base URL.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_document(r#"
+    ///     <div itemscope itemtype="https://schema.org/Person">
+    ///         <span itemprop="name">Ada Lovelace</span>
+    ///         <a itemprop="url" href="/ada">Profile</a>
+    ///     </div>
+    /// "#);
+    ///
+    /// let items = html.microdata();
+    /// assert_eq!(items[0].value("name").unwrap().as_str(), Some("Ada Lovelace"));
+    /// assert_eq!(items[0].value("url").unwrap().as_str(), Some("/ada"));
+    /// ```
+    pub fn microdata(&self) -> Vec<MicrodataItem<'_>> {
+        microdata::items(self)
+    }
+
+    /// Returns the headings of the document, `h1` to `h6`, in document order.
+    ///
+    /// Each heading is returned with its level, e.g. 2 for `h2`, and its text, with leading and
+    /// trailing whitespace removed and other runs of whitespace replaced by a single space. This
+    /// suits building a table of contents, with the elements giving the start of each section.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_document("<h1>Guide</h1><h2>Install</h2><p>..</p><h3>From\n source</h3>");
+    ///
+    /// let outline = html
+    ///     .outline()
+    ///     .into_iter()
+    ///     .map(|(level, text, _)| (level, text))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     outline,
+    ///     [(1, "Guide".to_owned()), (2, "Install".to_owned()), (3, "From source".to_owned())]
+    /// );
+    /// ```
+    pub fn outline(&self) -> Vec<(u8, String, ElementRef<'_>)> {
+        self.root_element()
+            .select_with_branch(crate::static_selector!("h1, h2, h3, h4, h5, h6"))
+            .map(|(heading, branch)| (branch as u8 + 1, heading.normalized_text(), heading))
+            .collect()
+    }
+
+    /// Returns the URLs of the background images set by the inline styles of the document, in
+    /// document order, together with the elements styled.
+    ///
+    /// The images come from the `url()` values of the last `background` or `background-image`
+    /// declaration of each `style` attribute, as a later declaration of either property
+    /// replaces the images of an earlier one. An element with several backgrounds yields
+    /// several URLs. The URLs are returned as written, without resolving relative ones.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_fragment(r#"
+    ///     <div style="background: url('/a.png') no-repeat, url(b.png)"></div>
+    ///     <p style="color: red; background-image: url(&quot;c.jpg&quot;)"></p>
+    /// "#);
+    ///
+    /// let images = html
+    ///     .background_images()
+    ///     .into_iter()
+    ///     .map(|(url, element)| (url, element.value().name()))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(
+    ///     images,
+    ///     [("/a.png".to_owned(), "div"), ("b.png".to_owned(), "div"), ("c.jpg".to_owned(), "p")]
+    /// );
+    /// ```
+    pub fn background_images(&self) -> Vec<(String, ElementRef<'_>)> {
+        self.root_element()
+            .select_inclusive(crate::static_selector!("[style]"))
+            .flat_map(|element| {
+                let background = style::declarations(element.attr("style").unwrap())
+                    .filter(|(property, _)| {
+                        property.eq_ignore_ascii_case("background")
+                            || property.eq_ignore_ascii_case("background-image")
+                    })
+                    .last();
+                background
+                    .map(|(_, value)| style::urls(value))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |url| (url, element))
+            })
+            .collect()
+    }
+
+    /// Returns the attributes which were repeated in start tags, in document order.
+    ///
+    /// These are only recorded by [`Html::parse_document_with_duplicate_attributes`] and
+    /// [`Html::parse_fragment_with_duplicate_attributes`], and empty otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_fragment_with_duplicate_attributes(r#"<a href="/a" HREF='/b'>"#);
+    /// let duplicate = &html.duplicate_attributes()[0];
+    ///
+    /// assert_eq!(duplicate.name, "href");
+    /// assert_eq!(duplicate.kept, "/a");
+    /// assert_eq!(duplicate.discarded, ["/b"]);
+    ///
+    /// let a = html.tree.get(duplicate.element).unwrap();
+    /// assert_eq!(a.value().as_element().unwrap().attr("href"), Some("/a"));
+    /// ```
+    pub fn duplicate_attributes(&self) -> &[DuplicateAttr] {
+        &self.duplicate_attributes
+    }
+
+    /// Returns the element matched by `:scope` in [`Html::select`].
+    fn scope(&self) -> Option<ElementRef<'_>> {
+        self.tree.root().children().find_map(ElementRef::wrap)
+    }
+
+    /// Extracts values from the elements matching a Scrapy-style selector list.
+    ///
+    /// Each selector of the list may end in one of the pseudo-elements
+    ///
+    /// - `::text`, extracting the text node children of every matched element, or
+    /// - `::attr(name)`, extracting the value of the attribute `name` of every matched element
+    ///   which has it.
+    ///
+    /// Selectors without a pseudo-element extract the matched elements themselves. A bare
+    /// pseudo-element, e.g. `::text`, applies to the root element. The values are ordered by
+    /// the document order of their elements.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    /// use scraper::extract::ExtractedValue;
+    ///
+    /// let html = Html::parse_fragment(r#"<h1>Title</h1><a href="/a">A</a><img src="b.png">"#);
+    ///
+    /// assert_eq!(html.extract("h1::text").unwrap(), [ExtractedValue::Text("Title")]);
+    /// assert_eq!(
+    ///     html.extract("a::attr(href), img::attr(src)").unwrap(),
+    ///     [ExtractedValue::Attr("/a"), ExtractedValue::Attr("b.png")],
+    /// );
+    /// ```
+    pub fn extract(&self, css: &str) -> Result<Vec<ExtractedValue<'_>>, SelectorParseError> {
+        Ok(Extraction::parse(css)?.run(self.root_element(), true, self.scope()))
+    }
+
+    /// Returns the elements whose own text contains `needle`, in document order.
+    ///
+    /// Each text node is searched separately, with leading and trailing whitespace removed and
+    /// every other run of whitespace replaced by a single space, as is `needle`. Only the
+    /// element directly containing a matching text node is returned, not its ancestors. Raw
+    /// text, like the contents of `<script>` and `<style>`, is not searched, and an empty
+    /// `needle` matches nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{CaseSensitivity, Html};
+    ///
+    /// let html = Html::parse_fragment(
+    ///     "<nav><a href=/in>Sign\n   in</a> <a href=/up>Sign up</a></nav><p>Please sign in.</p>",
+    /// );
+    ///
+    /// let found = html.find_text("sign in", CaseSensitivity::CaseSensitive);
+    /// assert_eq!(found.len(), 1);
+    /// assert_eq!(found[0].value().name(), "p");
+    ///
+    /// let found = html.find_text("sign in", CaseSensitivity::AsciiCaseInsensitive);
+    /// assert_eq!(found.len(), 2);
+    /// assert_eq!(found[0].attr("href"), Some("/in"));
+    /// assert_eq!(found[1].value().name(), "p");
+    /// ```
+    pub fn find_text(
+        &self,
+        needle: &str,
+        case_sensitivity: CaseSensitivity,
+    ) -> Vec<ElementRef<'_>> {
+        let normalize = |text: &str| {
+            let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            match case_sensitivity {
+                CaseSensitivity::CaseSensitive => text,
+                CaseSensitivity::AsciiCaseInsensitive => text.to_ascii_lowercase(),
+            }
+        };
+        let needle = normalize(needle);
+        if needle.is_empty() {
+            return Vec::new();
+        }
+
+        self.tree
+            .root()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|element| {
+                element.children().any(|child| {
+                    child
+                        .value()
+                        .as_text()
+                        .is_some_and(|text| !text.is_raw() && normalize(text).contains(&needle))
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the root `<html>` element.
+    pub fn root_element(&self) -> ElementRef<'_> {
+        let root_node = self
+            .tree
+            .root()
+            .children()
+            .find(|child| child.value().is_element())
+            .expect("html node missing");
+        ElementRef::wrap(root_node).unwrap()
+    }
+
+    /// Returns the doctype declared by the document, if any.
+    ///
+    /// Identifiers missing from the declaration are empty strings, so `<!DOCTYPE html>` has an
+    /// empty public and system ID. Fragments never have a doctype.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_document(r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 4.01//EN"><p>Hi"#);
+    /// let doctype = html.doctype().unwrap();
+    ///
+    /// assert_eq!(doctype.name(), "html");
+    /// assert_eq!(doctype.public_id(), "-//W3C//DTD HTML 4.01//EN");
+    /// assert_eq!(doctype.system_id(), "");
+    /// assert!(!html.is_html5_doctype());
+    /// ```
+    pub fn doctype(&self) -> Option<&Doctype> {
+        self.tree
+            .root()
+            .children()
+            .find_map(|child| child.value().as_doctype())
+    }
+
+    /// Returns the processing instructions of the document, like `<?xml-stylesheet href="a.css"?>`,
+    /// in document order.
+    ///
+    /// Only documents parsed with `Html::parse_xml` contain them, including the XML declaration
+    /// as a processing instruction with the target `xml`. The HTML parser reads `<?...>` as a
+    /// comment instead.
+    pub fn processing_instructions(&self) -> Vec<&ProcessingInstruction> {
+        self.tree
+            .root()
+            .descendants()
+            .filter_map(|node| node.value().as_processing_instruction())
+            .collect()
+    }
+
+    /// Returns true if the document declares the HTML5 doctype, `<!DOCTYPE html>`, without any
+    /// public or system ID.
+    ///
+    /// The name is case-insensitive, since the parser lowercases it.
+    pub fn is_html5_doctype(&self) -> bool {
+        self.doctype().is_some_and(|doctype| {
+            doctype.name() == "html"
+                && doctype.public_id().is_empty()
+                && doctype.system_id().is_empty()
+        })
+    }
+
+    /// Serializes the document into HTML, like [`Html::html`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let src = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01//EN" "http://www.w3.org/TR/html4/strict.dtd"><html><head></head><body>Hi</body></html>"#;
+    /// assert_eq!(Html::parse_document(src).outer_html(), src);
+    ///
+    /// assert_eq!(Html::parse_fragment("<p>a</p>b").outer_html(), "<p>a</p>b");
+    /// ```
+    pub fn outer_html(&self) -> String {
+        self.html()
+    }
+
+    /// Serializes the entire document into HTML.
+    ///
+    /// The doctype of a document keeps its public and system identifiers, and a fragment is
+    /// serialized without the `<html>` element wrapping its contents, so that parsing the
+    /// result the same way yields the same tree.
+    pub fn html(&self) -> String {
+        self.html_with(SerializeOptions::default())
+    }
+
+    /// Serializes the entire document into HTML like [`Html::html`], with the given options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::html::SerializeOptions;
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_fragment("<p>a<!-- note -->b</p>");
+    /// let options = SerializeOptions { include_comments: false };
+    ///
+    /// assert_eq!(html.html_with(options), "<p>ab</p>");
+    /// ```
+    pub fn html_with(&self, options: SerializeOptions) -> String {
+        let mut buf = Vec::new();
+        self.serialize_filtered_with(&mut buf, |_| true, options)
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// Serializes the document into HTML like [`Html::html`], writing it to `writer` and leaving
+    /// out the elements for which `keep` returns false, together with their contents.
+    ///
+    /// This sanitizes the output without modifying the document or building the HTML in memory.
+    /// Errors writing to `writer` are returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let html = Html::parse_fragment(r#"<p>Hi<script>track()</script></p><div class="ad"><b>Buy</b></div>"#);
+    ///
+    /// let mut output = Vec::new();
+    /// html.serialize_filtered_to(&mut output, |element| {
+    ///     element.value().name() != "script" && !element.value().classes().any(|class| class == "ad")
+    /// })
+    /// .unwrap();
+    /// assert_eq!(String::from_utf8(output).unwrap(), "<p>Hi</p>");
+    /// ```
+    pub fn serialize_filtered_to<W: io::Write, F: Fn(ElementRef) -> bool>(
+        &self,
+        writer: &mut W,
+        keep: F,
+    ) -> io::Result<()> {
+        self.serialize_filtered_with(writer, keep, SerializeOptions::default())
+    }
+
+    fn serialize_filtered_with<W: io::Write, F: Fn(ElementRef) -> bool>(
+        &self,
+        writer: &mut W,
+        keep: F,
+        options: SerializeOptions,
+    ) -> io::Result<()> {
+        let opts = SerializeOpts {
+            scripting_enabled: false,
+            traversal_scope: serialize::TraversalScope::IncludeNode,
+            create_missing_parent: false,
+        };
+        let mut serializer = HtmlSerializer::new(writer, opts);
+        let root = self.tree.root();
+        // The element wrapping a fragment is not part of it.
+        let root = match root.value() {
+            Node::Fragment => *self.root_element(),
+            _ => root,
+        };
+        crate::node::serializable::serialize_filtered(
+            root,
+            &mut serializer,
+            serialize::TraversalScope::ChildrenOnly(None),
+            |node| {
+                (options.include_comments || !node.value().is_comment())
+                    && ElementRef::wrap(node).is_none_or(&keep)
+            },
+        )
+    }
+
+    /// Removes all comments from the document, returning how many were removed.
+    ///
+    /// Text on both sides of a removed comment is merged into a single text node, as if the
+    /// comment had never been parsed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let mut html = Html::parse_document("<!-- a --><p>x<!-- b -->y<!--[if IE]><b>z</b><![endif]--></p>");
+    ///
+    /// assert_eq!(html.strip_comments(), 3);
+    /// assert_eq!(html.html(), "<html><head></head><body><p>xy</p></body></html>");
+    /// ```
+    pub fn strip_comments(&mut self) -> usize {
+        let comments = self
+            .tree
+            .root()
+            .descendants()
+            .filter(|node| node.value().is_comment())
+            .map(|node| node.id())
+            .collect::<Vec<_>>();
+
+        for &id in &comments {
+            let comment = self.tree.get(id).unwrap();
+            let text_id = |node: Option<NodeRef<Node>>| {
+                node.filter(|node| node.value().is_text())
+                    .map(|node| node.id())
+            };
+            let adjacent_texts =
+                text_id(comment.prev_sibling()).zip(text_id(comment.next_sibling()));
+            self.tree.get_mut(id).unwrap().detach();
+
+            if let Some((prev, next)) = adjacent_texts {
+                let mut next = self.tree.get_mut(next).unwrap();
+                next.detach();
+                let text = match next.value() {
+                    Node::Text(text) => text.text.clone(),
+                    _ => unreachable!(),
+                };
+                if let Node::Text(prev) = self.tree.get_mut(prev).unwrap().value() {
+                    prev.text.push_tendril(&text);
+                }
+            }
+        }
+
+        comments.len()
+    }
+
+    /// Declares `charset` as the character encoding of the document, e.g. after decoding it to
+    /// UTF-8 for storage.
+    ///
+    /// Updates every `<meta charset>` and `<meta http-equiv="Content-Type">`, keeping the MIME
+    /// type of the latter. If there are none, a `<meta charset>` is inserted at the start of
+    /// `<head>`. Fragments without a `<head>` are left unchanged in that case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Html;
+    ///
+    /// let mut html = Html::parse_document(
+    ///     r#"<head><meta http-equiv="content-type" content="text/html; charset=iso-8859-1"></head>"#,
+    /// );
+    /// html.set_charset_meta("utf-8");
+    /// assert_eq!(
+    ///     html.html(),
+    ///     r#"<html><head><meta http-equiv="content-type" content="text/html; charset=utf-8"></head><body></body></html>"#,
+    /// );
+    ///
+    /// let mut html = Html::parse_document("<title>T</title>");
+    /// html.set_charset_meta("utf-8");
+    /// assert_eq!(
+    ///     html.html(),
+    ///     r#"<html><head><meta charset="utf-8"><title>T</title></head><body></body></html>"#,
+    /// );
+    /// ```
+    pub fn set_charset_meta(&mut self, charset: &str) {
+        self.index.invalidate();
+        let metas = self
+            .select(&Selector::parse("meta[charset], meta[http-equiv][content]").unwrap())
+            .map(|meta| meta.id())
+            .collect::<Vec<_>>();
+
+        let mut declared = false;
+        for id in metas {
+            let mut node = self.tree.get_mut(id).unwrap();
+            let meta = node.value().as_element_mut().unwrap();
+            if meta.attr("charset").is_some() {
+                meta.set_attr("charset", charset);
+                declared = true;
+            } else if meta
+                .attr("http-equiv")
+                .is_some_and(|equiv| equiv.trim().eq_ignore_ascii_case("content-type"))
+            {
+                let content = meta.attr("content").unwrap_or_default();
+                let mime = content.split(';').next().unwrap_or_default().trim();
+                let mime = if mime.is_empty() { "text/html" } else { mime };
+                let content = format!("{mime}; charset={charset}");
+                meta.set_attr("content", &content);
+                declared = true;
+            }
+        }
+        if declared {
+            return;
+        }
+
+        let Some(head) = self
+            .select(&Selector::parse("head").unwrap())
+            .next()
+            .map(|head| head.id())
+        else {
+            return;
+        };
+        let meta = self.create_element("meta");
+        let mut node = self.tree.get_mut(meta).unwrap();
+        node.value()
+            .as_element_mut()
+            .unwrap()
+            .set_attr("charset", charset);
+        self.tree.get_mut(head).unwrap().prepend_id(meta);
+    }
+
+    /// Copies `element` and its descendants, typically from another document, into this
+    /// document, returning the ID of the copy.
+    ///
+    /// The copy is detached: it is not part of the tree until it is placed with
+    /// [`Html::append_child`], [`Html::insert_before`] or [`Html::insert_after`]. Since
+    /// [`Html::select`] visits elements in the order they were added to the document, it finds
+    /// imported elements after all parsed ones; select from [`Html::root_element`] to find them
+    /// in document order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let page = Html::parse_document("<article><h1>News</h1><p>Text</p></article>");
+    /// let article = page.select(&Selector::parse("article").unwrap()).next().unwrap();
+    ///
+    /// let mut digest = Html::parse_document("<main><hr></main>");
+    /// let main = digest.select(&Selector::parse("main").unwrap()).next().unwrap().id();
+    /// let copy = digest.import(article);
+    /// digest.append_child(main, copy);
+    ///
+    /// assert_eq!(
+    ///     digest.root_element().html(),
+    ///     "<html><head></head><body><main><hr><article><h1>News</h1><p>Text</p></article></main></body></html>",
+    /// );
+    /// ```
+    pub fn import(&mut self, element: ElementRef) -> NodeId {
+        self.index.invalidate();
+        self.copy_subtree(*element, None)
+    }
+
+    /// Copies `node` and its descendants, which may belong to another tree, into this one,
+    /// returning the ID of the copy of `node`.
+    ///
+    /// The copy is appended to the children of `parent`, or left detached without one.
+    pub(crate) fn copy_subtree(&mut self, node: NodeRef<Node>, parent: Option<NodeId>) -> NodeId {
+        let value = node.value().clone();
+        let root = match parent {
+            Some(parent) => self.tree.get_mut(parent).unwrap().append(value).id(),
+            None => self.tree.orphan(value).id(),
+        };
+        let mut parent = root;
+        for edge in node.traverse().skip(1) {
+            parent = match edge {
+                Edge::Open(child) => {
+                    let mut parent = self.tree.get_mut(parent).unwrap();
+                    parent.append(child.value().clone()).id()
+                }
+                Edge::Close(child) if child == node => break,
+                Edge::Close(_) => self.tree.get(parent).unwrap().parent().unwrap().id(),
+            };
+        }
+        root
+    }
+
+    /// Creates an element in the HTML namespace without attributes, returning its ID.
+    ///
+    /// Like an imported element, the new element is detached until it is placed with
+    /// [`Html::append_child`], [`Html::insert_before`] or [`Html::insert_after`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let mut html = Html::parse_fragment("<p>Hello</p>");
+    /// let p = html.select(&Selector::parse("p").unwrap()).next().unwrap().id();
+    /// let strong = html.create_element("strong");
+    /// let text = html.create_text(", world & all");
+    /// html.append_child(strong, text);
+    /// html.append_child(p, strong);
+    ///
+    /// assert_eq!(html.root_element().inner_html(), "<p>Hello<strong>, world &amp; all</strong></p>");
+    /// ```
+    pub fn create_element(&mut self, name: &str) -> NodeId {
+        self.index.invalidate();
+        let name = QualName::new(None, ns!(html), LocalName::from(name));
+        let element = Element::new(name, Vec::new());
+        self.tree.orphan(Node::Element(element)).id()
+    }
+
+    /// Creates a text node, returning its ID.
+    ///
+    /// The text is escaped when serialized. Like [`Html::create_element`], the node is detached
+    /// until it is placed.
+    pub fn create_text(&mut self, text: &str) -> NodeId {
+        self.tree.orphan(Node::Text(Text::new(text))).id()
+    }
+
+    /// Detaches `node` and its descendants from the tree, e.g. to drop unwanted elements.
+    ///
+    /// The node keeps its ID, so it can be placed again later.
+    pub fn detach(&mut self, node: NodeId) {
+        self.order.invalidate(&mut self.tree);
+        self.tree.get_mut(node).unwrap().detach();
+    }
+
+    /// Moves the node `child` to the end of the children of `parent`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `child` is `parent` or one of its ancestors. Both IDs must belong to this
+    /// document.
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        self.assert_movable(child, parent);
+        self.order.invalidate(&mut self.tree);
+        self.tree.get_mut(parent).unwrap().append_id(child);
+    }
+
+    /// Moves `node` to just before its new sibling `sibling`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sibling` has no parent, or if `node` is `sibling` or one of its ancestors.
+    /// Both IDs must belong to this document.
+    pub fn insert_before(&mut self, sibling: NodeId, node: NodeId) {
+        self.assert_movable(node, sibling);
+        self.order.invalidate(&mut self.tree);
+        self.tree.get_mut(node).unwrap().detach();
+        self.tree.get_mut(sibling).unwrap().insert_id_before(node);
+    }
+
+    /// Moves `node` to just after its new sibling `sibling`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sibling` has no parent, or if `node` is `sibling` or one of its ancestors.
+    /// Both IDs must belong to this document.
+    pub fn insert_after(&mut self, sibling: NodeId, node: NodeId) {
+        self.assert_movable(node, sibling);
+        self.order.invalidate(&mut self.tree);
+        self.tree.get_mut(node).unwrap().detach();
+        self.tree.get_mut(sibling).unwrap().insert_id_after(node);
+    }
+
+    /// Asserts that moving `node` next to or into `target` would not make it its own ancestor.
+    fn assert_movable(&self, node: NodeId, target: NodeId) {
+        let target = self.tree.get(target).unwrap();
+        assert!(
+            target.id() != node && !target.ancestors().any(|ancestor| ancestor.id() == node),
+            "cannot move a node into itself"
+        );
+    }
+}
+
+/// Iterator over elements matching a selector.
+///
+/// Elements are yielded in the order in which their nodes were created, which is document order
+/// unless the tree was changed after parsing. If every selector of the group requires an ancestor
+/// with an ID, like `#comments .author`, only the descendants of the elements with such an ID
+/// are matched against it.
+pub struct Select<'a, 'b> {
+    inner: Candidates<'a>,
+    scope: Option<ElementRef<'a>>,
+    selector: &'b Selector,
+    nth_index_cache: SelectCache<'b>,
+    /// The statistics recorded while matching, for [`Html::select_profiled`].
+    profile: Option<&'b mut MatchProfile>,
+}
+
+/// The nodes visited by [`Select`].
+#[derive(Debug, Clone)]
+enum Candidates<'a> {
+    /// All nodes of the tree.
+    All(Nodes<'a, Node>),
+    /// The nodes of the tree which descend from an element with an ID every selector requires
+    /// of an ancestor.
+    Anchored(Nodes<'a, Node>, HashSet<NodeId>),
+}
+
+impl<'a> Iterator for Candidates<'a> {
+    type Item = NodeRef<'a, Node>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::All(nodes) => nodes.next(),
+            Self::Anchored(nodes, subtrees) => nodes.find(|node| subtrees.contains(&node.id())),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::All(nodes) => nodes.size_hint(),
+            Self::Anchored(nodes, _) => (0, nodes.size_hint().1),
+        }
+    }
+}
+
+impl DoubleEndedIterator for Candidates<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::All(nodes) => nodes.next_back(),
+            Self::Anchored(nodes, subtrees) => nodes.rfind(|node| subtrees.contains(&node.id())),
+        }
+    }
+}
+
+impl fmt::Debug for Select<'_, '_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("Select")
+            .field("inner", &self.inner)
+            .field("scope", &self.scope)
+            .field("selector", &self.selector)
+            .field("nth_index_cache", &"..")
+            .field("profile", &self.profile)
+            .finish()
+    }
+}
+
+/// The clone does not share the caches of the original iterator, even if it was created with a
+/// [`MatchContext`]. This does not change the elements it yields.
+impl Clone for Select<'_, '_> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            scope: self.scope,
+            selector: self.selector,
+            nth_index_cache: SelectCache::default(),
+            profile: None,
+        }
+    }
+}
+
+impl<'a> Select<'a, '_> {
+    /// Returns the first matched element whose text satisfies `pred`.
+    ///
+    /// The text passed to `pred` is that of [`ElementRef::text`] with whitespace normalized:
+    /// leading and trailing whitespace is removed, and every other run of it is replaced by a
+    /// single space. No elements after the one returned are matched.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_document("<p>Subtotal: 10</p><p>\n  Total:\t12  </p>");
+    /// let p = Selector::parse("p").unwrap();
+    ///
+    /// let total = html.select(&p).find_text(|text| text.starts_with("Total:")).unwrap();
+    /// assert_eq!(total.inner_html(), "\n  Total:\t12  ");
+    /// ```
+    pub fn find_text<F: Fn(&str) -> bool>(mut self, pred: F) -> Option<ElementRef<'a>> {
+        self.find(|element| pred(&element.normalized_text()))
+    }
+
+    /// Returns the element of `node` if it matches the selector.
+    fn matching_element(&mut self, node: NodeRef<'a, Node>) -> Option<ElementRef<'a>> {
+        let element = ElementRef::wrap(node).filter(|element| element.parent().is_some())?;
+        let matched = match &mut self.profile {
+            Some(profile) => self.selector.matches_profiled(
+                &element,
+                self.scope,
+                self.nth_index_cache.get(),
+                profile,
+            ),
+            None => self.selector.matches_with_scope_and_cache(
+                &element,
+                self.scope,
+                self.nth_index_cache.get(),
+            ),
+        };
+        matched.then_some(element)
+    }
+}
+
+impl<'a, 'b> Iterator for Select<'a, 'b> {
+    type Item = ElementRef<'a>;
+
+    fn next(&mut self) -> Option<ElementRef<'a>> {
+        while let Some(node) = self.inner.next() {
+            if let Some(element) = self.matching_element(node) {
+                return Some(element);
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_lower, upper) = self.inner.size_hint();
+
+        (0, upper)
+    }
+}
+
+impl<'a, 'b> DoubleEndedIterator for Select<'a, 'b> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.inner.next_back() {
+            if let Some(element) = self.matching_element(node) {
+                return Some(element);
+            }
+        }
+        None
+    }
+}
+
+impl FusedIterator for Select<'_, '_> {}
+
+mod cached;
+mod depth_limit;
+mod duplicates;
+pub mod form;
+mod fragment_context;
+mod hooked_sink;
+mod index;
+mod interner;
+pub mod microdata;
+mod order;
+mod parse_options;
+mod serializable;
+mod source_location;
+mod tree_sink;
+#[cfg(feature = "xml")]
+mod xml;
+
+pub use self::cached::CachedHtml;
+pub use self::duplicates::DuplicateAttr;
+pub use self::fragment_context::FragmentContext;
+use self::index::LazyIndex;
+pub use self::index::{ClassIndex, IdIndex};
+pub use self::interner::Interner;
+use self::order::OrderIndex;
+pub use self::parse_options::ParseOptions;
+pub use self::serializable::SerializeOptions;
+
+#[cfg(test)]
+mod tests {
+    use html5ever::tree_builder::QuirksMode;
+
+    use super::Selector;
+    use super::{FragmentContext, Html};
+    use crate::node::ProcessingInstruction;
+    use crate::selector::MatchContext;
+    use crate::{CaseSensitivity, ElementRef};
+
+    #[test]
+    fn root_element_fragment() {
+        let html = Html::parse_fragment(r#"<a href="http://github.com">1</a>"#);
+        let root_ref = html.root_element();
+        let href = root_ref
+            .select(&Selector::parse("a").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(href.inner_html(), "1");
+        assert_eq!(href.value().attr("href").unwrap(), "http://github.com");
+    }
+
+    #[test]
+    fn root_element_document_doctype() {
+        let html = Html::parse_document("<!DOCTYPE html>\n<title>abc</title>");
+        let root_ref = html.root_element();
+        let title = root_ref
+            .select(&Selector::parse("title").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(title.inner_html(), "abc");
+    }
+
+    #[test]
+    fn doctype() {
+        let html5 = Html::parse_document("<!doctype HTML>\n<title>a</title>");
+        let doctype = html5.doctype().unwrap();
+        assert_eq!(
+            (doctype.name(), doctype.public_id(), doctype.system_id()),
+            ("html", "", "")
+        );
+        assert!(html5.is_html5_doctype());
+        assert_eq!(html5.quirks_mode, QuirksMode::NoQuirks);
+
+        let src = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN" "http://www.w3.org/TR/html4/loose.dtd"><html><head></head><body></body></html>"#;
+        let transitional = Html::parse_document(src);
+        let doctype = transitional.doctype().unwrap();
+        assert_eq!(
+            (doctype.name(), doctype.public_id(), doctype.system_id()),
+            (
+                "html",
+                "-//W3C//DTD HTML 4.01 Transitional//EN",
+                "http://www.w3.org/TR/html4/loose.dtd"
+            )
+        );
+        assert!(!transitional.is_html5_doctype());
+        assert_eq!(transitional.quirks_mode, QuirksMode::LimitedQuirks);
+        assert_eq!(transitional.html(), src);
+
+        let legacy = Html::parse_document(r#"<!DOCTYPE html SYSTEM "about:legacy-compat">"#);
+        assert_eq!(legacy.doctype().unwrap().system_id(), "about:legacy-compat");
+        assert!(!legacy.is_html5_doctype());
+
+        let none = Html::parse_document("<!-- no doctype --><title>a</title>");
+        assert!(none.doctype().is_none());
+        assert!(!none.is_html5_doctype());
+        assert_eq!(none.quirks_mode, QuirksMode::Quirks);
+
+        assert!(Html::parse_fragment("<!DOCTYPE html><p>a</p>")
+            .doctype()
+            .is_none());
+    }
+
+    #[test]
+    fn import() {
+        let first = Html::parse_fragment(r#"<div class="card"><h2>A</h2><!-- c -->text</div>"#);
+        let second = Html::parse_fragment("<ul><li>1</li><li>2</li></ul>");
+        let select = |html: &Html, css| {
+            html.select(&Selector::parse(css).unwrap())
+                .next()
+                .unwrap()
+                .id()
+        };
+
+        let mut combined = Html::parse_fragment("<section><hr></section>");
+        let section = select(&combined, "section");
+        let hr = select(&combined, "hr");
+
+        let card = combined.import(
+            first
+                .select(&Selector::parse(".card").unwrap())
+                .next()
+                .unwrap(),
+        );
+        assert_eq!(
+            combined.select(&Selector::parse(".card").unwrap()).count(),
+            0
+        );
+        combined.append_child(section, card);
+        let item = combined.import(
+            second
+                .select(&Selector::parse("li + li").unwrap())
+                .next()
+                .unwrap(),
+        );
+        combined.insert_before(hr, item);
+        let list = combined.import(
+            second
+                .root_element()
+                .first_child()
+                .and_then(ElementRef::wrap)
+                .unwrap(),
+        );
+        combined.insert_after(hr, list);
+
+        assert_eq!(
+            combined.root_element().inner_html(),
+            r#"<section><li>2</li><hr><ul><li>1</li><li>2</li></ul><div class="card"><h2>A</h2><!-- c -->text</div></section>"#
+        );
+        assert_eq!(
+            combined
+                .select(&Selector::parse("section > :last-child h2").unwrap())
+                .count(),
+            1
+        );
+
+        // Moving a placed node detaches it from its old position.
+        combined.insert_before(item, card);
+        combined.append_child(section, item);
+        assert_eq!(
+            combined.root_element().inner_html(),
+            r#"<section><div class="card"><h2>A</h2><!-- c -->text</div><hr><ul><li>1</li><li>2</li></ul><li>2</li></section>"#
+        );
+        assert_eq!(
+            first.root_element().inner_html(),
+            r#"<div class="card"><h2>A</h2><!-- c -->text</div>"#
+        );
+    }
+
+    #[test]
+    fn create_nodes() {
+        let mut html = Html::parse_document(
+            "<body><p>Keep <script>alert(1)</script>this</p><img src=x onerror=y></body>",
+        );
+        let select_ids = |html: &Html, css| {
+            html.select(&Selector::parse(css).unwrap())
+                .map(|element| element.id())
+                .collect::<Vec<_>>()
+        };
+
+        // Replace each script with a placeholder and drop images.
+        for script in select_ids(&html, "script") {
+            let placeholder = html.create_element("span");
+            let text = html.create_text("<removed>");
+            html.append_child(placeholder, text);
+            html.insert_before(script, placeholder);
+            html.detach(script);
+        }
+        for img in select_ids(&html, "img") {
+            html.detach(img);
+        }
+
+        let body = select_ids(&html, "body")[0];
+        let footer = html.create_element("footer");
+        html.append_child(body, footer);
+
+        assert_eq!(
+            html.html(),
+            "<html><head></head><body><p>Keep <span>&lt;removed&gt;</span>this</p><footer></footer></body></html>"
+        );
+        assert!(select_ids(&html, "script, img").is_empty());
+        assert_eq!(select_ids(&html, "p > span:only-child").len(), 1);
+        let footer = html.tree.get(footer).and_then(ElementRef::wrap).unwrap();
+        assert!(footer.value().is_in_html_namespace());
+        assert_eq!(footer.value().name(), "footer");
+    }
+
+    #[test]
+    fn background_images() {
+        let html = Html::parse_document(
+            r#"<body style="BACKGROUND-IMAGE: url(body.png)">
+                <div style="background-image: url(a.png); background: red">a</div>
+                <div style="background: url(b.png); background-image: url(c.png), url(d.png)">b</div>
+                <div style="color: red">c</div>
+                <div style="background-image: url(e.png); color: url(f.png)">d</div>
+            </body>"#,
+        );
+        let images = html
+            .background_images()
+            .into_iter()
+            .map(|(url, element)| (url, element.value().name(), element.text().next()))
+            .collect::<Vec<_>>();
+
+        let owned = |url: &str| url.to_owned();
+        assert_eq!(
+            images,
+            [
+                (owned("body.png"), "body", Some("\n                ")),
+                (owned("c.png"), "div", Some("b")),
+                (owned("d.png"), "div", Some("b")),
+                (owned("e.png"), "div", Some("d")),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xml")]
+    fn processing_instructions() {
+        let document = r#"<?xml version="1.0"?><?xml-stylesheet href="a.css" type="text/css"?><root><?php echo 1; ?><a></a></root>"#;
+        let xml = Html::parse_xml(document);
+
+        let pis = xml
+            .processing_instructions()
+            .iter()
+            .map(|pi| (pi.target(), pi.data()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pis,
+            [
+                ("xml", r#"version="1.0""#),
+                ("xml-stylesheet", r#"href="a.css" type="text/css""#),
+                ("php", "echo 1; "),
+            ]
+        );
+        assert_eq!(xml.html(), document);
+        assert_eq!(Html::parse_xml(&xml.html()).html(), document);
+
+        let root = xml.root_element();
+        assert_eq!(root.html(), "<root><?php echo 1; ?><a></a></root>");
+        assert_eq!(root.html_xhtml(), "<root><?php echo 1; ?><a></a></root>");
+    }
+
+    #[test]
+    fn processing_instructions_html() {
+        let mut html = Html::parse_fragment("<p>a</p>");
+        assert!(html.processing_instructions().is_empty());
+
+        let p = html.root_element().first_child().unwrap().id();
+        html.tree
+            .get_mut(p)
+            .unwrap()
+            .append(crate::Node::ProcessingInstruction(ProcessingInstruction {
+                target: "php".into(),
+                data: "echo 1;".into(),
+            }));
+        assert_eq!(html.processing_instructions().len(), 1);
+        assert_eq!(html.html(), "<p>a<?php echo 1;?></p>");
+    }
+
+    #[test]
+    fn set_charset_meta() {
+        let charset_meta = |src: &str| {
+            let mut html = Html::parse_document(src);
+            html.set_charset_meta("utf-8");
+            html.select(&Selector::parse("head").unwrap())
+                .next()
+                .unwrap()
+                .inner_html()
+        };
+
+        assert_eq!(
+            charset_meta(r#"<meta charset="Shift_JIS"><title>T</title>"#),
+            r#"<meta charset="utf-8"><title>T</title>"#
+        );
+        assert_eq!(
+            charset_meta(
+                r#"<meta name="x" content="y"><meta HTTP-EQUIV="Content-Type" content="application/xhtml+xml;charset=latin1">"#
+            ),
+            r#"<meta name="x" content="y"><meta http-equiv="Content-Type" content="application/xhtml+xml; charset=utf-8">"#
+        );
+        assert_eq!(
+            charset_meta(r#"<meta http-equiv="content-type" content=""><meta charset=x>"#),
+            r#"<meta http-equiv="content-type" content="text/html; charset=utf-8"><meta charset="utf-8">"#
+        );
+        assert_eq!(
+            charset_meta(r#"<meta http-equiv="refresh" content="5"><title>T</title>"#),
+            r#"<meta charset="utf-8"><meta http-equiv="refresh" content="5"><title>T</title>"#
+        );
+        assert_eq!(charset_meta(""), r#"<meta charset="utf-8">"#);
+
+        let mut fragment = Html::parse_fragment("<p>a</p>");
+        fragment.set_charset_meta("utf-8");
+        assert_eq!(fragment.root_element().inner_html(), "<p>a</p>");
+    }
+
+    #[test]
+    fn move_raw_text() {
+        let mut html = Html::parse_fragment(
+            "<script>if (a < b && c) {}</script><div></div><p>x &lt; y&amp;</p>",
+        );
+        let children = html
+            .root_element()
+            .children()
+            .map(|child| child.id())
+            .collect::<Vec<_>>();
+        let texts = html
+            .tree
+            .nodes()
+            .filter(|node| node.value().is_text())
+            .map(|node| (node.id(), node.value().as_text().unwrap().is_raw()))
+            .collect::<Vec<_>>();
+        let [(script_text, true), (p_text, false)] = texts[..] else {
+            panic!("{texts:?}");
+        };
+
+        html.append_child(children[1], script_text);
+        html.append_child(children[0], p_text);
+
+        let expected = "<script>x &lt; y&amp;</script><div>if (a < b && c) {}</div><p></p>";
+        assert_eq!(html.root_element().inner_html(), expected);
+        let mut buf = Vec::new();
+        html.serialize_filtered_to(&mut buf, |_| true).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), expected);
+    }
+
+    #[test]
+    fn raw_text_contexts() {
+        let html = Html::parse_fragment(
+            "<noscript><img src=x></noscript><svg><style>a &amp; b</style></svg><textarea>&lt;</textarea>",
+        );
+        let raw = html
+            .tree
+            .values()
+            .filter_map(crate::Node::as_text)
+            .map(|text| (text.as_str(), text.is_raw()))
+            .collect::<Vec<_>>();
+        assert_eq!(raw, [("<img src=x>", true), ("a & b", false), ("<", false)]);
+        assert_eq!(
+            html.root_element().inner_html(),
+            "<noscript><img src=x></noscript><svg><style>a &amp; b</style></svg><textarea>&lt;</textarea>"
+        );
+    }
+
+    #[test]
+    fn raw_text_scripting_disabled() {
+        let opts = html5ever::driver::ParseOpts {
+            tree_builder: html5ever::tree_builder::TreeBuilderOpts {
+                scripting_enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let html = Html::parse_fragment_with_opts(
+            "<noscript>&lt;img src=x&gt;<b>b</b></noscript>",
+            FragmentContext::Body,
+            opts,
+        );
+        let raw = html
+            .tree
+            .values()
+            .filter_map(crate::Node::as_text)
+            .map(|text| (text.as_str(), text.is_raw()))
+            .collect::<Vec<_>>();
+        assert_eq!(raw, [("<img src=x>", false), ("b", false)]);
+        assert_eq!(
+            html.root_element().inner_html(),
+            "<noscript>&lt;img src=x&gt;<b>b</b></noscript>"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xml")]
+    fn raw_text_xml() {
+        let xml = Html::parse_xml(
+            r#"<html xmlns="http://www.w3.org/1999/xhtml"><script>a &amp;&amp; b</script></html>"#,
+        );
+        let text = xml.tree.values().find_map(crate::Node::as_text).unwrap();
+        assert_eq!(text.as_str(), "a && b");
+        assert!(!text.is_raw());
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot move a node into itself")]
+    fn append_child_into_itself() {
+        let mut html = Html::parse_fragment("<div><p></p></div>");
+        let div = html.root_element().first_child().unwrap();
+        let (div, p) = (div.id(), div.first_child().unwrap().id());
+        html.append_child(p, div);
+    }
+
+    #[test]
+    fn root_element_document_comment() {
+        let html = Html::parse_document("<!-- comment --><title>abc</title>");
+        let root_ref = html.root_element();
+        let title = root_ref
+            .select(&Selector::parse("title").unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(title.inner_html(), "abc");
+    }
+
+    #[test]
+    fn select_is_reversible() {
+        let html = Html::parse_document("<p>element1</p><p>element2</p><p>element3</p>");
+        let selector = Selector::parse("p").unwrap();
+        let result: Vec<_> = html
+            .select(&selector)
+            .rev()
+            .map(|e| e.inner_html())
+            .collect();
+        assert_eq!(result, vec!["element3", "element2", "element1"]);
+    }
+
+    #[test]
+    fn select_has_a_size_hint() {
+        let html = Html::parse_document("<p>element1</p><p>element2</p><p>element3</p>");
+        let selector = Selector::parse("p").unwrap();
+        let (lower, upper) = html.select(&selector).size_hint();
+        assert_eq!(lower, 0);
+        assert_eq!(upper, Some(10));
+    }
+
+    #[test]
+    fn select_text_leading_fragment() {
+        let fragment = Html::parse_fragment("foo<span>bar</span><a>baz</a>qux");
+        let names = |selector: &str| {
+            fragment
+                .select(&Selector::parse(selector).unwrap())
+                .map(|element| element.value().name())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names("span, a"), ["span", "a"]);
+        assert_eq!(names(":scope > *"), ["span", "a"]);
+        assert_eq!(names("span:first-child"), ["span"]);
+        assert_eq!(names("a:last-child"), ["a"]);
+        assert_eq!(names("span + a"), ["a"]);
+        assert!(names("span:only-child").is_empty());
+
+        let text = fragment
+            .select(&Selector::parse(":scope > a").unwrap())
+            .next()
+            .unwrap()
+            .text()
+            .collect::<String>();
+        assert_eq!(text, "baz");
+    }
+
+    #[test]
+    fn parse_fragment_in() {
+        let names = |fragment: &str, context| {
+            Html::parse_fragment_in(fragment, context)
+                .root_element()
+                .descendent_elements()
+                .skip(1)
+                .map(|element| element.value().name().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names("<tr><td>a</td></tr>", FragmentContext::Body),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names("<tbody><tr><td>a</td></tr></tbody>", FragmentContext::Table),
+            ["tbody", "tr", "td"]
+        );
+        assert_eq!(
+            names("<tr><td>a</td></tr>", FragmentContext::Table),
+            ["tbody", "tr", "td"]
+        );
+        assert_eq!(
+            names("<tr><td>a</td></tr>", FragmentContext::TableBody),
+            ["tr", "td"]
+        );
+        assert_eq!(
+            names("<td>a</td><th>b</th>", FragmentContext::TableRow),
+            ["td", "th"]
+        );
+        assert_eq!(
+            names(
+                "<option>a<optgroup><option>b</optgroup>",
+                FragmentContext::Select
+            ),
+            ["option", "optgroup", "option"]
+        );
+        assert_eq!(
+            names("<tr><td>a</td></tr><td>b</td>", FragmentContext::Template),
+            ["tr", "td", "tr", "td"]
+        );
+        assert_eq!(
+            Html::parse_fragment_in("<p>a</p>", FragmentContext::default()),
+            Html::parse_fragment("<p>a</p>")
+        );
+    }
+
+    #[test]
+    fn outline() {
+        let html = Html::parse_document(
+            r#"
+            <h2>Intro</h2>
+            <section><h1 id="main">Main <em>topic</em></h1><h6>Deep</h6></section>
+            <header><h4>  Spaced
+                out </h4></header>
+            "#,
+        );
+        let outline = html.outline();
+
+        let headings = outline
+            .iter()
+            .map(|(level, text, _)| (*level, text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            [
+                (2, "Intro"),
+                (1, "Main topic"),
+                (6, "Deep"),
+                (4, "Spaced out")
+            ]
+        );
+        assert_eq!(outline[1].2.value().id(), Some("main"));
+        assert!(Html::parse_document("<p>none</p>").outline().is_empty());
+    }
+
+    #[test]
+    fn select_profiled() {
+        let html = Html::parse_document(
+            "<title>t</title><ul><li>1</li><li class=odd>2</li><li>3</li></ul><p class=odd>4</p>",
+        );
+        // html, head, title, body, ul, three li and p.
+        let elements = 9;
+
+        for css in [
+            "li",
+            ".odd, li:nth-child(odd)",
+            ":scope > body > *",
+            "ul:has(.odd) li, p",
+            "table",
+        ] {
+            let selector = Selector::parse(css).unwrap();
+            let (matched, profile) = html.select_profiled(&selector);
+            assert_eq!(
+                matched,
+                html.select(&selector).collect::<Vec<_>>(),
+                "{}",
+                css
+            );
+            assert_eq!(profile.elements_visited(), elements, "{}", css);
+            assert_eq!(profile.matches(), matched.len(), "{}", css);
+            assert!(profile.slowest().is_some());
+        }
+
+        let selector = Selector::parse(".odd, li:nth-child(odd)").unwrap();
+        let (_, profile) = html.select_profiled(&selector);
+        let stats = profile
+            .selectors()
+            .iter()
+            .map(|selector| (selector.css(), selector.attempts(), selector.matches()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            stats,
+            [
+                (".odd", elements, 2),
+                ("li:nth-child(2n+1)", elements - 2, 2)
+            ]
+        );
+        assert!(profile.total_time() >= profile.slowest().unwrap().time());
+
+        // Only the descendants of the anchor are candidates, as for `Html::select`.
+        let html = Html::parse_document("<p>1</p><ul id=list><li>2</li><li>3</li></ul>");
+        let (matched, profile) = html.select_profiled(&Selector::parse("#list li").unwrap());
+        assert_eq!(matched.len(), 2);
+        assert_eq!(profile.elements_visited(), 2);
+    }
+
+    #[test]
+    fn select_scope_document() {
+        let html =
+            Html::parse_document("<!DOCTYPE html><title>a</title><div><p>b</p></div><p>c</p>");
+        let names = |selector: &str| {
+            html.select(&Selector::parse(selector).unwrap())
+                .map(|element| element.value().name())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(":scope"), ["html"]);
+        assert_eq!(names(":scope > head"), ["head"]);
+        assert_eq!(names(":scope > *"), ["head", "body"]);
+        assert!(names(":scope > title").is_empty());
+        assert_eq!(names(":scope title"), ["title"]);
+        assert_eq!(names("body p"), ["p", "p"]);
+        assert_eq!(names("body > p"), ["p"]);
+    }
+
+    #[test]
+    fn select_text_leading_fragment_root_element() {
+        let fragment = Html::parse_fragment("foo<span>bar</span><a>baz</a>qux");
+        let root = fragment.root_element();
+        assert_eq!(
+            root.text().collect::<Vec<_>>(),
+            ["foo", "bar", "baz", "qux"]
+        );
+
+        let selector = Selector::parse(":scope > span, :scope > a").unwrap();
+        let names = root
+            .select(&selector)
+            .map(|element| element.value().name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["span", "a"]);
+    }
+
+    #[test]
+    fn select_chunks() {
+        let html = Html::parse_fragment("<p>1</p><p>2</p><p>3</p><p>4</p><p>5</p>");
+        let selector = Selector::parse("p").unwrap();
+        let chunks = html
+            .select_chunks(&selector, 2)
+            .map(|chunk| chunk.iter().map(|p| p.inner_html()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(chunks, [vec!["1", "2"], vec!["3", "4"], vec!["5"]]);
+
+        assert_eq!(html.select_chunks(&selector, 5).count(), 1);
+
+        let selector = Selector::parse("span").unwrap();
+        assert_eq!(html.select_chunks(&selector, 2).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn select_chunks_zero_size() {
+        let html = Html::parse_fragment("<p>1</p>");
+        let selector = Selector::parse("p").unwrap();
+        let _ = html.select_chunks(&selector, 0);
+    }
+
+    #[test]
+    fn select_first_and_required() {
+        let html = Html::parse_fragment("<p>1</p><p>2</p>");
+        let p = Selector::parse("p").unwrap();
+        let span = Selector::parse("div > span.missing").unwrap();
+
+        assert_eq!(html.select_first(&p).unwrap().inner_html(), "1");
+        assert_eq!(html.select_required(&p), Ok(html.select_first(&p).unwrap()));
+        assert_eq!(html.select_first(&span), None);
+
+        let err = html.select_required(&span).unwrap_err();
+        assert_eq!(err.selector(), "div > span.missing");
+        assert_eq!(
+            err.to_string(),
+            "No element matches the selector \"div > span.missing\""
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn json_ld() {
+        let html = Html::parse_document(
+            r#"
+            <head>
+                <script type="application/ld+json">
+                    {"@context": "https://schema.org", "@type": "Article", "headline": "A < B"}
+                </script>
+                <script type="application/ld+json">{"@type": </script>
+                <script type="text/javascript">{"@type": "Ignored"}</script>
+            </head>
+            <body>
+                <script type=" Application/LD+JSON ">[{"@type": "BreadcrumbList"}]</script>
+            </body>
+            "#,
+        );
+
+        let mut errors = Vec::new();
+        let values = html.json_ld_with_errors(&mut errors);
+        assert_eq!(
+            values,
+            [
+                serde_json::json!({
+                    "@context": "https://schema.org",
+                    "@type": "Article",
+                    "headline": "A < B",
+                }),
+                serde_json::json!([{"@type": "BreadcrumbList"}]),
+            ]
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is_eof());
+        assert_eq!(html.json_ld(), values);
+    }
+
+    #[test]
+    fn select_owned_subtrees() {
+        let html = Html::parse_document(
+            r#"<div class="product"><h2>A</h2><template><p>t</p></template></div>
+               <div class="product"><h2>B</h2><!-- c --></div>"#,
+        );
+        let products = html
+            .select_owned_subtrees(&Selector::parse("div.product").unwrap())
+            .collect::<Vec<_>>();
+        let originals = html
+            .select(&Selector::parse("div.product").unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(products.len(), 2);
+        for (product, original) in products.iter().zip(originals) {
+            assert!(product.tree.root().value().is_fragment());
+            assert_eq!(product.root_element().html(), original.html());
+        }
+
+        let h2 = Selector::parse(":scope > h2").unwrap();
+        let names = products
+            .iter()
+            .map(|product| product.select(&h2).next().unwrap().inner_html())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["A", "B"]);
+    }
+
+    #[cfg(feature = "atomic")]
+    #[test]
+    fn send_owned_subtrees() {
+        use std::sync::mpsc;
+        use std::thread;
+
+        let (tx, rx) = mpsc::channel();
+        let consumer = thread::spawn(move || {
+            rx.iter()
+                .map(|item: Html| item.root_element().text().collect::<String>())
+                .collect::<Vec<_>>()
+        });
+
+        let html = Html::parse_fragment("<ul><li>a</li><li>b</li></ul>");
+        for item in html.select_owned_subtrees(&Selector::parse("li").unwrap()) {
+            tx.send(item).unwrap();
+        }
+        drop(tx);
+
+        assert_eq!(consumer.join().unwrap(), ["a", "b"]);
+    }
+
+    #[test]
+    fn select_between() {
+        let html = Html::parse_document(
+            r#"
+            <section id="s1"><h2 id="h1">One</h2><p id="p1"></p></section>
+            <section id="s2"><p id="p2"></p><h2 id="h2">Two</h2><p id="p3"></p></section>
+            <p id="p4"></p>
+            "#,
+        );
+        let by_id = |id: &str| {
+            html.select(&Selector::parse(&format!("#{}", id)).unwrap())
+                .next()
+                .unwrap()
+        };
+        let between = |start: &str, end: &str, selector: &str| {
+            html.select_between(
+                by_id(start),
+                by_id(end),
+                &Selector::parse(selector).unwrap(),
+            )
+            .map(|element| element.value().id().unwrap())
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(between("h1", "h2", "[id]"), ["p1", "p2"]);
+        assert_eq!(between("h1", "p4", "[id]"), ["p1", "s2", "p2", "h2", "p3"]);
+        assert_eq!(between("s1", "p3", "p"), ["p2"]);
+        assert_eq!(between("s2", "p4", "*"), Vec::<&str>::new());
+        assert_eq!(between("p1", "h2", "p:first-child"), ["p2"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "start must precede end")]
+    fn select_between_reversed() {
+        let html = Html::parse_fragment("<hr><hr>");
+        let rules = html
+            .select(&Selector::parse("hr").unwrap())
+            .collect::<Vec<_>>();
+        html.select_between(rules[1], rules[0], &Selector::parse("p").unwrap())
+            .for_each(drop);
+    }
+
+    #[test]
+    #[should_panic(expected = "end must not be inside start")]
+    fn select_between_nested() {
+        let html = Html::parse_fragment("<div><hr></div><p></p>");
+        let div = html
+            .select(&Selector::parse("div").unwrap())
+            .next()
+            .unwrap();
+        let hr = html.select(&Selector::parse("hr").unwrap()).next().unwrap();
+        html.select_between(div, hr, &Selector::parse("p").unwrap())
+            .for_each(drop);
+    }
+
+    #[test]
+    fn html_round_trip() {
+        for src in [
+            "<!DOCTYPE html><html><head></head><body><p>a</p></body></html>",
+            "<!-- first --><!DOCTYPE html SYSTEM \"about:legacy-compat\"><html><head></head><body></body></html><!-- last -->",
+            "<!DOCTYPE html PUBLIC '-//W3C//DTD \"quoted\"//EN'><html><head></head><body></body></html>",
+            "<html><head></head><body></body></html>",
+        ] {
+            assert_eq!(Html::parse_document(src).html(), src);
+        }
+
+        let fragment = Html::parse_fragment("<!-- c -->text<p>a</p><br>");
+        assert_eq!(fragment.html(), "<!-- c -->text<p>a</p><br>");
+        assert_eq!(Html::parse_fragment("").html(), "");
+
+        let options = super::SerializeOptions {
+            include_comments: false,
+        };
+        let html = Html::parse_document(
+            "<!-- c --><!DOCTYPE html SYSTEM \"about:legacy-compat\"><html><head></head><body></body></html>",
+        );
+        assert_eq!(
+            html.html_with(options),
+            "<!DOCTYPE html SYSTEM \"about:legacy-compat\"><html><head></head><body></body></html>"
+        );
+        assert_eq!(fragment.html_with(options), "text<p>a</p><br>");
+    }
+
+    #[test]
+    fn select_with_context() {
+        let rows = (0..20)
+            .map(|i| format!("<tr><td>{i}</td><td>{i}</td><td>{i}</td></tr>"))
+            .collect::<String>();
+        let html = Html::parse_document(&format!("<table>{rows}</table>"));
+        let selectors = [
+            "td:nth-child(2)",
+            "tr:nth-child(odd) td:last-child",
+            "tr:nth-last-child(3n+1) > td:nth-of-type(1)",
+            "tr:first-child td:nth-last-of-type(2)",
+        ]
+        .map(|selector| Selector::parse(selector).unwrap());
+
+        let mut context = MatchContext::new();
+        for selector in &selectors {
+            let expected = html.select(selector).collect::<Vec<_>>();
+            assert!(!expected.is_empty());
+
+            let with_context = html
+                .select_with_context(selector, &mut context)
+                .collect::<Vec<_>>();
+            assert_eq!(with_context, expected);
+
+            let mut select = html.select_with_context(selector, &mut context);
+            select.next();
+            assert_eq!(select.clone().collect::<Vec<_>>(), expected[1..]);
+            assert_eq!(select.rev().collect::<Vec<_>>().len(), expected.len() - 1);
+        }
+
+        let table = html
+            .select(&Selector::parse("table").unwrap())
+            .next()
+            .unwrap();
+        for selector in &selectors {
+            assert_eq!(
+                table
+                    .select_with_context(selector, &mut context)
+                    .collect::<Vec<_>>(),
+                table.select(selector).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn tag_names_case_insensitive_in_html() {
+        let html = Html::parse_document("<Item>a</Item><svg><foreignObject/></svg>");
+        for css in ["Item", "item", "ITEM"] {
+            assert_eq!(
+                html.select(&Selector::parse(css).unwrap()).count(),
+                1,
+                "{}",
+                css
+            );
+        }
+        // Names of foreign elements keep their case, and are matched case-sensitively.
+        assert_eq!(
+            html.select(&Selector::parse("foreignObject").unwrap())
+                .count(),
+            1
+        );
+        assert_eq!(
+            html.select(&Selector::parse("foreignobject").unwrap())
+                .count(),
+            0
+        );
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn tag_names_case_sensitive_in_xml() {
+        let xml = Html::parse_xml(
+            r#"<?xml version="1.0"?>
+            <feed><Item ID="a">First</Item><item>Second</item></feed>"#,
+        );
+        let names = |css| {
+            xml.select(&Selector::parse(css).unwrap())
+                .map(|element| element.inner_html())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("Item"), ["First"]);
+        assert_eq!(names("item"), ["Second"]);
+        assert!(names("ITEM").is_empty());
+        assert_eq!(names("[ID]"), ["First"]);
+        assert!(names("[id]").is_empty());
+
+        let xhtml = Html::parse_xml(
+            r#"<html xmlns="http://www.w3.org/1999/xhtml"><body><Item>Third</Item></body></html>"#,
+        );
+        assert_eq!(xhtml.select(&Selector::parse("Item").unwrap()).count(), 1);
+        assert_eq!(xhtml.select(&Selector::parse("item").unwrap()).count(), 0);
+    }
+
+    #[test]
+    fn select_anchored() {
+        let mut html = Html::parse_document(
+            r#"
+            <div id="a"><p class="x" n="1"></p><div id="a"><p class="x" n="2"></p></div></div>
+            <p class="x" n="3"></p>
+            <section id="b"><p class="x" n="4"></p><p class="y" n="5"></p></section>
+            <div id="a"><span><p class="x" n="6"></p></span></div>
+            <ul><li id="c" n="7"></li><li class="x" n="8"></li></ul>
+            "#,
+        );
+        // `[id=..]` selects the same elements as `#..`, but does not restrict traversal.
+        let selectors = [
+            ("#a .x", "[id=a] .x"),
+            ("#b .x, #a span > .x", "[id=b] .x, [id=a] span > .x"),
+            ("#b .x + .y", "[id=b] .x + .y"),
+            ("#a ~ section .x", "[id=a] ~ section .x"),
+            ("#c + .x", "[id=c] + .x"),
+            ("div#a", "div[id=a]"),
+            ("#missing .x", "[id=missing] .x"),
+        ];
+        fn numbers<'a>(elements: impl Iterator<Item = ElementRef<'a>>) -> Vec<&'a str> {
+            elements
+                .map(|element| element.attr("n").unwrap_or("-"))
+                .collect()
+        }
+
+        for edited in [false, true] {
+            if edited {
+                // Neither the index nor the order of the nodes in the arena follow the edits.
+                html.build_index();
+                let li = Selector::parse("[n='8']").unwrap();
+                let li = html.select(&li).next().unwrap().id();
+                let ul = html.tree.get(li).unwrap().parent().unwrap().id();
+                let body = html.tree.get(ul).unwrap().parent().unwrap().id();
+                let body_ref = html.tree.get(body).unwrap();
+                let first = body_ref.children().find(|node| node.value().is_element());
+                html.append_child(body, first.unwrap().id());
+                let mut ul = html.tree.get_mut(ul).unwrap();
+                ul.value().as_element_mut().unwrap().set_attr("id", "a");
+            }
+            for (anchored, plain) in selectors {
+                let anchored = Selector::parse(anchored).unwrap();
+                let plain = Selector::parse(plain).unwrap();
+                assert_eq!(
+                    numbers(html.select(&anchored)),
+                    numbers(html.select(&plain))
+                );
+                assert_eq!(
+                    numbers(html.select(&anchored).rev()),
+                    numbers(html.select(&plain).rev())
+                );
+            }
+        }
+
+        let selector = Selector::parse("#a .x").unwrap();
+        assert_eq!(numbers(html.select(&selector)), ["1", "2", "6", "8"]);
+        assert!(matches!(
+            html.select(&selector).inner,
+            super::Candidates::Anchored(..)
+        ));
+    }
+
+    #[test]
+    fn find_text() {
+        let html = Html::parse_document(
+            r#"<div id="a">Total: <span id="b"> 12
+            EUR </span><b id="c">Total<i>:</i></b></div>
+            <script>var total = "12 EUR";</script><style>.total {}</style>
+            <ul><li id="d">total: 12 eur</li><li id="e">TOTAL: 12 EUR</li></ul>"#,
+        );
+        let ids = |needle: &str, case_sensitivity| {
+            html.find_text(needle, case_sensitivity)
+                .iter()
+                .map(|element| element.attr("id").unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("12 EUR", CaseSensitivity::CaseSensitive), ["b", "e"]);
+        assert_eq!(ids(" 12\tEUR ", CaseSensitivity::CaseSensitive), ["b", "e"]);
+        assert_eq!(
+            ids("12 eur", CaseSensitivity::AsciiCaseInsensitive),
+            ["b", "d", "e"]
+        );
+        assert_eq!(ids("Total", CaseSensitivity::CaseSensitive), ["a", "c"]);
+        assert_eq!(
+            ids("total:", CaseSensitivity::AsciiCaseInsensitive),
+            ["a", "d", "e"]
+        );
+        assert!(ids("Total: 12", CaseSensitivity::CaseSensitive).is_empty());
+        assert!(ids("var", CaseSensitivity::CaseSensitive).is_empty());
+        assert!(ids(" ", CaseSensitivity::CaseSensitive).is_empty());
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let depth = 50_000;
+        let source = format!("{}x{}", "<span>".repeat(depth), "</span>".repeat(depth));
+        let html = Html::parse_document(&source);
+        assert_eq!(
+            html.html(),
+            format!("<html><head></head><body>{source}</body></html>")
+        );
+
+        let selector = Selector::parse("body > span").unwrap();
+        let outer = html.select(&selector).next().unwrap();
+        assert_eq!(outer.text().collect::<Vec<_>>(), ["x"]);
+        assert_eq!(outer.to_fragment().root_element().html(), source);
+        assert_eq!(html.clone(), html);
+
+        let safe = Html::fuzz_safe_parse(&source);
+        let depth = safe.tree.nodes().map(|node| node.ancestors().count()).max();
+        assert_eq!(depth, Some(Html::MAX_DEPTH));
+        assert_eq!(safe.root_element().text().collect::<Vec<_>>(), ["x"]);
+        assert_eq!(
+            safe.select(&Selector::parse("span").unwrap()).count(),
+            50_000
+        );
+    }
+
+    #[test]
+    fn deeply_nested_microdata() {
+        let depth = 50_000;
+        let source = format!(
+            "<span itemscope>{}",
+            r#"<span itemscope itemprop="child">"#.repeat(depth - 1)
+        );
+        let html = Html::parse_document(&source);
+
+        let items = html.microdata();
+        assert_eq!(items.len(), 1);
+        let mut item = &items[0];
+        let mut nested = 1;
+        while let Some(value) = item.value("child") {
+            item = value.as_item().unwrap();
+            nested += 1;
+        }
+        assert_eq!(nested, depth);
     }
 
     #[cfg(feature = "atomic")]
@@ -0,0 +1,211 @@
+//! Numbering of elements for constant-time ancestry and document order checks.
+
+use std::fmt;
+use std::num::NonZeroU32;
+
+use ego_tree::iter::Edge;
+use ego_tree::{NodeId, Tree};
+
+use crate::node::TreeOrder;
+use crate::Node;
+
+/// Whether the elements of a document are numbered by [`Html::build_order_index`].
+///
+/// Documents are compared without it, like their other indexes.
+///
+/// [`Html::build_order_index`]: super::Html::build_order_index
+#[derive(Clone, Default)]
+pub(crate) struct OrderIndex {
+    built: bool,
+}
+
+impl OrderIndex {
+    /// Numbers the elements of `tree` in a traversal from its root, dropping the numbers of
+    /// nodes outside it.
+    pub(crate) fn build(&mut self, tree: &mut Tree<Node>) {
+        self.invalidate(tree);
+
+        let mut numbers = Vec::new();
+        let mut open = Vec::<(NodeId, NonZeroU32)>::new();
+        let mut counter = NonZeroU32::MIN;
+        for edge in tree.root().traverse() {
+            match edge {
+                Edge::Open(node) if node.value().is_element() => open.push((node.id(), counter)),
+                Edge::Close(node) if node.value().is_element() => {
+                    let (id, enter) = open.pop().unwrap();
+                    numbers.push((id, TreeOrder::new(enter, counter)));
+                }
+                _ => (),
+            }
+            // Too many nodes to number; the checks keep walking the tree instead.
+            let Some(next) = counter.checked_add(1) else {
+                return;
+            };
+            counter = next;
+        }
+
+        for (id, number) in numbers {
+            if let Node::Element(element) = tree.get_mut(id).unwrap().value() {
+                element.tree_order = number;
+            }
+        }
+        self.built = true;
+    }
+
+    /// Drops the numbers after the tree is rearranged, so that checks walk it again.
+    pub(crate) fn invalidate(&mut self, tree: &mut Tree<Node>) {
+        if !std::mem::take(&mut self.built) {
+            return;
+        }
+        for value in tree.values_mut() {
+            if let Node::Element(element) = value {
+                element.tree_order = TreeOrder::default();
+            }
+        }
+    }
+}
+
+impl PartialEq for OrderIndex {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for OrderIndex {}
+
+impl fmt::Debug for OrderIndex {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = if self.built { "built" } else { "not built" };
+        fmt.debug_tuple("OrderIndex").field(&state).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::{ElementRef, Html, Selector};
+
+    /// The answers of the checks for every pair of elements in `html`, in the order the elements
+    /// were created.
+    fn relations(html: &Html) -> Vec<(bool, bool, Option<Ordering>)> {
+        let elements = html
+            .tree
+            .nodes()
+            .filter_map(ElementRef::wrap)
+            .collect::<Vec<_>>();
+        elements
+            .iter()
+            .flat_map(|element| {
+                elements.iter().map(move |other| {
+                    (
+                        element.contains(other),
+                        element.is_child_of(other),
+                        element.cmp_document_order(other),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Whether all elements in the tree are numbered, or `None` if only some are.
+    fn numbered(html: &Html) -> Option<bool> {
+        let numbered = html
+            .tree
+            .root()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .map(|element| element.value().tree_order.is_numbered())
+            .collect::<Vec<_>>();
+        let all = numbered[0];
+        numbered.iter().all(|&other| other == all).then_some(all)
+    }
+
+    fn id(html: &Html, css: &str) -> ego_tree::NodeId {
+        html.select(&Selector::parse(css).unwrap())
+            .next()
+            .unwrap()
+            .id()
+    }
+
+    #[test]
+    fn order_index() {
+        let mut html = Html::parse_document(
+            r#"<div id="a"><p id="b">x<i id="c"></i></p><!-- c --><p id="d"></p></div>
+            <section id="e"><div><span id="f"></span></div></section><p id="g"></p>"#,
+        );
+        let walked = relations(&html);
+        assert_eq!(numbered(&html), Some(false));
+
+        html.build_order_index();
+        assert_eq!(numbered(&html), Some(true));
+        assert_eq!(relations(&html), walked);
+
+        let clone = html.clone();
+        assert_eq!(numbered(&clone), Some(false));
+        assert_eq!(relations(&clone), walked);
+
+        // Adding nodes keeps the others numbered.
+        let extra = html.create_element("b");
+        let e = id(&html, "#e");
+        html.tree.get_mut(e).unwrap().append_id(extra);
+        let partly_numbered = relations(&html);
+        html.order.invalidate(&mut html.tree);
+        let walked = relations(&html);
+        assert_eq!(partly_numbered, walked);
+        html.build_order_index();
+        assert_eq!(relations(&html), walked);
+
+        // Moving nodes drops the numbers.
+        let (a, e, f) = (id(&html, "#a"), id(&html, "#e"), id(&html, "#f"));
+        let rearrangements: [&dyn Fn(&mut Html); 4] = [
+            &|html| html.append_child(f, a),
+            &|html| html.insert_after(e, f),
+            &|html| html.insert_before(e, f),
+            &|html| html.detach(f),
+        ];
+        for rearrange in rearrangements {
+            html.build_order_index();
+            let before = relations(&html);
+            rearrange(&mut html);
+            assert_eq!(numbered(&html), Some(false));
+            let walked = relations(&html);
+            assert_ne!(walked, before);
+            html.build_order_index();
+            assert_eq!(numbered(&html), Some(true));
+            assert_eq!(relations(&html), walked);
+        }
+
+        // Detached elements are not numbered and belong to no tree position.
+        let detached = ElementRef::wrap(html.tree.get(f).unwrap()).unwrap();
+        let a = ElementRef::wrap(html.tree.get(a).unwrap()).unwrap();
+        assert!(!detached.value().tree_order.is_numbered());
+        assert!(!detached.is_child_of(&a));
+        assert!(!a.contains(&detached));
+    }
+
+    #[test]
+    fn order_index_in_forms() {
+        let mut html = Html::parse_document(
+            r#"<form id="f"><fieldset><input name="a"></fieldset></form>
+            <input name="b" form="f"><input name="c"><form id="g"><input name="d"></form>"#,
+        );
+        let names = |html: &Html| {
+            html.forms()
+                .iter()
+                .map(|form| {
+                    form.inputs()
+                        .iter()
+                        .filter_map(|input| input.attr("name"))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let walked = names(&html);
+        assert_eq!(walked, ["a b", "d"]);
+        html.build_order_index();
+        assert_eq!(names(&html), walked);
+    }
+}
@@ -0,0 +1,310 @@
+use ego_tree::NodeId;
+use html5ever::driver::{self, ParseOpts};
+use html5ever::tree_builder::TreeBuilderOpts;
+use tendril::TendrilSink;
+
+use super::depth_limit::DepthLimit;
+use super::duplicates::DuplicateFinder;
+use super::hooked_sink::{HookedSink, ParseHook};
+use super::source_location::{self, SourceLocator};
+use super::{FragmentContext, Html, Interner};
+use crate::node::Element;
+
+/// Options for parsing HTML, combining the features of the specialized `Html::parse_*`
+/// functions.
+///
+/// Each of those functions parses with one feature enabled, e.g.
+/// [`Html::parse_document_with_source_locations`] records where elements start and
+/// [`Html::parse_with_interner`] shares attribute values between documents. These options enable
+/// any combination of them.
+///
+/// # Example
+///
+/// ```
+/// use scraper::html::{Interner, ParseOptions};
+/// use scraper::Selector;
+///
+/// let mut interner = Interner::new();
+/// let html = ParseOptions::new()
+///     .source_locations(true)
+///     .duplicate_attributes(true)
+///     .interner(&mut interner)
+///     .parse_document("<p>\n  <a class=navigation class=link href=/>Home</a>");
+///
+/// let a = html.select(&Selector::parse("a").unwrap()).next().unwrap();
+/// assert_eq!(a.source_location(), Some((2, 3)));
+/// assert_eq!(html.duplicate_attributes()[0].discarded, ["link"]);
+/// assert_eq!(interner.len(), 1);
+/// ```
+#[derive(Debug)]
+pub struct ParseOptions<'i> {
+    source_locations: bool,
+    duplicate_attributes: bool,
+    fuzz_safe: bool,
+    scripting: bool,
+    interner: Option<&'i mut Interner>,
+}
+
+impl<'i> ParseOptions<'i> {
+    /// Creates the options used by [`Html::parse_document`], with every feature disabled and
+    /// scripting enabled.
+    pub fn new() -> Self {
+        Self {
+            source_locations: false,
+            duplicate_attributes: false,
+            fuzz_safe: false,
+            scripting: true,
+            interner: None,
+        }
+    }
+
+    /// Sets whether to record where each element starts, like
+    /// [`Html::parse_document_with_source_locations`].
+    pub fn source_locations(mut self, enabled: bool) -> Self {
+        self.source_locations = enabled;
+        self
+    }
+
+    /// Sets whether to record the attributes repeated in start tags, like
+    /// [`Html::parse_document_with_duplicate_attributes`].
+    pub fn duplicate_attributes(mut self, enabled: bool) -> Self {
+        self.duplicate_attributes = enabled;
+        self
+    }
+
+    /// Sets whether to limit how deeply nodes are nested, like [`Html::fuzz_safe_parse`].
+    pub fn fuzz_safe(mut self, enabled: bool) -> Self {
+        self.fuzz_safe = enabled;
+        self
+    }
+
+    /// Sets whether to parse with scripting enabled, which leaves the text of `<noscript>`
+    /// elements raw. This is the default, as in browsers.
+    pub fn scripting(mut self, enabled: bool) -> Self {
+        self.scripting = enabled;
+        self
+    }
+
+    /// Shares attribute values with other documents parsed using the same `interner`, like
+    /// [`Html::parse_with_interner`].
+    pub fn interner(mut self, interner: &'i mut Interner) -> Self {
+        self.interner = Some(interner);
+        self
+    }
+
+    /// Parses a string of HTML as a document.
+    pub fn parse_document(self, document: &str) -> Html {
+        let opts = self.parse_opts();
+        let locate = self.locates_tags();
+        let mut html = Html::new_document();
+        html.scripting_enabled = self.scripting;
+        let sink = HookedSink::new(html, self.hooks(document));
+
+        if locate {
+            source_location::parse_document(sink, document, opts)
+        } else {
+            driver::parse_document(sink, opts).one(document)
+        }
+    }
+
+    /// Parses a string of HTML as a fragment, as the contents of a `<body>` element.
+    pub fn parse_fragment(self, fragment: &str) -> Html {
+        self.parse_fragment_in(fragment, FragmentContext::Body)
+    }
+
+    /// Parses a string of HTML as a fragment in the given context.
+    pub fn parse_fragment_in(self, fragment: &str, context: FragmentContext) -> Html {
+        let opts = self.parse_opts();
+        let locate = self.locates_tags();
+        let mut html = Html::new_fragment();
+        html.scripting_enabled = self.scripting;
+        let sink = HookedSink::new(html, self.hooks(fragment));
+
+        if locate {
+            source_location::parse_fragment(sink, fragment, context.name(), opts)
+        } else {
+            driver::parse_fragment(sink, opts, context.name(), Vec::new()).one(fragment)
+        }
+    }
+
+    /// Parses a string of XML as a document, like [`Html::parse_xml`].
+    ///
+    /// Source locations and duplicate attributes are only recorded for HTML, and XML has no
+    /// `<noscript>` elements, so only [`ParseOptions::fuzz_safe`] and
+    /// [`ParseOptions::interner`] apply.
+    #[cfg(feature = "xml")]
+    pub fn parse_xml(mut self, document: &str) -> Html {
+        self.source_locations = false;
+        self.duplicate_attributes = false;
+        let mut hooks = self.hooks(document);
+        hooks.0.insert(0, Box::new(super::xml::XmlDocument));
+
+        let sink = HookedSink::new(Html::new_document(), hooks);
+        xml5ever::driver::parse_document(sink, Default::default()).one(document)
+    }
+
+    /// Returns whether a hook needs to be told where start tags begin.
+    fn locates_tags(&self) -> bool {
+        self.source_locations || self.duplicate_attributes
+    }
+
+    fn parse_opts(&self) -> ParseOpts {
+        ParseOpts {
+            tree_builder: TreeBuilderOpts {
+                scripting_enabled: self.scripting,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Returns the hooks implementing the enabled features for parsing `source`.
+    fn hooks<'a>(self, source: &'a str) -> Hooks<'a>
+    where
+        'i: 'a,
+    {
+        let mut hooks: Vec<Box<dyn ParseHook + 'a>> = Vec::new();
+        if self.source_locations {
+            hooks.push(Box::new(SourceLocator::new(source)));
+        }
+        if self.duplicate_attributes {
+            hooks.push(Box::new(DuplicateFinder::new(source)));
+        }
+        if self.fuzz_safe {
+            hooks.push(Box::new(DepthLimit {
+                max_depth: Html::MAX_DEPTH,
+            }));
+        }
+        if let Some(interner) = self.interner {
+            hooks.push(Box::new(interner));
+        }
+        Hooks(hooks)
+    }
+}
+
+impl Default for ParseOptions<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The hooks of the features enabled by [`ParseOptions`], called in order.
+struct Hooks<'a>(Vec<Box<dyn ParseHook + 'a>>);
+
+impl ParseHook for Hooks<'_> {
+    fn start_tag(&mut self, offset: usize) {
+        for hook in &mut self.0 {
+            hook.start_tag(offset);
+        }
+    }
+
+    fn element_created(&mut self, id: NodeId, element: &mut Element) {
+        for hook in &mut self.0 {
+            hook.element_created(id, element);
+        }
+    }
+
+    fn parse_error(&mut self, msg: &str) {
+        for hook in &mut self.0 {
+            hook.parse_error(msg);
+        }
+    }
+
+    fn append_target(&mut self, html: &Html, parent: NodeId) -> NodeId {
+        self.0
+            .iter_mut()
+            .fold(parent, |parent, hook| hook.append_target(html, parent))
+    }
+
+    fn finish(&mut self, html: &mut Html) {
+        for hook in &mut self.0 {
+            hook.finish(html);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseOptions;
+    use crate::html::{FragmentContext, Html, Interner};
+    use crate::Selector;
+
+    #[test]
+    fn combined_features() {
+        let source = format!(
+            "<p>\n<a class=navigation-link class=x href=/>{}</a>",
+            "<b>".repeat(1000)
+        );
+        let mut interner = Interner::new();
+        let html = ParseOptions::new()
+            .source_locations(true)
+            .duplicate_attributes(true)
+            .fuzz_safe(true)
+            .interner(&mut interner)
+            .parse_document(&source);
+
+        let a = html.select(&Selector::parse("a").unwrap()).next().unwrap();
+        assert_eq!(a.source_location(), Some((2, 1)));
+        assert_eq!(html.duplicate_attributes().len(), 1);
+        assert_eq!(html.duplicate_attributes()[0].element, a.id());
+        let depth = html.tree.nodes().map(|node| node.ancestors().count()).max();
+        assert_eq!(depth, Some(Html::MAX_DEPTH));
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn single_features_match_parse_functions() {
+        let source = "<table>\n<tr><td a=1 a=2>1</td></tr>\n</table><noscript><b>x</b></noscript>";
+
+        assert_eq!(
+            ParseOptions::new().parse_document(source),
+            Html::parse_document(source)
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .source_locations(true)
+                .parse_fragment(source),
+            Html::parse_fragment_with_source_locations(source)
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .duplicate_attributes(true)
+                .parse_document(source),
+            Html::parse_document_with_duplicate_attributes(source)
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .scripting(false)
+                .parse_fragment_in("<td>1</td>", FragmentContext::TableRow)
+                .html(),
+            "<td>1</td>"
+        );
+        assert_eq!(
+            ParseOptions::new().scripting(false).parse_document(source),
+            Html::parse_document_with_opts(
+                source,
+                html5ever::driver::ParseOpts {
+                    tree_builder: html5ever::tree_builder::TreeBuilderOpts {
+                        scripting_enabled: false,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            )
+        );
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_with_interner() {
+        let mut interner = Interner::new();
+        let source = r#"<feed><Link href="https://example.com/"/><Link href="https://example.com/"/></feed>"#;
+        let xml = ParseOptions::new()
+            .interner(&mut interner)
+            .parse_xml(source);
+
+        assert_eq!(xml, Html::parse_xml(source));
+        assert_eq!(xml.select(&Selector::parse("link").unwrap()).count(), 0);
+        assert_eq!(interner.len(), 1);
+    }
+}
@@ -2,21 +2,51 @@ use std::io::Error;
 
 use html5ever::serialize::{Serialize, Serializer, TraversalScope};
 
+use crate::node::serializable::ByParent;
 use crate::Html;
 
+/// Options for serializing HTML, passed to [`Html::html_with`],
+/// [`ElementRef::html_with`](crate::ElementRef::html_with) and
+/// [`ElementRef::inner_html_with`](crate::ElementRef::inner_html_with).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializeOptions {
+    /// Whether comments are serialized. Defaults to true.
+    pub include_comments: bool,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            include_comments: true,
+        }
+    }
+}
+
 impl Serialize for Html {
     fn serialize<S: Serializer>(
         &self,
         serializer: &mut S,
         traversal_scope: TraversalScope,
     ) -> Result<(), Error> {
-        crate::node::serializable::serialize(self.tree.root(), serializer, traversal_scope)
+        crate::node::serializable::serialize(
+            self.tree.root(),
+            &mut ByParent(serializer),
+            traversal_scope,
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Html;
+    use html5ever::tree_builder::QuirksMode;
+
+    use std::io;
+
+    use super::SerializeOptions;
+    use crate::{CaseSensitivity, Html, Selector};
+
+    const COMMENTED: &str = "<!DOCTYPE html><!-- generated --><html><head><!--[if IE]><script src=\"ie.js\"></script><![endif]--></head><body><ul><li>a<!-- 1 -->b</li><!-- 2 --><li>c</li></ul><p>d<!-- 3 --><!-- 4 -->e</p></body></html>";
+    const STRIPPED: &str = "<!DOCTYPE html><html><head></head><body><ul><li>ab</li><li>c</li></ul><p>de</p></body></html>";
 
     #[test]
     fn test_serialize() {
@@ -24,4 +54,102 @@ mod tests {
         let html = Html::parse_document(src);
         assert_eq!(html.html(), src);
     }
+
+    #[test]
+    fn test_serialize_doctype() {
+        let html = Html::parse_document("<!DOCTYPE html>\n<html><title>a</title><p>b</p></html>");
+        let serialized = html.html();
+        assert!(
+            serialized.starts_with("<!DOCTYPE html><html>"),
+            "{}",
+            serialized
+        );
+
+        let reparsed = Html::parse_document(&serialized);
+        assert_eq!(reparsed.quirks_mode, QuirksMode::NoQuirks);
+        assert_eq!(reparsed.html(), serialized);
+    }
+
+    #[test]
+    fn test_serialize_without_comments() {
+        let html = Html::parse_document(COMMENTED);
+        let options = SerializeOptions {
+            include_comments: false,
+        };
+        assert_eq!(html.html_with(options), STRIPPED);
+        assert_eq!(html.html_with(SerializeOptions::default()), html.html());
+        assert_eq!(html.html(), COMMENTED);
+
+        let ul = html.select(&Selector::parse("ul").unwrap()).next().unwrap();
+        assert_eq!(ul.html_with(options), "<ul><li>ab</li><li>c</li></ul>");
+        assert_eq!(ul.inner_html_with(options), "<li>ab</li><li>c</li>");
+        assert_eq!(ul.inner_html(), "<li>a<!-- 1 -->b</li><!-- 2 --><li>c</li>");
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let mut html = Html::parse_document(COMMENTED);
+        assert_eq!(html.strip_comments(), 6);
+        assert_eq!(html.html(), STRIPPED);
+        assert_eq!(html.strip_comments(), 0);
+
+        let texts = html
+            .tree
+            .root()
+            .descendants()
+            .filter_map(|node| node.value().as_text())
+            .map(|text| text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["ab", "c", "de"]);
+    }
+
+    #[test]
+    fn test_serialize_filtered_to() {
+        let html = Html::parse_document(
+            r#"<head><script>a()</script><title>T</title></head><body><div class="ad">x<p>y</p></div><p>z<!-- c --><script>b()</script></p><img src=i></body>"#,
+        );
+        let mut output = Vec::new();
+        html.serialize_filtered_to(&mut output, |element| {
+            !matches!(element.value().name(), "script" | "img")
+                && !element
+                    .value()
+                    .has_class("ad", CaseSensitivity::CaseSensitive)
+        })
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "<html><head><title>T</title></head><body><p>z<!-- c --></p></body></html>"
+        );
+
+        let mut output = Vec::new();
+        html.serialize_filtered_to(&mut output, |_| true).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), html.html());
+    }
+
+    #[test]
+    fn test_serialize_filtered_to_error() {
+        struct Full(usize);
+
+        impl io::Write for Full {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.0 < buf.len() {
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+                }
+                self.0 -= buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let html = Html::parse_fragment("<p>0123456789</p>");
+        let err = html
+            .serialize_filtered_to(&mut Full(10), |_| true)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WriteZero);
+        assert!(html.serialize_filtered_to(&mut Full(100), |_| true).is_ok());
+    }
 }
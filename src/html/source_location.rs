@@ -0,0 +1,300 @@
+use ego_tree::NodeId;
+use html5ever::buffer_queue::BufferQueue;
+use html5ever::driver::ParseOpts;
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::{
+    Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts, TokenizerResult,
+};
+use html5ever::tree_builder::{create_element, TreeBuilder, TreeSink};
+use html5ever::QualName;
+
+use super::hooked_sink::{HookedSink, ParseHook};
+use super::Html;
+use crate::node::Element;
+
+/// Records the source locations of elements.
+///
+/// The start tags are located by [`parse_document`] and [`parse_fragment`], which tell the hook
+/// where each of them begins. Elements implied by the parser, e.g. a missing `<tbody>`, have no
+/// start tag and so are not located.
+pub(crate) struct SourceLocator<'s> {
+    source: &'s str,
+    /// Byte offsets of the start of every line.
+    line_starts: Vec<usize>,
+    /// Byte offset of the start tag being processed by the tree builder.
+    tag: Option<usize>,
+    /// The located elements and the byte offsets of their start tags.
+    located: Vec<(NodeId, usize)>,
+}
+
+impl<'s> SourceLocator<'s> {
+    pub(crate) fn new(source: &'s str) -> Self {
+        let bytes = source.as_bytes();
+        let mut line_starts = vec![0];
+        for (i, &byte) in bytes.iter().enumerate() {
+            // Like the tokenizer, treat CRLF as one line break and a lone CR as a line break.
+            let line_break =
+                byte == b'\n' || (byte == b'\r' && bytes.get(i + 1).copied() != Some(b'\n'));
+            if line_break {
+                line_starts.push(i + 1);
+            }
+        }
+
+        SourceLocator {
+            source,
+            line_starts,
+            tag: None,
+            located: Vec::new(),
+        }
+    }
+
+    /// Returns the source, as passed to [`SourceLocator::new`].
+    pub(crate) fn source(&self) -> &'s str {
+        self.source
+    }
+
+    /// Returns the located elements and the byte offsets of their start tags.
+    pub(crate) fn located(&self) -> &[(NodeId, usize)] {
+        &self.located
+    }
+
+    /// Returns the 1-based line and column of the byte offset `start`.
+    fn line_and_column(&self, start: usize) -> (usize, usize) {
+        let line = self
+            .line_starts
+            .partition_point(|&line_start| line_start <= start);
+        let column = self.source[self.line_starts[line - 1]..start]
+            .chars()
+            .count()
+            + 1;
+        (line, column)
+    }
+}
+
+impl ParseHook for SourceLocator<'_> {
+    fn start_tag(&mut self, offset: usize) {
+        self.tag = Some(offset);
+    }
+
+    fn element_created(&mut self, id: NodeId, element: &mut Element) {
+        let Some(start) = self.tag else {
+            return;
+        };
+        // Processing a start tag may create implied elements and clones of formatting elements
+        // before the element of the tag, which is created last.
+        let name = &self.source[start + 1..];
+        if !name
+            .get(..element.name.local.len())
+            .is_some_and(|name| name.eq_ignore_ascii_case(&element.name.local))
+        {
+            return;
+        }
+        match self.located.last_mut() {
+            Some(last) if last.1 == start => last.0 = id,
+            _ => self.located.push((id, start)),
+        }
+    }
+
+    fn finish(&mut self, html: &mut Html) {
+        for &(id, start) in &self.located {
+            let location = self.line_and_column(start);
+            let mut node = html.tree.get_mut(id).unwrap();
+            if let Some(element) = node.value().as_element_mut() {
+                element.source_location = Some(location);
+            }
+        }
+    }
+}
+
+/// Passes the tokens of the source on to the tree builder, telling its hook where each start
+/// tag begins.
+struct TagStarts<'s, H> {
+    tree_builder: TreeBuilder<NodeId, HookedSink<H>>,
+    source: &'s str,
+    /// Byte offset up to which the source has been fed to the tokenizer.
+    fed: usize,
+    /// The value of `fed` when the last token was emitted.
+    last_token: usize,
+}
+
+impl<H: ParseHook> TokenSink for TagStarts<'_, H> {
+    type Handle = NodeId;
+
+    fn process_token(&mut self, token: Token, line_number: u64) -> TokenSinkResult<NodeId> {
+        if let Token::TagToken(Tag {
+            kind: TagKind::StartTag,
+            ..
+        }) = token
+        {
+            // Every `<` in the data state either starts a tag or is emitted as text once the
+            // next character is fed, so the tag starts at the first `<` after the last token,
+            // which may have been emitted when the `<` itself was fed.
+            let from = self.last_token.saturating_sub(1);
+            let start = self.source.as_bytes()[from..]
+                .iter()
+                .position(|&byte| byte == b'<');
+            if let Some(start) = start {
+                self.tree_builder.sink.hook_mut().start_tag(from + start);
+            }
+        }
+        // Parse errors are also reported in the middle of tags.
+        if !matches!(token, Token::ParseError(_)) {
+            self.last_token = self.fed;
+        }
+        self.tree_builder.process_token(token, line_number)
+    }
+
+    fn end(&mut self) {
+        self.tree_builder.end();
+    }
+
+    fn adjusted_current_node_present_but_not_in_html_namespace(&self) -> bool {
+        self.tree_builder
+            .adjusted_current_node_present_but_not_in_html_namespace()
+    }
+}
+
+/// Parses `source` as a document, telling the hook of `sink` where each start tag begins.
+pub(crate) fn parse_document<H: ParseHook>(
+    sink: HookedSink<H>,
+    source: &str,
+    opts: ParseOpts,
+) -> Html {
+    let tree_builder = TreeBuilder::new(sink, opts.tree_builder);
+    run(tree_builder, opts.tokenizer, source)
+}
+
+/// Parses `source` as a fragment in the context of an element named `context`, telling the
+/// hook of `sink` where each start tag begins.
+pub(crate) fn parse_fragment<H: ParseHook>(
+    mut sink: HookedSink<H>,
+    source: &str,
+    context: QualName,
+    opts: ParseOpts,
+) -> Html {
+    let context = create_element(&mut sink, context, Vec::new());
+    let tree_builder = TreeBuilder::new_for_fragment(sink, context, None, opts.tree_builder);
+    let opts = TokenizerOpts {
+        initial_state: Some(tree_builder.tokenizer_state_for_context_elem()),
+        ..opts.tokenizer
+    };
+    run(tree_builder, opts, source)
+}
+
+fn run<H: ParseHook>(
+    tree_builder: TreeBuilder<NodeId, HookedSink<H>>,
+    opts: TokenizerOpts,
+    source: &str,
+) -> Html {
+    let sink = TagStarts {
+        tree_builder,
+        source,
+        fed: 0,
+        last_token: 0,
+    };
+    let mut tokenizer = Tokenizer::new(sink, opts);
+
+    // Feed `<` and `>` on their own, so that the tokens ending or starting with them are
+    // emitted while the offset of the character is known.
+    let tendril = StrTendril::from_slice(source);
+    let mut input = BufferQueue::default();
+    let mut start = 0;
+    while start < source.len() {
+        let end = match source.as_bytes()[start] {
+            b'<' | b'>' => start + 1,
+            _ => source[start..]
+                .find(['<', '>'])
+                .map_or(source.len(), |end| start + end),
+        };
+        tokenizer.sink.fed = end;
+        input.push_back(tendril.subtendril(start as u32, (end - start) as u32));
+        while let TokenizerResult::Script(_) = tokenizer.feed(&mut input) {}
+        start = end;
+    }
+    tokenizer.end();
+    tokenizer.sink.tree_builder.sink.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Html, Selector};
+
+    fn locations(html: &Html, selector: &str) -> Vec<Option<(usize, usize)>> {
+        html.select(&Selector::parse(selector).unwrap())
+            .map(|element| element.source_location())
+            .collect()
+    }
+
+    #[test]
+    fn document_locations() {
+        let html = Html::parse_document_with_source_locations(
+            "<!DOCTYPE html>\n<title>t</title>\n<DIV id=a>\n  <p>1</p> <p\n     class=x>2</p>\n</DIV>",
+        );
+
+        assert_eq!(locations(&html, "html, head, body"), [None, None, None]);
+        assert_eq!(locations(&html, "title"), [Some((2, 1))]);
+        assert_eq!(locations(&html, "div"), [Some((3, 1))]);
+        assert_eq!(locations(&html, "p"), [Some((4, 3)), Some((4, 12))]);
+    }
+
+    #[test]
+    fn implied_elements() {
+        let html = Html::parse_fragment_with_source_locations(
+            "<table>\n<tr><td>1</td></tr>\n<tbody><tr><td>2</td></tr></tbody>\n</table>",
+        );
+
+        assert_eq!(locations(&html, "table"), [Some((1, 1))]);
+        assert_eq!(locations(&html, "tbody"), [None, Some((3, 1))]);
+        assert_eq!(locations(&html, "tr"), [Some((2, 1)), Some((3, 8))]);
+    }
+
+    #[test]
+    fn tags_in_comments_attributes_and_raw_text() {
+        let html = Html::parse_fragment_with_source_locations(
+            "<!-- <p> --><a title='<p>' data-x=\"<b>\">x</a><script>'<p>'</script><p><b>y</b>\n<textarea><b></textarea><b>z</b>",
+        );
+
+        assert_eq!(locations(&html, "a"), [Some((1, 13))]);
+        assert_eq!(locations(&html, "script"), [Some((1, 46))]);
+        assert_eq!(locations(&html, "p"), [Some((1, 68))]);
+        assert_eq!(locations(&html, "b"), [Some((1, 71)), Some((2, 25))]);
+        assert_eq!(locations(&html, "textarea"), [Some((2, 1))]);
+    }
+
+    #[test]
+    fn text_ending_before_tags() {
+        let html = Html::parse_fragment_with_source_locations(
+            "1 &am<i>a</i>< <u>b</u><<s>c</s>&amp;<svg><foreignObject/></svg>",
+        );
+
+        assert_eq!(locations(&html, "i"), [Some((1, 6))]);
+        assert_eq!(locations(&html, "u"), [Some((1, 16))]);
+        assert_eq!(locations(&html, "s"), [Some((1, 25))]);
+        assert_eq!(locations(&html, "svg"), [Some((1, 38))]);
+        assert_eq!(locations(&html, "foreignObject"), [Some((1, 43))]);
+    }
+
+    #[test]
+    fn reconstructed_formatting_elements() {
+        let html = Html::parse_fragment_with_source_locations("<b>1<p>2</b>3</p><b>4</b>");
+
+        // The `b` reopened in the paragraph has no start tag.
+        assert_eq!(locations(&html, "b"), [Some((1, 1)), None, Some((1, 18))]);
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let html =
+            Html::parse_fragment_with_source_locations("äöü <b>x</b>\r\n€<i>y</i>\r<u>z</u>");
+
+        assert_eq!(locations(&html, "b"), [Some((1, 5))]);
+        assert_eq!(locations(&html, "i"), [Some((2, 2))]);
+        assert_eq!(locations(&html, "u"), [Some((3, 1))]);
+    }
+
+    #[test]
+    fn not_recorded_by_default() {
+        let html = Html::parse_fragment("<p>x</p>");
+        assert_eq!(locations(&html, "p"), [None]);
+    }
+}
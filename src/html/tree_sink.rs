@@ -43,7 +43,7 @@ impl TreeSink for Html {
     // What is the name of this element?
     //
     // Should never be called on a non-element node; feel free to panic!.
-    fn elem_name(&self, target: &Self::Handle) -> ExpandedName {
+    fn elem_name(&self, target: &Self::Handle) -> ExpandedName<'_> {
         self.tree
             .get(*target)
             .unwrap()
@@ -118,7 +118,7 @@ impl TreeSink for Html {
             NodeOrText::AppendText(text) => {
                 let text = make_tendril(text);
 
-                let did_concat = parent.last_child().map_or(false, |mut n| match n.value() {
+                let did_concat = parent.last_child().is_some_and(|mut n| match n.value() {
                     Node::Text(t) => {
                         t.text.push_tendril(&text);
                         true
@@ -127,7 +127,8 @@ impl TreeSink for Html {
                 });
 
                 if !did_concat {
-                    parent.append(Node::Text(Text { text }));
+                    let raw = is_raw_text_element(parent.value(), self.scripting_enabled);
+                    parent.append(Node::Text(Text::parsed(text, raw)));
                 }
             }
         }
@@ -160,19 +161,19 @@ impl TreeSink for Html {
                 NodeOrText::AppendText(text) => {
                     let text = make_tendril(text);
 
-                    let did_concat =
-                        sibling
-                            .prev_sibling()
-                            .map_or(false, |mut n| match n.value() {
-                                Node::Text(t) => {
-                                    t.text.push_tendril(&text);
-                                    true
-                                }
-                                _ => false,
-                            });
+                    let did_concat = sibling.prev_sibling().is_some_and(|mut n| match n.value() {
+                        Node::Text(t) => {
+                            t.text.push_tendril(&text);
+                            true
+                        }
+                        _ => false,
+                    });
 
                     if !did_concat {
-                        sibling.insert_before(Node::Text(Text { text }));
+                        let raw = sibling.parent().is_some_and(|mut parent| {
+                            is_raw_text_element(parent.value(), self.scripting_enabled)
+                        });
+                        sibling.insert_before(Node::Text(Text::parsed(text, raw)));
                     }
                 }
             }
@@ -245,3 +246,24 @@ impl TreeSink for Html {
         }
     }
 }
+
+/// Returns true if `node` is an element whose text contents the tokenizer leaves undecoded.
+///
+/// With scripting disabled, `<noscript>` is parsed like any other element.
+fn is_raw_text_element(node: &Node, scripting_enabled: bool) -> bool {
+    node.as_element().is_some_and(|element| {
+        !element.in_xml_document
+            && element.name.ns == ns!(html)
+            && match element.name.local {
+                local_name!("script")
+                | local_name!("style")
+                | local_name!("xmp")
+                | local_name!("iframe")
+                | local_name!("noembed")
+                | local_name!("noframes")
+                | local_name!("plaintext") => true,
+                local_name!("noscript") => scripting_enabled,
+                _ => false,
+            }
+    })
+}
@@ -0,0 +1,16 @@
+use ego_tree::NodeId;
+
+use super::hooked_sink::ParseHook;
+use crate::node::Element;
+
+/// Marks the elements of a document parsed by [`Html::parse_xml`](super::Html::parse_xml).
+///
+/// Selectors match the names of elements in an XML document case-sensitively, even those in the
+/// HTML namespace, so every element records that it belongs to one.
+pub(crate) struct XmlDocument;
+
+impl ParseHook for XmlDocument {
+    fn element_created(&mut self, _id: NodeId, element: &mut Element) {
+        element.in_xml_document = true;
+    }
+}
@@ -145,12 +145,21 @@ pub use crate::selector::Selector;
 
 pub use selectors::{attr::CaseSensitivity, Element};
 
+/// Makes a [`LocalName`](node::LocalName) from a string literal, interned at compile time for
+/// names known to html5ever such as `"div"` or `"href"`.
+pub use html5ever::local_name;
+
+#[cfg(feature = "macros")]
+pub use scraper_macros::selector;
+
 pub mod element_ref;
 pub mod error;
+pub mod extract;
 pub mod html;
 pub mod node;
 pub mod selectable;
 pub mod selector;
+pub mod selector_cache;
 
 #[cfg(feature = "atomic")]
 pub(crate) mod tendril_util {
@@ -178,5 +187,41 @@ pub(crate) mod tendril_util {
 
 pub use tendril_util::StrTendril;
 
+/// Returns a `&'static Selector` parsed from a string literal the first time it is evaluated.
+///
+/// Each use of the macro caches its selector in a `static`, so a selector used in a function
+/// called many times is only parsed once. Unlike the `selector!` macro of the `macros` feature,
+/// this does not validate the selector at compile time.
+///
+/// # Panics
+///
+/// Panics on first evaluation if the selector is invalid.
+///
+/// # Example
+///
+/// ```
+/// use scraper::{static_selector, Html};
+///
+/// fn titles(html: &Html) -> Vec<String> {
+///     html.select(static_selector!("h1.title"))
+///         .map(|h1| h1.inner_html())
+///         .collect()
+/// }
+///
+/// let html = Html::parse_fragment(r#"<h1 class="title">Hello</h1>"#);
+/// assert_eq!(titles(&html), ["Hello"]);
+/// ```
+#[macro_export]
+macro_rules! static_selector {
+    ($css:literal) => {{
+        static SELECTOR: ::std::sync::LazyLock<$crate::Selector> =
+            ::std::sync::LazyLock::new(|| match $crate::Selector::parse($css) {
+                Ok(selector) => selector,
+                Err(err) => panic!("invalid selector {:?}: {}", $css, err),
+            });
+        &*SELECTOR
+    }};
+}
+
 #[cfg(test)]
 mod test;
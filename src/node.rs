@@ -1,15 +1,14 @@
 //! HTML nodes.
 
-#[cfg(not(feature = "deterministic"))]
-use ahash::AHashMap as HashMap;
-#[cfg(not(feature = "deterministic"))]
-use std::collections::hash_map;
 use std::fmt;
+use std::num::NonZeroU32;
 use std::ops::Deref;
 use std::slice::Iter as SliceIter;
 
 use crate::{CaseSensitivity, StrTendril};
-use html5ever::{Attribute, LocalName, QualName};
+use ego_tree::NodeRef;
+use html5ever::Attribute;
+pub use html5ever::{LocalName, Namespace, Prefix, QualName};
 use once_cell::unsync::OnceCell;
 
 /// An HTML node.
@@ -110,6 +109,46 @@ impl Node {
             _ => None,
         }
     }
+
+    /// Returns self as a mutable doctype.
+    pub fn as_doctype_mut(&mut self) -> Option<&mut Doctype> {
+        match *self {
+            Node::Doctype(ref mut d) => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Returns self as a mutable comment.
+    pub fn as_comment_mut(&mut self) -> Option<&mut Comment> {
+        match *self {
+            Node::Comment(ref mut c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns self as mutable text.
+    pub fn as_text_mut(&mut self) -> Option<&mut Text> {
+        match *self {
+            Node::Text(ref mut t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Returns self as a mutable element.
+    pub fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match *self {
+            Node::Element(ref mut e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Returns self as a mutable processing instruction.
+    pub fn as_processing_instruction_mut(&mut self) -> Option<&mut ProcessingInstruction> {
+        match *self {
+            Node::ProcessingInstruction(ref mut pi) => Some(pi),
+            _ => None,
+        }
+    }
 }
 
 // Always use one line.
@@ -176,6 +215,16 @@ pub struct Comment {
     pub comment: StrTendril,
 }
 
+impl Comment {
+    /// Returns the text between `<!--` and `-->`.
+    ///
+    /// Conditional comments like `<!--[if IE]>...<![endif]-->` are ordinary comments, whose text
+    /// includes the condition.
+    pub fn text(&self) -> &str {
+        &self.comment
+    }
+}
+
 impl Deref for Comment {
     type Target = str;
 
@@ -195,6 +244,46 @@ impl fmt::Debug for Comment {
 pub struct Text {
     /// The text.
     pub text: StrTendril,
+
+    raw: bool,
+}
+
+impl Text {
+    /// Creates text which is escaped when serialized.
+    pub fn new(text: &str) -> Self {
+        Text {
+            text: StrTendril::from(text),
+            raw: false,
+        }
+    }
+
+    pub(crate) fn parsed(text: StrTendril, raw: bool) -> Self {
+        Text { text, raw }
+    }
+
+    /// Returns true if the text was parsed as the contents of a raw text element, such as
+    /// `<script>` or `<style>`.
+    ///
+    /// Character references in raw text are not decoded by the parser, so it is serialized
+    /// verbatim rather than escaped, even after the node is moved to another element. Text
+    /// in `<noscript>` is raw unless the document was parsed with scripting disabled through
+    /// [`Html::parse_document_with_opts`](crate::Html::parse_document_with_opts).
+    pub fn is_raw(&self) -> bool {
+        self.raw
+    }
+
+    /// Returns the text as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns true if the text consists only of ASCII whitespace, the whitespace of HTML, like
+    /// the indentation between tags.
+    ///
+    /// Other whitespace, such as a non-breaking space, is content. Empty text is whitespace.
+    pub fn is_whitespace(&self) -> bool {
+        self.text.bytes().all(|byte| byte.is_ascii_whitespace())
+    }
 }
 
 impl Deref for Text {
@@ -212,15 +301,10 @@ impl fmt::Debug for Text {
 }
 
 /// A Map of attributes that preserves the order of the attributes.
-#[cfg(feature = "deterministic")]
+///
+/// Parsed attributes are in source order, which is also the order they are serialized in.
 pub type Attributes = indexmap::IndexMap<QualName, StrTendril>;
 
-/// A Map of attributes that doesn't preserve the order of the attributes.
-/// Please enable the `deterministic` feature for order-preserving
-/// (de)serialization.
-#[cfg(not(feature = "deterministic"))]
-pub type Attributes = HashMap<QualName, StrTendril>;
-
 /// An HTML element.
 #[derive(Clone, PartialEq, Eq)]
 pub struct Element {
@@ -233,8 +317,62 @@ pub struct Element {
     id: OnceCell<Option<StrTendril>>,
 
     classes: OnceCell<Vec<LocalName>>,
+
+    pub(crate) source_location: Option<(usize, usize)>,
+
+    /// Whether the element belongs to a document parsed as XML, whose names are matched
+    /// case-sensitively.
+    pub(crate) in_xml_document: bool,
+
+    pub(crate) tree_order: TreeOrder,
+}
+
+/// The numbers at which a traversal of the tree enters and exits an element, assigned by
+/// [`Html::build_order_index`](crate::Html::build_order_index).
+///
+/// An element contains another if it is entered before and exited after it. Clones start
+/// without numbers, since they are not part of the tree the numbers were assigned in, and
+/// elements are compared without them.
+#[derive(Debug, Default)]
+pub(crate) struct TreeOrder(Option<(NonZeroU32, NonZeroU32)>);
+
+impl TreeOrder {
+    pub(crate) fn new(enter: NonZeroU32, exit: NonZeroU32) -> Self {
+        Self(Some((enter, exit)))
+    }
+
+    /// Returns whether the element numbered `other` is a descendant of this one, if both are
+    /// numbered.
+    pub(crate) fn contains(&self, other: &TreeOrder) -> Option<bool> {
+        let ((enter, exit), (other_enter, other_exit)) = (self.0?, other.0?);
+        Some(enter < other_enter && other_exit < exit)
+    }
+
+    /// Compares the positions of the elements in document order, if both are numbered.
+    pub(crate) fn cmp_position(&self, other: &TreeOrder) -> Option<std::cmp::Ordering> {
+        Some(self.0?.0.cmp(&other.0?.0))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_numbered(&self) -> bool {
+        self.0.is_some()
+    }
 }
 
+impl Clone for TreeOrder {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl PartialEq for TreeOrder {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for TreeOrder {}
+
 impl Element {
     #[doc(hidden)]
     pub fn new(name: QualName, attributes: Vec<Attribute>) -> Self {
@@ -248,6 +386,9 @@ impl Element {
             name,
             id: OnceCell::new(),
             classes: OnceCell::new(),
+            source_location: None,
+            in_xml_document: false,
+            tree_order: TreeOrder::default(),
         }
     }
 
@@ -256,31 +397,102 @@ impl Element {
         self.name.local.deref()
     }
 
+    /// Returns the interned local name of the element.
+    ///
+    /// Comparing it against a [`LocalName`] made once, e.g. by the [`local_name!`] macro for
+    /// names known to html5ever, compares pointers rather than strings.
+    ///
+    /// [`local_name!`]: crate::local_name
+    pub fn local_name(&self) -> &LocalName {
+        &self.name.local
+    }
+
+    /// Returns true if the local name of the element is `name`, comparing interned atoms.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{local_name, Html};
+    ///
+    /// let html = Html::parse_fragment("<div></div>");
+    /// let div = html.root_element().child_elements().next().unwrap();
+    ///
+    /// assert!(div.value().name_is(&local_name!("div")));
+    /// assert!(!div.value().name_is(&local_name!("span")));
+    /// ```
+    pub fn name_is(&self, name: &LocalName) -> bool {
+        self.name.local == *name
+    }
+
+    /// Returns the full name of the element, including its namespace and prefix.
+    pub fn name_qual(&self) -> &QualName {
+        &self.name
+    }
+
+    /// Returns the URL of the element's namespace, e.g. `http://www.w3.org/2000/svg` for an
+    /// `<svg>` element.
+    pub fn namespace(&self) -> &str {
+        self.name.ns.deref()
+    }
+
+    /// Returns the prefix of the element name, e.g. `svg` for `<svg:rect>`.
+    ///
+    /// The HTML parser never records prefixes, so this is only set for elements parsed as XML.
+    pub fn prefix(&self) -> Option<&str> {
+        self.name.prefix.as_deref()
+    }
+
+    /// Returns true if the element is in the HTML namespace, i.e. neither an SVG nor a MathML
+    /// element.
+    pub fn is_in_html_namespace(&self) -> bool {
+        self.name.ns == ns!(html)
+    }
+
+    /// Returns the 1-based line and column of the element's start tag in the parsed source.
+    ///
+    /// See [`ElementRef::source_location`](crate::ElementRef::source_location).
+    pub fn source_location(&self) -> Option<(usize, usize)> {
+        self.source_location
+    }
+
     /// Returns the element ID.
     pub fn id(&self) -> Option<&str> {
         self.id
             .get_or_init(|| {
                 self.attrs
                     .iter()
-                    .find(|(name, _)| name.local.as_ref() == "id")
+                    .find(|(name, _)| name.local == local_name!("id"))
                     .map(|(_, value)| value.clone())
             })
             .as_deref()
     }
 
     /// Returns true if element has the class.
+    ///
+    /// Class selectors like `.foo` are matched by this method, case-sensitively even in quirks
+    /// mode.
     pub fn has_class(&self, class: &str, case_sensitive: CaseSensitivity) -> bool {
         self.classes()
             .any(|c| case_sensitive.eq(c.as_bytes(), class.as_bytes()))
     }
 
     /// Returns an iterator over the element's classes.
-    pub fn classes(&self) -> Classes {
-        let classes = self.classes.get_or_init(|| {
+    ///
+    /// The `class` attribute is split on whitespace once and kept for later calls and selector
+    /// matching. Each class is returned once, in sorted rather than attribute order.
+    pub fn classes(&self) -> Classes<'_> {
+        Classes {
+            inner: self.class_names().iter(),
+        }
+    }
+
+    /// Returns the element's classes as atoms, like [`Element::classes`].
+    pub(crate) fn class_names(&self) -> &[LocalName] {
+        self.classes.get_or_init(|| {
             let mut classes: Vec<LocalName> = self
                 .attrs
                 .iter()
-                .filter(|(name, _)| name.local.as_ref() == "class")
+                .filter(|(name, _)| name.local == local_name!("class"))
                 .flat_map(|(_, value)| value.split_whitespace().map(LocalName::from))
                 .collect();
 
@@ -288,11 +500,7 @@ impl Element {
             classes.dedup();
 
             classes
-        });
-
-        Classes {
-            inner: classes.iter(),
-        }
+        })
     }
 
     /// Returns the value of an attribute.
@@ -301,8 +509,108 @@ impl Element {
         self.attrs.get(&qualname).map(Deref::deref)
     }
 
-    /// Returns an iterator over the element's attributes.
-    pub fn attrs(&self) -> Attrs {
+    /// Returns true if the boolean attribute `name` is set.
+    ///
+    /// A boolean attribute is true if it is present, whatever its value: `disabled`,
+    /// `disabled=""`, `disabled="disabled"` and even `disabled="false"` all disable an element.
+    /// Only a missing attribute is false.
+    ///
+    /// The boolean attributes of HTML are `allowfullscreen`, `async`, `autofocus`, `autoplay`,
+    /// `checked`, `controls`, `default`, `defer`, `disabled`, `formnovalidate`, `hidden`,
+    /// `inert`, `ismap`, `itemscope`, `loop`, `multiple`, `muted`, `nomodule`, `novalidate`,
+    /// `open`, `playsinline`, `readonly`, `required`, `reversed`, `selected` and
+    /// `shadowrootdelegatesfocus`.
+    pub fn has_bool_attr(&self, name: &str) -> bool {
+        self.attr(name).is_some()
+    }
+
+    /// Sets the value of an attribute in no namespace, adding it after the others if missing.
+    pub fn set_attr(&mut self, name: &str, value: &str) {
+        let name = QualName::new(None, ns!(), LocalName::from(name));
+        self.attrs.insert(name, StrTendril::from(value));
+        self.attrs_changed();
+    }
+
+    /// Removes an attribute in no namespace, returning its value if it was present.
+    ///
+    /// The remaining attributes keep their order.
+    pub fn remove_attr(&mut self, name: &str) -> Option<String> {
+        let name = QualName::new(None, ns!(), LocalName::from(name));
+        let value = self.attrs.shift_remove(&name)?;
+        self.attrs_changed();
+        Some(value.into())
+    }
+
+    /// Adds a class to the `class` attribute, unless the element already has it.
+    pub fn push_class(&mut self, class: &str) {
+        if self.classes().any(|c| c == class) {
+            return;
+        }
+
+        let classes = match self.attr("class").map(str::trim_end) {
+            Some(classes) if !classes.is_empty() => format!("{classes} {class}"),
+            _ => class.to_owned(),
+        };
+        self.set_attr("class", &classes);
+    }
+
+    /// Removes every occurrence of a class from the `class` attribute, returning true if the
+    /// element had it.
+    ///
+    /// The remaining classes keep their order, separated by single spaces.
+    pub fn remove_class(&mut self, class: &str) -> bool {
+        if !self.classes().any(|c| c == class) {
+            return false;
+        }
+
+        let classes = self
+            .attr("class")
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter(|c| *c != class)
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.set_attr("class", &classes);
+        true
+    }
+
+    /// Clears the ID and classes cached from the previous attributes.
+    fn attrs_changed(&mut self) {
+        self.id = OnceCell::new();
+        self.classes = OnceCell::new();
+    }
+
+    /// Returns the value of an attribute in the namespace with the URL `ns`.
+    ///
+    /// [`Element::attr`] only finds attributes in no namespace, like most HTML attributes. In
+    /// SVG, `xlink:href` is found by `attr_ns("http://www.w3.org/1999/xlink", "href")`.
+    pub fn attr_ns(&self, ns: &str, local: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(name, _)| &*name.ns == ns && &*name.local == local)
+            .map(|(_, value)| value.deref())
+    }
+
+    /// Returns the value of the first attribute, in source order, whose local name is
+    /// `local_name`, whatever its namespace or prefix.
+    ///
+    /// Unlike [`Element::attr`], this finds `xlink:href` when asked for `href`.
+    pub fn attr_local(&self, local_name: &str) -> Option<&str> {
+        self.attrs_local(local_name).next()
+    }
+
+    /// Returns the values of all attributes whose local name is `local_name`, whatever their
+    /// namespace or prefix, in source order.
+    pub fn attrs_local(&self, local_name: &str) -> impl Iterator<Item = &str> {
+        let local_name = LocalName::from(local_name);
+        self.attrs
+            .iter()
+            .filter(move |(name, _)| name.local == local_name)
+            .map(|(_, value)| value.deref())
+    }
+
+    /// Returns an iterator over the element's attributes, in source order.
+    pub fn attrs(&self) -> Attrs<'_> {
         Attrs {
             inner: self.attrs.iter(),
         }
@@ -325,13 +633,8 @@ impl<'a> Iterator for Classes<'a> {
 }
 
 /// An iterator over a node's attributes.
-#[cfg(feature = "deterministic")]
 pub type AttributesIter<'a> = indexmap::map::Iter<'a, QualName, StrTendril>;
 
-/// An iterator over a node's attributes.
-#[cfg(not(feature = "deterministic"))]
-pub type AttributesIter<'a> = hash_map::Iter<'a, QualName, StrTendril>;
-
 /// Iterator over attributes.
 #[allow(missing_debug_implementations)]
 #[derive(Clone)]
@@ -357,6 +660,28 @@ impl fmt::Debug for Element {
     }
 }
 
+/// An owned copy of an element's name, attributes and optionally text, made by
+/// [`ElementRef::to_owned_value`](crate::ElementRef::to_owned_value).
+///
+/// Unlike [`Element`], this is easy to construct, compare and, with the `serde` feature,
+/// serialize and deserialize as part of other data, e.g. `{"name": "a", "attrs": {"href": "/"}}`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedElement {
+    /// The local name of the element.
+    pub name: String,
+
+    /// The attributes of the element by local name, in source order.
+    pub attrs: indexmap::IndexMap<String, String>,
+
+    /// The text of the element's descendants, if it was collected.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub text: Option<String>,
+}
+
 /// HTML Processing Instruction.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ProcessingInstruction {
@@ -366,6 +691,18 @@ pub struct ProcessingInstruction {
     pub data: StrTendril,
 }
 
+impl ProcessingInstruction {
+    /// Returns the target, e.g. `xml-stylesheet` for `<?xml-stylesheet href="a.css"?>`.
+    pub fn target(&self) -> &str {
+        self.target.deref()
+    }
+
+    /// Returns the data following the target, e.g. `href="a.css"`.
+    pub fn data(&self) -> &str {
+        self.data.deref()
+    }
+}
+
 impl Deref for ProcessingInstruction {
     type Target = str;
 
@@ -374,4 +711,308 @@ impl Deref for ProcessingInstruction {
     }
 }
 
+/// Returns the text of a text node concatenated with that of the text nodes immediately
+/// preceding and following it.
+///
+/// The parser merges adjacent text, but trees built or modified by hand can split a run of text
+/// across several nodes. Returns an empty string if `node` is not a text node.
+///
+/// # Example
+///
+/// ```
+/// use scraper::node::{contiguous_text, Text};
+/// use scraper::{Html, Node};
+///
+/// let mut html = Html::parse_fragment("<p>Fish </p>");
+/// let p = html.root_element().first_child().unwrap().id();
+/// let mut p = html.tree.get_mut(p).unwrap();
+/// let chips = p.append(Node::Text(Text::new("& chips"))).id();
+///
+/// assert_eq!(contiguous_text(html.tree.get(chips).unwrap()), "Fish & chips");
+/// ```
+pub fn contiguous_text<'a>(node: NodeRef<'a, Node>) -> String {
+    let Some(text) = node.value().as_text() else {
+        return String::new();
+    };
+
+    let text_of = |sibling: NodeRef<'a, Node>| sibling.value().as_text().map(Text::as_str);
+    let mut preceding = node.prev_siblings().map_while(text_of).collect::<Vec<_>>();
+    preceding.reverse();
+
+    let mut contiguous = preceding.concat();
+    contiguous.push_str(text);
+    contiguous.extend(node.next_siblings().map_while(text_of));
+    contiguous
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls;
 pub(crate) mod serializable;
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+
+    use super::{contiguous_text, Comment, Node, Text};
+    use crate::{CaseSensitivity, Html, Selector};
+
+    fn text(text: &str) -> Node {
+        Node::Text(Text::new(text))
+    }
+
+    #[test]
+    fn text_is_whitespace() {
+        let html = Html::parse_fragment("<ul>\n  <li>a</li>\n</ul><p>&nbsp;</p>");
+        let texts = html
+            .tree
+            .values()
+            .filter_map(Node::as_text)
+            .map(|text| (text.as_str(), text.is_whitespace()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            texts,
+            [
+                ("\n  ", true),
+                ("a", false),
+                ("\n", true),
+                ("\u{a0}", false)
+            ]
+        );
+        assert!(Text::new("").is_whitespace());
+    }
+
+    #[test]
+    fn bool_attrs() {
+        let html = Html::parse_fragment(
+            r#"<input disabled><input disabled=""><input disabled="false"><input checked>"#,
+        );
+        let inputs = html
+            .tree
+            .values()
+            .filter_map(Node::as_element)
+            .filter(|element| element.name() == "input")
+            .map(|element| element.has_bool_attr("disabled"))
+            .collect::<Vec<_>>();
+        assert_eq!(inputs, [true, true, true, false]);
+    }
+
+    #[test]
+    fn element_namespaces() {
+        let html = Html::parse_document(
+            r#"<p>a</p><svg viewBox="0 0 1 1"><rect/><foreignObject><b>b</b></foreignObject></svg><math><mi>x</mi></math>"#,
+        );
+        let names = html
+            .tree
+            .values()
+            .filter_map(Node::as_element)
+            .skip_while(|element| element.name() != "p")
+            .map(|element| {
+                assert!(element.prefix().is_none());
+                assert_eq!(element.name_qual().local.deref(), element.name());
+                (
+                    element.name(),
+                    element.namespace(),
+                    element.is_in_html_namespace(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        const HTML: &str = "http://www.w3.org/1999/xhtml";
+        const SVG: &str = "http://www.w3.org/2000/svg";
+        const MATHML: &str = "http://www.w3.org/1998/Math/MathML";
+        assert_eq!(
+            names,
+            [
+                ("p", HTML, true),
+                ("svg", SVG, false),
+                ("rect", SVG, false),
+                ("foreignObject", SVG, false),
+                ("b", HTML, true),
+                ("math", MATHML, false),
+                ("mi", MATHML, false),
+            ]
+        );
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn element_prefix_in_xml() {
+        let xml = Html::parse_xml(
+            r#"<doc xmlns:svg="http://www.w3.org/2000/svg"><svg:rect/><item/></doc>"#,
+        );
+        let elements = xml
+            .tree
+            .values()
+            .filter_map(Node::as_element)
+            .map(|element| (element.prefix(), element.name(), element.namespace()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            elements,
+            [
+                (None, "doc", ""),
+                (Some("svg"), "rect", "http://www.w3.org/2000/svg"),
+                (None, "item", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn attr_local() {
+        let html = Html::parse_fragment(
+            r##"<svg><use xlink:href="#a" href="#b"></use><use href="#c"></use><use></use></svg>"##,
+        );
+        let uses = html
+            .tree
+            .values()
+            .filter_map(Node::as_element)
+            .filter(|element| element.name() == "use")
+            .collect::<Vec<_>>();
+
+        assert_eq!(uses[0].attr("href"), Some("#b"));
+        assert_eq!(uses[0].attr_local("href"), Some("#a"));
+        assert_eq!(
+            uses[0].attrs_local("href").collect::<Vec<_>>(),
+            ["#a", "#b"]
+        );
+        assert_eq!(uses[0].attr_local("xlink:href"), None);
+
+        assert_eq!(uses[1].attr_local("href"), Some("#c"));
+        assert_eq!(uses[1].attrs_local("href").collect::<Vec<_>>(), ["#c"]);
+
+        assert_eq!(uses[2].attr_local("href"), None);
+        assert_eq!(uses[2].attrs_local("href").count(), 0);
+    }
+
+    #[test]
+    fn classes_agree_with_selectors() {
+        const CLASSES: [&str; 6] = ["foo", "Foo", "bar", "baz", "foo-bar", "x"];
+        let html = Html::parse_fragment(
+            r#"<p class="foo">1</p><p class=" foo\tbar  foo">2</p><p class="Foo foo-bar">3</p><p class="">4</p><p>5</p><p class="baz BAR">6</p>"#,
+        );
+
+        for p in html.select(&Selector::parse("p").unwrap()) {
+            let element = p.value();
+            for class in CLASSES {
+                let selector = Selector::parse(&format!(".{class}")).unwrap();
+                assert_eq!(
+                    element.has_class(class, CaseSensitivity::CaseSensitive),
+                    selector.matches(&p),
+                    "{element:?} {class}"
+                );
+            }
+
+            let classes = element.classes().collect::<Vec<_>>();
+            let mut split = element
+                .attr("class")
+                .unwrap_or("")
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            split.sort_unstable();
+            split.dedup();
+            assert_eq!(classes, split);
+            assert_eq!(
+                element.has_class("BAR", CaseSensitivity::AsciiCaseInsensitive),
+                classes.contains(&"bar") || classes.contains(&"BAR")
+            );
+        }
+    }
+
+    #[test]
+    fn mutate_in_place() {
+        let mut html = Html::parse_fragment(r#"<p class="a  b" id="x" title="t">old</p>"#);
+        let p = html
+            .select(&Selector::parse("p").unwrap())
+            .next()
+            .unwrap()
+            .id();
+        let text = html.tree.get(p).unwrap().first_child().unwrap().id();
+
+        html.tree
+            .get_mut(text)
+            .unwrap()
+            .value()
+            .as_text_mut()
+            .unwrap()
+            .text = "new".into();
+
+        let mut node = html.tree.get_mut(p).unwrap();
+        let element = node.value().as_element_mut().unwrap();
+        assert_eq!(element.id(), Some("x"));
+        assert!(element.classes().eq(["a", "b"]));
+        element.push_class("c");
+        element.push_class("a");
+        assert!(element.remove_class("b"));
+        assert!(!element.remove_class("b"));
+        assert_eq!(element.attr("class"), Some("a c"));
+        assert_eq!(element.remove_attr("id"), Some("x".to_owned()));
+        assert_eq!(element.remove_attr("id"), None);
+        element.set_attr("data-n", "1");
+        assert!(node.value().as_comment_mut().is_none());
+
+        let p = html.select(&Selector::parse(".c").unwrap()).next().unwrap();
+        assert_eq!(p.html(), r#"<p class="a c" title="t" data-n="1">new</p>"#);
+        assert_eq!(p.value().id(), None);
+        assert_eq!(html.select(&Selector::parse(".b, #x").unwrap()).count(), 0);
+
+        let mut bare = Html::parse_fragment("<i></i>");
+        let i = bare.root_element().first_child().unwrap().id();
+        let mut node = bare.tree.get_mut(i).unwrap();
+        let element = node.value().as_element_mut().unwrap();
+        assert!(!element.remove_class("a"));
+        element.push_class("a");
+        assert_eq!(element.attr("class"), Some("a"));
+    }
+
+    #[test]
+    fn attributes_in_source_order() {
+        let html = Html::parse_fragment(r#"<div b=2 a=1 c=3 b=4 data-z="" id=x></div>"#);
+        let div = html
+            .select(&Selector::parse("div").unwrap())
+            .next()
+            .unwrap();
+
+        let attrs = div.value().attrs().collect::<Vec<_>>();
+        assert_eq!(
+            attrs,
+            [
+                ("b", "2"),
+                ("a", "1"),
+                ("c", "3"),
+                ("data-z", ""),
+                ("id", "x")
+            ]
+        );
+        assert_eq!(
+            div.html(),
+            r#"<div b="2" a="1" c="3" data-z="" id="x"></div>"#
+        );
+    }
+
+    #[test]
+    fn contiguous_text_split_nodes() {
+        // Split around the `&amp;` character reference, as a tree builder emitting each token
+        // separately would do.
+        let mut html = Html::parse_fragment("<p>a<b>b</b></p>");
+        let p = html.root_element().first_child().unwrap();
+        let (p, a) = (p.id(), p.first_child().unwrap().id());
+        let mut p_mut = html.tree.get_mut(p).unwrap();
+        let fish = p_mut.append(text("Fish ")).id();
+        let and = p_mut.append(text("&")).id();
+        p_mut.append(text(" chips"));
+        let indent = p_mut.append(text("\n  ")).id();
+        p_mut.append(Node::Comment(Comment {
+            comment: "c".into(),
+        }));
+        let after = p_mut.append(text("d")).id();
+
+        let contiguous = |id| contiguous_text(html.tree.get(id).unwrap());
+        assert_eq!(contiguous(fish), "Fish & chips\n  ");
+        assert_eq!(contiguous(and), "Fish & chips\n  ");
+        assert_eq!(contiguous(indent), "Fish & chips\n  ");
+        assert_eq!(contiguous(after), "d");
+        assert_eq!(contiguous(a), "a");
+        assert_eq!(contiguous(p), "");
+    }
+}
@@ -0,0 +1,149 @@
+//! Serialization of node values with serde.
+//!
+//! Elements are serialized as `{ "name": ..., "attrs": { ... } }`, the same shape as an
+//! [`OwnedElement`](super::OwnedElement) without text, so they can be deserialized into one.
+//! Text and comments are serialized as strings.
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use super::{Comment, Doctype, Element, Node, ProcessingInstruction, Text};
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Node::Document => serializer.serialize_unit_variant("Node", 0, "Document"),
+            Node::Fragment => serializer.serialize_unit_variant("Node", 1, "Fragment"),
+            Node::Doctype(doctype) => {
+                serializer.serialize_newtype_variant("Node", 2, "Doctype", doctype)
+            }
+            Node::Comment(comment) => {
+                serializer.serialize_newtype_variant("Node", 3, "Comment", comment)
+            }
+            Node::Text(text) => serializer.serialize_newtype_variant("Node", 4, "Text", text),
+            Node::Element(element) => {
+                serializer.serialize_newtype_variant("Node", 5, "Element", element)
+            }
+            Node::ProcessingInstruction(pi) => {
+                serializer.serialize_newtype_variant("Node", 6, "ProcessingInstruction", pi)
+            }
+        }
+    }
+}
+
+impl Serialize for Doctype {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Doctype", 3)?;
+        state.serialize_field("name", self.name())?;
+        state.serialize_field("public_id", self.public_id())?;
+        state.serialize_field("system_id", self.system_id())?;
+        state.end()
+    }
+}
+
+impl Serialize for Comment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.text())
+    }
+}
+
+impl Serialize for Text {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Serialize for Element {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Attrs<'a>(&'a Element);
+
+        impl Serialize for Attrs<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.attrs())
+            }
+        }
+
+        let mut state = serializer.serialize_struct("Element", 2)?;
+        state.serialize_field("name", self.name())?;
+        state.serialize_field("attrs", &Attrs(self))?;
+        state.end()
+    }
+}
+
+impl Serialize for ProcessingInstruction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ProcessingInstruction", 2)?;
+        state.serialize_field("target", &*self.target)?;
+        state.serialize_field("data", &*self.data)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::node::OwnedElement;
+    use crate::{Html, Node, Selector};
+
+    #[test]
+    fn node_kinds() {
+        let html = Html::parse_document(
+            r#"<!DOCTYPE html><!-- c --><html><body><p b="2" a="1">x &amp; y</p></body></html>"#,
+        );
+        let nodes = html
+            .tree
+            .root()
+            .descendants()
+            .map(|node| serde_json::to_value(node.value()).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            nodes,
+            [
+                json!("Document"),
+                json!({"Doctype": {"name": "html", "public_id": "", "system_id": ""}}),
+                json!({"Comment": " c "}),
+                json!({"Element": {"name": "html", "attrs": {}}}),
+                json!({"Element": {"name": "head", "attrs": {}}}),
+                json!({"Element": {"name": "body", "attrs": {}}}),
+                json!({"Element": {"name": "p", "attrs": {"b": "2", "a": "1"}}}),
+                json!({"Text": "x & y"}),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(Html::parse_fragment("").tree.root().value()).unwrap(),
+            json!("Fragment")
+        );
+
+        // Attributes keep their source order.
+        let p = html.select(&Selector::parse("p").unwrap()).next().unwrap();
+        assert_eq!(
+            serde_json::to_string(p.value()).unwrap(),
+            r#"{"name":"p","attrs":{"b":"2","a":"1"}}"#
+        );
+    }
+
+    #[test]
+    fn owned_element_round_trip() {
+        let html = Html::parse_fragment(r#"<a href="/x" class="c">Go <b>on</b></a>"#);
+        let a = html.select(&Selector::parse("a").unwrap()).next().unwrap();
+
+        let owned = a.to_owned_value(true);
+        let json = serde_json::to_string(&owned).unwrap();
+        assert_eq!(
+            json,
+            r#"{"name":"a","attrs":{"href":"/x","class":"c"},"text":"Go on"}"#
+        );
+        assert_eq!(serde_json::from_str::<OwnedElement>(&json).unwrap(), owned);
+
+        let owned = a.to_owned_value(false);
+        let json = serde_json::to_string(&owned).unwrap();
+        assert_eq!(json, r#"{"name":"a","attrs":{"href":"/x","class":"c"}}"#);
+        assert_eq!(serde_json::from_str::<OwnedElement>(&json).unwrap(), owned);
+
+        // An element serialized directly has the same shape.
+        let value = serde_json::to_value(Node::Element(a.value().clone())).unwrap();
+        let owned_from_node = serde_json::from_value::<OwnedElement>(value["Element"].clone());
+        assert_eq!(owned_from_node.unwrap(), owned);
+    }
+}
@@ -1,32 +1,203 @@
-use std::io::Error;
+use std::io::{Error, Write};
+use std::str;
 
 use ego_tree::{iter::Edge, NodeRef};
-use html5ever::serialize::{Serializer, TraversalScope};
+use html5ever::serialize::{AttrRef, HtmlSerializer, Serializer, TraversalScope};
+use html5ever::{local_name, ns, QualName};
 
+use crate::html::SerializeOptions;
+use crate::node::{Doctype, ProcessingInstruction, Text};
 use crate::Node;
 
+/// A serializer which knows how text nodes were parsed.
+pub(crate) trait TextSerializer: Serializer {
+    /// Writes a text node, raw text verbatim and other text escaped, whatever its parent.
+    fn write_text_node(&mut self, text: &Text) -> Result<(), Error>;
+
+    /// Writes a doctype, with its public and system identifiers if the format allows.
+    fn write_doctype_node(&mut self, doctype: &Doctype) -> Result<(), Error> {
+        self.write_doctype(doctype.name())
+    }
+
+    /// Writes a processing instruction as `<?target data?>`.
+    fn write_processing_instruction_node(
+        &mut self,
+        pi: &ProcessingInstruction,
+    ) -> Result<(), Error> {
+        self.write_processing_instruction(pi.target(), pi.data())
+    }
+}
+
+impl<Wr: Write> TextSerializer for HtmlSerializer<Wr> {
+    fn write_text_node(&mut self, text: &Text) -> Result<(), Error> {
+        if text.is_raw() {
+            return self.writer.write_all(text.as_bytes());
+        }
+
+        for c in text.chars() {
+            match c {
+                '&' => self.writer.write_all(b"&amp;"),
+                '\u{00A0}' => self.writer.write_all(b"&nbsp;"),
+                '<' => self.writer.write_all(b"&lt;"),
+                '>' => self.writer.write_all(b"&gt;"),
+                c => write!(self.writer, "{c}"),
+            }?;
+        }
+        Ok(())
+    }
+
+    // html5ever only writes the name of doctypes, losing the identifiers of legacy doctypes.
+    fn write_doctype_node(&mut self, doctype: &Doctype) -> Result<(), Error> {
+        let quoted = |id: &str| {
+            let quote = if id.contains('"') { '\'' } else { '"' };
+            format!("{quote}{id}{quote}")
+        };
+
+        write!(self.writer, "<!DOCTYPE {}", doctype.name())?;
+        match (doctype.public_id(), doctype.system_id()) {
+            ("", "") => {}
+            ("", system_id) => write!(self.writer, " SYSTEM {}", quoted(system_id))?,
+            (public_id, "") => write!(self.writer, " PUBLIC {}", quoted(public_id))?,
+            (public_id, system_id) => write!(
+                self.writer,
+                " PUBLIC {} {}",
+                quoted(public_id),
+                quoted(system_id)
+            )?,
+        }
+        self.writer.write_all(b">")
+    }
+
+    // html5ever closes processing instructions with `>` as SGML did, which XML parsers reject.
+    fn write_processing_instruction_node(
+        &mut self,
+        pi: &ProcessingInstruction,
+    ) -> Result<(), Error> {
+        write!(self.writer, "<?{} {}?>", pi.target(), pi.data())
+    }
+}
+
+/// Adapts a serializer which escapes text depending on its parent element, as html5ever
+/// serializers do, for serializing [`Html`](crate::Html) and
+/// [`ElementRef`](crate::ElementRef) with any [`Serializer`].
+pub(crate) struct ByParent<'s, S>(pub(crate) &'s mut S);
+
+impl<S: Serializer> Serializer for ByParent<'_, S> {
+    fn start_elem<'a, AttrIter>(&mut self, name: QualName, attrs: AttrIter) -> Result<(), Error>
+    where
+        AttrIter: Iterator<Item = AttrRef<'a>>,
+    {
+        self.0.start_elem(name, attrs)
+    }
+
+    fn end_elem(&mut self, name: QualName) -> Result<(), Error> {
+        self.0.end_elem(name)
+    }
+
+    fn write_text(&mut self, text: &str) -> Result<(), Error> {
+        self.0.write_text(text)
+    }
+
+    fn write_comment(&mut self, text: &str) -> Result<(), Error> {
+        self.0.write_comment(text)
+    }
+
+    fn write_doctype(&mut self, name: &str) -> Result<(), Error> {
+        self.0.write_doctype(name)
+    }
+
+    fn write_processing_instruction(&mut self, target: &str, data: &str) -> Result<(), Error> {
+        self.0.write_processing_instruction(target, data)
+    }
+}
+
+impl<S: Serializer> TextSerializer for ByParent<'_, S> {
+    fn write_text_node(&mut self, text: &Text) -> Result<(), Error> {
+        self.0.write_text(text)
+    }
+}
+
+/// Writes serialized HTML into a `String`, appending to its contents.
+///
+/// The serializers of this crate only ever write whole strings, so every chunk passed to
+/// [`Write::write`] is valid UTF-8 on its own; this is only checked in debug builds.
+pub(crate) struct StringWriter<'s>(pub(crate) &'s mut String);
+
+impl Write for StringWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        debug_assert!(
+            str::from_utf8(buf).is_ok(),
+            "serializer wrote invalid UTF-8"
+        );
+        // SAFETY: The serializers writing here only write the bytes of whole `str`s, and
+        // `write_all` never splits them since this always writes the entire buffer.
+        unsafe { self.0.as_mut_vec() }.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 /// Serialize an HTML node using html5ever serializer.
-pub(crate) fn serialize<S: Serializer>(
+pub(crate) fn serialize<S: TextSerializer>(
+    self_node: NodeRef<Node>,
+    serializer: &mut S,
+    traversal_scope: TraversalScope,
+) -> Result<(), Error> {
+    serialize_with(
+        self_node,
+        serializer,
+        traversal_scope,
+        SerializeOptions::default(),
+    )
+}
+
+/// Like [`serialize`], but leaving out the nodes excluded by `options`.
+pub(crate) fn serialize_with<S: TextSerializer>(
     self_node: NodeRef<Node>,
     serializer: &mut S,
     traversal_scope: TraversalScope,
+    options: SerializeOptions,
 ) -> Result<(), Error> {
-    for edge in self_node.traverse() {
+    serialize_filtered(self_node, serializer, traversal_scope, |node| {
+        options.include_comments || !node.value().is_comment()
+    })
+}
+
+/// Like [`serialize`], but leaving out the nodes for which `keep` returns false, together with
+/// their descendants.
+pub(crate) fn serialize_filtered<S: TextSerializer, F: FnMut(NodeRef<Node>) -> bool>(
+    self_node: NodeRef<Node>,
+    serializer: &mut S,
+    traversal_scope: TraversalScope,
+    mut keep: F,
+) -> Result<(), Error> {
+    let mut traverse = self_node.traverse();
+    while let Some(edge) = traverse.next() {
         match edge {
             Edge::Open(node) => {
                 if node == self_node && traversal_scope == TraversalScope::ChildrenOnly(None) {
                     continue;
                 }
+                if !keep(node) {
+                    traverse.find(|edge| matches!(edge, Edge::Close(closed) if *closed == node));
+                    continue;
+                }
 
                 match *node.value() {
                     Node::Doctype(ref doctype) => {
-                        serializer.write_doctype(doctype.name())?;
+                        serializer.write_doctype_node(doctype)?;
                     }
                     Node::Comment(ref comment) => {
                         serializer.write_comment(comment)?;
                     }
                     Node::Text(ref text) => {
-                        serializer.write_text(text)?;
+                        serializer.write_text_node(text)?;
+                    }
+                    Node::ProcessingInstruction(ref pi) => {
+                        serializer.write_processing_instruction_node(pi)?;
                     }
                     Node::Element(ref elem) => {
                         let attrs = elem.attrs.iter().map(|(k, v)| (k, &v[..]));
@@ -50,3 +221,132 @@ pub(crate) fn serialize<S: Serializer>(
 
     Ok(())
 }
+
+/// Serializes nodes as XHTML, i.e. as HTML which is also well-formed XML.
+///
+/// Void elements are closed with `/>`, and text and attribute values are escaped by XML rules,
+/// including the contents of raw text elements like `<script>`.
+pub(crate) struct XhtmlSerializer<Wr: Write> {
+    writer: Wr,
+    /// Whether the children of each open element are ignored, as for void elements.
+    ignore_children: Vec<bool>,
+}
+
+impl<Wr: Write> XhtmlSerializer<Wr> {
+    pub(crate) fn new(writer: Wr) -> Self {
+        Self {
+            writer,
+            ignore_children: Vec::new(),
+        }
+    }
+
+    fn ignoring(&self) -> bool {
+        self.ignore_children.last().copied().unwrap_or(false)
+    }
+
+    fn write_escaped(&mut self, text: &str, attr_mode: bool) -> Result<(), Error> {
+        for c in text.chars() {
+            match c {
+                '&' => self.writer.write_all(b"&amp;"),
+                '<' => self.writer.write_all(b"&lt;"),
+                '>' => self.writer.write_all(b"&gt;"),
+                '"' if attr_mode => self.writer.write_all(b"&quot;"),
+                c => write!(self.writer, "{c}"),
+            }?;
+        }
+        Ok(())
+    }
+}
+
+fn is_void(name: &QualName) -> bool {
+    name.ns == ns!(html)
+        && matches!(
+            name.local,
+            local_name!("area")
+                | local_name!("base")
+                | local_name!("basefont")
+                | local_name!("bgsound")
+                | local_name!("br")
+                | local_name!("col")
+                | local_name!("embed")
+                | local_name!("frame")
+                | local_name!("hr")
+                | local_name!("img")
+                | local_name!("input")
+                | local_name!("keygen")
+                | local_name!("link")
+                | local_name!("meta")
+                | local_name!("param")
+                | local_name!("source")
+                | local_name!("track")
+                | local_name!("wbr")
+        )
+}
+
+impl<Wr: Write> TextSerializer for XhtmlSerializer<Wr> {
+    fn write_text_node(&mut self, text: &Text) -> Result<(), Error> {
+        self.write_text(text)
+    }
+}
+
+impl<Wr: Write> Serializer for XhtmlSerializer<Wr> {
+    fn start_elem<'a, AttrIter>(&mut self, name: QualName, attrs: AttrIter) -> Result<(), Error>
+    where
+        AttrIter: Iterator<Item = AttrRef<'a>>,
+    {
+        if self.ignoring() {
+            self.ignore_children.push(true);
+            return Ok(());
+        }
+
+        write!(self.writer, "<{}", name.local)?;
+        for (attr, value) in attrs {
+            self.writer.write_all(b" ")?;
+            match attr.ns {
+                ns!(xml) => self.writer.write_all(b"xml:")?,
+                ns!(xmlns) if attr.local != local_name!("xmlns") => {
+                    self.writer.write_all(b"xmlns:")?
+                }
+                ns!(xlink) => self.writer.write_all(b"xlink:")?,
+                _ => (),
+            }
+            write!(self.writer, "{}=\"", attr.local)?;
+            self.write_escaped(value, true)?;
+            self.writer.write_all(b"\"")?;
+        }
+
+        let void = is_void(&name);
+        self.writer.write_all(if void { b" />" } else { b">" })?;
+        self.ignore_children.push(void);
+        Ok(())
+    }
+
+    fn end_elem(&mut self, name: QualName) -> Result<(), Error> {
+        if self.ignore_children.pop().unwrap_or(false) {
+            return Ok(());
+        }
+        write!(self.writer, "</{}>", name.local)
+    }
+
+    fn write_text(&mut self, text: &str) -> Result<(), Error> {
+        if self.ignoring() {
+            return Ok(());
+        }
+        self.write_escaped(text, false)
+    }
+
+    fn write_comment(&mut self, text: &str) -> Result<(), Error> {
+        if self.ignoring() {
+            return Ok(());
+        }
+        write!(self.writer, "<!--{text}-->")
+    }
+
+    fn write_doctype(&mut self, name: &str) -> Result<(), Error> {
+        write!(self.writer, "<!DOCTYPE {name}>")
+    }
+
+    fn write_processing_instruction(&mut self, target: &str, data: &str) -> Result<(), Error> {
+        write!(self.writer, "<?{target} {data}?>")
+    }
+}
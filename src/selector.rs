@@ -1,22 +1,53 @@
 //! CSS selectors.
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt;
+use std::marker::PhantomData;
 
 pub use cssparser::ToCss;
-use html5ever::{LocalName, Namespace};
+use html5ever::LocalName;
 use selectors::{
     matching,
-    parser::{self, ParseRelative, SelectorList, SelectorParseErrorKind},
+    parser::{Combinator, Component, ParseRelative, SelectorList},
     NthIndexCache,
 };
 
-use crate::error::SelectorErrorKind;
+pub(crate) use self::bloom::AncestorFilter;
+pub use self::builder::{CombinatorStep, SelectorBuilder};
+pub use self::explain::{
+    ComponentExplanation, CompoundExplanation, MatchExplanation, Relation, SelectorExplanation,
+};
+pub use self::extensions::Extensions;
+use self::parser::ParserConfig;
+pub use self::parser::{
+    CssLocalName, CssString, CustomPseudoClass, Direction, NonTSPseudoClass, Parser, PseudoElement,
+    Simple,
+};
+pub use self::profile::{MatchProfile, SelectorProfile};
+
+use crate::error::{NamedSelectorError, SelectorParseError, SelectorParseWarning};
 use crate::ElementRef;
 
 /// Wrapper around CSS selectors.
 ///
 /// Represents a "selector group", i.e. a comma-separated list of selectors.
+///
+/// The relational pseudo-class `:has()` is supported. Matching it walks the candidate element's
+/// descendants (or following siblings, for `:has(+ x)` and `:has(~ x)`) for every candidate, so
+/// selectors like `div:has(p)` can be expensive on large documents.
+///
+/// `:nth-child()` and `:nth-last-child()` accept an `of S` clause, as in
+/// `li:nth-child(2 of .item)`, counting only the siblings matching the selector list `S`.
+///
+/// The negation pseudo-class `:not()` accepts a list of complex selectors, as in
+/// `:not(div > .ad, aside *)`, including compound selectors like `:not(.a.b)` and nested
+/// negations like `:not(:not(:first-child))`.
+///
+/// Since a parsed document is never displayed, selectors copied from stylesheets are accepted as
+/// if the user never interacted with it: `:link` and `:any-link` match every `<a>` and `<area>`
+/// element with an `href` attribute, as no link has been visited, while `:visited`, `:hover`,
+/// `:active`, `:focus`, `:focus-visible` and `:focus-within` never match.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Selector {
     /// The CSS selectors.
@@ -25,13 +56,474 @@ pub struct Selector {
 
 impl Selector {
     /// Parses a CSS selector group.
-    pub fn parse(selectors: &'_ str) -> Result<Self, SelectorErrorKind> {
+    ///
+    /// On failure, the returned error records where in `selectors` parsing stopped.
+    pub fn parse(selectors: &str) -> Result<Self, SelectorParseError> {
+        Self::parse_with_namespaces(selectors, &[])
+    }
+
+    /// Parses named selectors, reporting every invalid one instead of only the first.
+    ///
+    /// If a name occurs more than once, the last selector with that name is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Selector;
+    ///
+    /// let errors = Selector::parse_many([("title", "h1"), ("price", ".price >"), ("image", "img[")])
+    ///     .unwrap_err();
+    /// let names = errors.iter().map(|error| error.name()).collect::<Vec<_>>();
+    /// assert_eq!(names, ["price", "image"]);
+    ///
+    /// let selectors = Selector::parse_many([("title", "h1"), ("price", ".price")]).unwrap();
+    /// assert_eq!(selectors["price"], Selector::parse(".price").unwrap());
+    /// ```
+    pub fn parse_many<'a, I>(
+        selectors: I,
+    ) -> Result<HashMap<String, Selector>, Vec<NamedSelectorError>>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut parsed = HashMap::new();
+        let mut errors = Vec::new();
+        for (name, css) in selectors {
+            match Selector::parse(css) {
+                Ok(selector) => {
+                    parsed.insert(name.to_owned(), selector);
+                }
+                Err(error) => errors.push(NamedSelectorError::new(name, error)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(parsed)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses selectors, reporting every invalid one, together with its index, instead of only
+    /// the first.
+    pub fn parse_all(
+        selectors: &[&str],
+    ) -> Result<Vec<Selector>, Vec<(usize, SelectorParseError)>> {
+        let mut parsed = Vec::with_capacity(selectors.len());
+        let mut errors = Vec::new();
+        for (index, css) in selectors.iter().enumerate() {
+            match Selector::parse(css) {
+                Ok(selector) => parsed.push(selector),
+                Err(error) => errors.push((index, error)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(parsed)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the CSS text of this selector group.
+    ///
+    /// The result parses back to an equivalent selector, but is not necessarily identical to
+    /// the original input, e.g. whitespace is normalized.
+    pub fn css(&self) -> String {
+        self.to_css_string()
+    }
+
+    /// Parses a CSS selector group, resolving namespace prefixes with the given
+    /// `(prefix, namespace URL)` pairs.
+    ///
+    /// The prefixes `svg`, `mathml`, `xlink` and `xml` are always available, also to
+    /// [`Selector::parse`], but can be overridden here. Type selectors without a prefix match
+    /// elements in any namespace, while attribute selectors without a prefix only match
+    /// attributes in no namespace, so `[href]` matches `href` but not `xlink:href`, which is
+    /// matched by `[xlink|href]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let fragment = Html::parse_fragment(r#"<a href="/html"></a><svg><a href="/svg"></a></svg>"#);
+    /// let selector = Selector::parse_with_namespaces(
+    ///     "html|a",
+    ///     &[("html", "http://www.w3.org/1999/xhtml")],
+    /// ).unwrap();
+    ///
+    /// let hrefs = fragment.select(&selector).map(|a| a.attr("href").unwrap()).collect::<Vec<_>>();
+    /// assert_eq!(hrefs, ["/html"]);
+    /// ```
+    pub fn parse_with_namespaces(
+        selectors: &str,
+        namespaces: &[(&str, &str)],
+    ) -> Result<Self, SelectorParseError> {
+        Self::parse_with_config(
+            selectors,
+            ParserConfig {
+                namespaces,
+                extensions: None,
+            },
+        )
+    }
+
+    /// Parses a CSS selector group which may use the custom pseudo-classes registered in
+    /// `extensions`.
+    ///
+    /// Custom pseudo-classes can appear anywhere a built-in pseudo-class can, including inside
+    /// `:not()` and `:has()`. Pseudo-classes which are neither built in nor registered are still
+    /// rejected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    /// use scraper::selector::Extensions;
+    ///
+    /// let mut extensions = Extensions::new();
+    /// extensions.add_functional_pseudo_class("has-attr", |element, name| {
+    ///     element.attr(name).is_some()
+    /// });
+    ///
+    /// let fragment = Html::parse_fragment(r#"<p data-price="3">A</p><p>B</p>"#);
+    /// let selector = Selector::parse_with_extensions("p:not(:has-attr(data-price))", &extensions)
+    ///     .unwrap();
+    ///
+    /// let texts = fragment.select(&selector).map(|p| p.inner_html()).collect::<Vec<_>>();
+    /// assert_eq!(texts, ["B"]);
+    /// ```
+    pub fn parse_with_extensions(
+        selectors: &str,
+        extensions: &Extensions,
+    ) -> Result<Self, SelectorParseError> {
+        Self::parse_with_config(
+            selectors,
+            ParserConfig {
+                namespaces: &[],
+                extensions: Some(extensions),
+            },
+        )
+    }
+
+    /// Parses a CSS selector group whose selectors may start with a combinator, as accepted by
+    /// jQuery's `find`.
+    ///
+    /// A selector starting with a combinator is relative to the `:scope` element, so `> li` is
+    /// parsed as `:scope > li`. With [`ElementRef::select`], this finds the children of the
+    /// element, and `+ p` and `~ p` find its following siblings. Other selectors are parsed as by
+    /// [`Selector::parse`]. The returned error refers to the selector with `:scope` inserted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment("<ul><li>a<ul><li>b</li></ul></li></ul><p>c</p>");
+    /// let ul = html.select(&Selector::parse("ul").unwrap()).next().unwrap();
+    ///
+    /// let children = Selector::parse_relative("> li").unwrap();
+    /// assert_eq!(children.css(), ":scope > li");
+    /// assert_eq!(ul.select(&children).count(), 1);
+    ///
+    /// let next = Selector::parse_relative("+ p").unwrap();
+    /// assert_eq!(ul.select(&next).next().unwrap().inner_html(), "c");
+    /// ```
+    pub fn parse_relative(selectors: &str) -> Result<Self, SelectorParseError> {
+        let mut parser_input = cssparser::ParserInput::new(selectors);
+        let mut parser = cssparser::Parser::new(&mut parser_input);
+
+        let mut absolute = Vec::new();
+        loop {
+            parser.skip_whitespace();
+            let start = parser.position();
+            let relative = parser
+                .try_parse(|parser| match parser.next()? {
+                    cssparser::Token::Delim('>' | '+' | '~') => Ok(()),
+                    _ => Err(parser.new_error_for_next_token::<()>()),
+                })
+                .is_ok();
+            let _ = parser.parse_until_before(cssparser::Delimiter::Comma, |parser| {
+                while parser.next().is_ok() {}
+                Ok::<_, cssparser::ParseError<()>>(())
+            });
+
+            let selector = parser.slice_from(start).trim_end();
+            if relative {
+                absolute.push(format!(":scope {}", selector));
+            } else {
+                absolute.push(selector.to_owned());
+            }
+
+            if parser.next().is_err() {
+                break;
+            }
+        }
+
+        Self::parse(&absolute.join(", "))
+    }
+
+    /// Returns the selectors of this group starting with `:scope` followed by a sibling
+    /// combinator, e.g. `:scope + p`, together with their indices in the group.
+    ///
+    /// These can match elements outside the `:scope` element, which [`ElementRef::select`] then
+    /// also visits.
+    pub(crate) fn sibling_relative(&self) -> Option<(Self, Vec<usize>)> {
+        let (indices, selectors): (Vec<_>, Vec<_>) = self
+            .selectors
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, selector)| {
+                let mut components = selector.iter_raw_parse_order_from(0);
+                matches!(components.next(), Some(Component::Scope))
+                    && matches!(
+                        components.next(),
+                        Some(Component::Combinator(
+                            Combinator::NextSibling | Combinator::LaterSibling
+                        ))
+                    )
+            })
+            .map(|(index, selector)| (index, selector.clone()))
+            .unzip();
+
+        if indices.is_empty() {
+            None
+        } else {
+            let selectors = SelectorList(selectors.into_iter().collect());
+            Some((Self { selectors }, indices))
+        }
+    }
+
+    /// Parses a CSS selector group, dropping the selectors of the comma-separated list which fail
+    /// to parse instead of failing as a whole.
+    ///
+    /// This matches the forgiving parsing of the arguments of `:is()` and `:where()`. Returns the
+    /// group of the remaining selectors, or `None` if none remain, together with a warning for
+    /// every dropped selector.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Selector;
+    ///
+    /// let (selector, warnings) = Selector::parse_forgiving("h1, :bogus(3), .title");
+    ///
+    /// assert_eq!(selector.unwrap().css(), "h1, .title");
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(warnings[0].selector(), ":bogus(3)");
+    /// assert_eq!(warnings[0].span(), 4..13);
+    /// ```
+    pub fn parse_forgiving(selectors: &str) -> (Option<Self>, Vec<SelectorParseWarning>) {
         let mut parser_input = cssparser::ParserInput::new(selectors);
         let mut parser = cssparser::Parser::new(&mut parser_input);
+        let config = ParserConfig::default();
 
-        SelectorList::parse(&Parser, &mut parser, ParseRelative::No)
+        let mut survivors = Vec::new();
+        let mut warnings = Vec::new();
+        loop {
+            parser.skip_whitespace();
+            let start = parser.position();
+            let selector = parser.parse_until_before(cssparser::Delimiter::Comma, |parser| {
+                selectors::parser::Selector::parse(&config, parser)
+            });
+
+            match selector {
+                Ok(selector) => survivors.push(selector),
+                Err(err) => {
+                    let text = parser.slice_from(start);
+                    let start = start.byte_index();
+                    let end = start + text.trim_end().len();
+                    warnings.push(SelectorParseWarning::new(
+                        start..end,
+                        SelectorParseError::new(selectors, err),
+                    ));
+                }
+            }
+
+            if parser.next().is_err() {
+                break;
+            }
+        }
+
+        let selector = (!survivors.is_empty()).then(|| Self {
+            selectors: SelectorList(survivors.into_iter().collect()),
+        });
+        (selector, warnings)
+    }
+
+    /// Parses a CSS selector group, dropping the parts which fail to parse instead of failing as
+    /// a whole.
+    ///
+    /// Unlike [`Selector::parse_forgiving`], which drops whole selectors of the comma-separated
+    /// list, this drops individual simple selectors, like `:bogus` or `[=x]`, and combinators,
+    /// keeping the rest of each selector. Returns a warning for every dropped part. If nothing
+    /// remains, the returned selector matches no element.
+    ///
+    /// This is meant for selectors from sources which cannot be fixed, like messy configuration.
+    /// Dropping parts changes what a selector means and usually makes it match more elements:
+    /// `div:bogus > a` matches every link directly inside a `div`, and `a >> b` becomes `a > b`.
+    /// Check the warnings before relying on the result.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Selector;
+    ///
+    /// let (selector, warnings) = Selector::parse_lenient("ul.menu:hovering > li, a[=x]");
+    ///
+    /// assert_eq!(selector.css(), "ul.menu > li, a");
+    /// let dropped = warnings.iter().map(|w| w.selector()).collect::<Vec<_>>();
+    /// assert_eq!(dropped, [":hovering", "[=x]"]);
+    /// ```
+    pub fn parse_lenient(selectors: &str) -> (Self, Vec<SelectorParseWarning>) {
+        let (selector, warnings) = Self::lenient(selectors);
+        let selector = selector.unwrap_or_else(|| Self::parse(":not(*)").unwrap());
+        (selector, warnings)
+    }
+
+    fn parse_with_config(
+        selectors: &str,
+        config: ParserConfig<'_>,
+    ) -> Result<Self, SelectorParseError> {
+        let mut parser_input = cssparser::ParserInput::new(selectors);
+        let mut parser = cssparser::Parser::new(&mut parser_input);
+
+        SelectorList::parse(&config, &mut parser, ParseRelative::No)
             .map(|selectors| Self { selectors })
-            .map_err(SelectorErrorKind::from)
+            .map_err(|err| SelectorParseError::new(selectors, err))
+    }
+
+    /// Returns an iterator over the comma-separated selectors of this group, each as a
+    /// standalone `Selector`.
+    ///
+    /// Each component matches exactly the elements it would match if it had been parsed on its
+    /// own.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Selector;
+    ///
+    /// let selector = Selector::parse("h1, h2, .title").unwrap();
+    /// let components = selector.components().map(|s| s.css()).collect::<Vec<_>>();
+    /// assert_eq!(components, ["h1", "h2", ".title"]);
+    /// ```
+    pub fn components(&self) -> impl Iterator<Item = Selector> + '_ {
+        self.selectors.0.iter().map(|selector| Self {
+            selectors: SelectorList(std::iter::once(selector.clone()).collect()),
+        })
+    }
+
+    /// Returns the number of comma-separated selectors in this group.
+    pub fn len(&self) -> usize {
+        self.selectors.0.len()
+    }
+
+    /// Returns true if this group contains no selectors.
+    ///
+    /// Parsing never produces an empty group, so this is only true for selectors built by
+    /// other means.
+    pub fn is_empty(&self) -> bool {
+        self.selectors.0.is_empty()
+    }
+
+    /// Returns the specificity of this selector.
+    ///
+    /// For a selector group, this is the highest specificity among its selectors, i.e. the
+    /// specificity with which the group matches an element matched by all of its selectors.
+    pub fn specificity(&self) -> Specificity {
+        self.selectors
+            .0
+            .iter()
+            .map(|selector| Specificity::from_packed(selector.specificity()))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Returns the element name required by the rightmost compound selector of every selector
+    /// in the group, if they all require the same one.
+    ///
+    /// Every element matched by this selector then has that name, which allows looking up
+    /// candidate elements in an index. The name is returned as written in the selector: it
+    /// matches elements of XML documents exactly, but HTML elements ASCII case-insensitively,
+    /// so it should be lowercased to look up the elements of an HTML document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Selector;
+    ///
+    /// let selector = Selector::parse("nav > a.external, footer a[href]").unwrap();
+    /// assert_eq!(selector.rightmost_local_name(), Some("a"));
+    ///
+    /// let selector = Selector::parse("channel > Item").unwrap();
+    /// assert_eq!(selector.rightmost_local_name(), Some("Item"));
+    ///
+    /// let selector = Selector::parse("a, area").unwrap();
+    /// assert_eq!(selector.rightmost_local_name(), None);
+    /// ```
+    pub fn rightmost_local_name(&self) -> Option<&str> {
+        self.rightmost_common(|component| match component {
+            Component::LocalName(name) => Some(&*name.name.0),
+            _ => None,
+        })
+        .into_iter()
+        .next()
+    }
+
+    /// Returns an ID required by the rightmost compound selector of every selector in the group,
+    /// if there is one.
+    pub fn rightmost_id(&self) -> Option<&str> {
+        self.rightmost_common(|component| match component {
+            Component::ID(id) => Some(&*id.0),
+            _ => None,
+        })
+        .into_iter()
+        .next()
+    }
+
+    /// Returns the classes required by the rightmost compound selector of every selector in the
+    /// group.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::Selector;
+    ///
+    /// let selector = Selector::parse(".product.sale > .price, .product .price.old").unwrap();
+    /// assert_eq!(selector.rightmost_classes(), ["price"]);
+    /// ```
+    pub fn rightmost_classes(&self) -> Vec<&str> {
+        self.rightmost_common(|component| match component {
+            Component::Class(class) => Some(&*class.0),
+            _ => None,
+        })
+    }
+
+    /// Returns the values `extract` yields for the rightmost compound selector of every
+    /// selector in the group, in the order of the first selector.
+    fn rightmost_common<'a, F>(&'a self, extract: F) -> Vec<&'a str>
+    where
+        F: Fn(&'a Component<Simple>) -> Option<&'a str>,
+    {
+        let mut selectors = self
+            .selectors
+            .0
+            .iter()
+            // Iterating stops at the first combinator, i.e. after the rightmost compound.
+            .map(|selector| selector.iter().filter_map(&extract).collect::<Vec<_>>());
+
+        let mut common = Vec::new();
+        for value in selectors.next().unwrap_or_default() {
+            if !common.contains(&value) {
+                common.push(value);
+            }
+        }
+        for values in selectors {
+            common.retain(|value| values.contains(value));
+        }
+        common
     }
 
     /// Returns true if the element matches this selector.
@@ -42,10 +534,93 @@ impl Selector {
     /// Returns true if the element matches this selector.
     /// The optional `scope` argument is used to specify which element has `:scope` pseudo-class.
     /// When it is `None`, `:scope` will match the root element.
+    ///
+    /// This is the same as [`Selector::matches_in_scope`].
     pub fn matches_with_scope(&self, element: &ElementRef, scope: Option<ElementRef>) -> bool {
+        self.matches_in_scope(element, scope)
+    }
+
+    /// Returns true if the element matches this selector, with `scope` as the element matched by
+    /// the `:scope` pseudo-class.
+    ///
+    /// When `scope` is `None`, `:scope` matches the root element. Passing the element a search
+    /// starts from matches like [`ElementRef::select`], e.g. `:scope > p` only matches children
+    /// of `scope`. Note that, unlike `ElementRef::select`, this does not require `element` to be
+    /// a descendant of `scope`.
+    ///
+    /// Matching structural pseudo-classes like `:nth-child` computes sibling positions from
+    /// scratch on every call; use [`Selector::matches_in_scope_cached`] to reuse them when
+    /// matching many elements.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let fragment = Html::parse_fragment("<div><p>1</p><section><p>2</p></section></div>");
+    /// let div = fragment.select(&Selector::parse("div").unwrap()).next().unwrap();
+    /// let selector = Selector::parse(":scope > p").unwrap();
+    ///
+    /// let matches = div
+    ///     .descendent_elements()
+    ///     .filter(|element| selector.matches_in_scope(element, Some(div)))
+    ///     .map(|p| p.inner_html())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(matches, ["1"]);
+    /// ```
+    pub fn matches_in_scope(&self, element: &ElementRef, scope: Option<ElementRef>) -> bool {
         self.matches_with_scope_and_cache(element, scope, &mut NthIndexCache::default())
     }
 
+    /// Returns true if the element matches this selector, like [`Selector::matches_in_scope`],
+    /// reusing the caches of `context` across calls.
+    pub fn matches_in_scope_cached<'a>(
+        &'a self,
+        element: &ElementRef<'a>,
+        scope: Option<ElementRef<'a>>,
+        context: &mut MatchContext<'a>,
+    ) -> bool {
+        self.matches_with_scope_and_cache(element, scope, &mut context.nth_index_cache)
+    }
+
+    /// Returns the elements matching this selector, in the order given.
+    ///
+    /// This is equivalent to filtering with [`Selector::matches`], but shares the caches of
+    /// structural pseudo-classes like `:nth-child` between the elements, and does not look at any
+    /// other elements of the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use scraper::{Html, Selector};
+    ///
+    /// let html = Html::parse_fragment("<ul><li>1</li><li>2</li><li>3</li></ul>");
+    /// let items = html.select(&Selector::parse("li").unwrap()).collect::<Vec<_>>();
+    ///
+    /// let odd = Selector::parse(":nth-child(odd)").unwrap().filter(items);
+    /// assert_eq!(odd.iter().map(|li| li.inner_html()).collect::<Vec<_>>(), ["1", "3"]);
+    /// ```
+    pub fn filter<'a, I>(&self, elements: I) -> Vec<ElementRef<'a>>
+    where
+        I: IntoIterator<Item = ElementRef<'a>>,
+    {
+        self.partition(elements).0
+    }
+
+    /// Splits the elements into those matching this selector and those not matching it, each
+    /// in the order given.
+    ///
+    /// Like [`Selector::filter`], this shares caches between the elements.
+    pub fn partition<'a, I>(&self, elements: I) -> (Vec<ElementRef<'a>>, Vec<ElementRef<'a>>)
+    where
+        I: IntoIterator<Item = ElementRef<'a>>,
+    {
+        let mut nth_index_cache = NthIndexCache::default();
+        elements.into_iter().partition(|element| {
+            self.matches_with_scope_and_cache(element, None, &mut nth_index_cache)
+        })
+    }
+
     // The `nth_index_cache` must not be used after `self` is dropped
     // to avoid incorrect results (even though no undefined behaviour is possible)
     // due to the usage of selector memory addresses as cache keys.
@@ -55,22 +630,104 @@ impl Selector {
         scope: Option<ElementRef>,
         nth_index_cache: &mut NthIndexCache,
     ) -> bool {
-        let mut context = matching::MatchingContext::new(
-            matching::MatchingMode::Normal,
-            None,
-            nth_index_cache,
-            matching::QuirksMode::NoQuirks,
-            matching::NeedsSelectorFlags::No,
-            matching::IgnoreNthChildForInvalidation::No,
-        );
-        context.scope_element = scope.map(|x| selectors::Element::opaque(&x));
+        self.matching_branch(element, scope, nth_index_cache)
+            .is_some()
+    }
+
+    /// Returns the index of the first selector of the group which matches `element`.
+    pub(crate) fn matching_branch(
+        &self,
+        element: &ElementRef,
+        scope: Option<ElementRef>,
+        nth_index_cache: &mut NthIndexCache,
+    ) -> Option<usize> {
+        let mut context = matching_context(scope, nth_index_cache);
+        self.selectors
+            .0
+            .iter()
+            .position(|s| matching::matches_selector(s, 0, None, element, &mut context))
+    }
+
+    /// Returns, for each selector of the group, the tag names, IDs and classes which its
+    /// rightmost compound selector requires of every matching element.
+    pub(crate) fn subject_keys(&self) -> impl Iterator<Item = Vec<SubjectKey<'_>>> {
+        self.selectors.0.iter().map(|selector| {
+            selector
+                .iter()
+                .filter_map(|component| match component {
+                    Component::ID(id) => Some(SubjectKey::Id(&id.0)),
+                    Component::Class(class) => Some(SubjectKey::Class(&class.0)),
+                    // Which of the names is compared depends on the element.
+                    Component::LocalName(name) if name.name == name.lower_name => {
+                        Some(SubjectKey::LocalName(&name.name.0))
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    /// Returns IDs such that every element matching the group has an ancestor with one of them,
+    /// or `None` if some selector of the group requires no ancestor with an ID.
+    ///
+    /// For `#comments .author`, this is `comments`. Only IDs in compound selectors followed by a
+    /// child or descendant combinator count, which includes `nav` in `#nav li + li a`, since
+    /// siblings share their ancestors, but not `a` in `#a ~ p span`.
+    pub(crate) fn anchor_ids(&self) -> Option<Vec<&LocalName>> {
         self.selectors
             .0
             .iter()
-            .any(|s| matching::matches_selector(s, 0, None, element, &mut context))
+            .map(|selector| {
+                let mut iter = selector.iter();
+                let mut ancestor = false;
+                loop {
+                    let id = iter.by_ref().find_map(|component| match component {
+                        Component::ID(id) => Some(&id.0),
+                        _ => None,
+                    });
+                    if id.is_some() {
+                        if ancestor {
+                            return id;
+                        }
+                        // Skip the rest of the compound selector.
+                        for _ in iter.by_ref() {}
+                    }
+                    ancestor = match iter.next_sequence()? {
+                        Combinator::Child | Combinator::Descendant => true,
+                        Combinator::NextSibling | Combinator::LaterSibling => false,
+                        _ => return None,
+                    };
+                }
+            })
+            .collect()
     }
 }
 
+/// A tag name, ID or class required of the elements matching a selector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SubjectKey<'a> {
+    LocalName(&'a LocalName),
+    Id(&'a LocalName),
+    Class(&'a LocalName),
+}
+
+/// Returns the context for matching selectors with `:scope` matching `scope`.
+fn matching_context<'c>(
+    scope: Option<ElementRef>,
+    nth_index_cache: &'c mut NthIndexCache,
+) -> matching::MatchingContext<'c, Simple> {
+    let mut context = matching::MatchingContext::new(
+        matching::MatchingMode::Normal,
+        None,
+        nth_index_cache,
+        matching::QuirksMode::NoQuirks,
+        matching::NeedsSelectorFlags::No,
+        matching::IgnoreNthChildForInvalidation::No,
+    );
+    context.scope_element = scope.map(|x| selectors::Element::opaque(&x));
+    context
+}
+
 impl ToCss for Selector {
     fn to_css<W>(&self, dest: &mut W) -> fmt::Result
     where
@@ -80,133 +737,309 @@ impl ToCss for Selector {
     }
 }
 
-/// An implementation of `Parser` for `selectors`
-#[derive(Clone, Copy, Debug)]
-pub struct Parser;
-impl<'i> parser::Parser<'i> for Parser {
-    type Impl = Simple;
-    type Error = SelectorParseErrorKind<'i>;
+/// The specificity of a selector, as used by CSS to rank competing rules.
+///
+/// Specificities are ordered by their ID count, then their class-like count (classes, attributes
+/// and pseudo-classes), then their element count (type selectors and pseudo-elements).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Specificity {
+    /// The number of ID selectors.
+    pub id: u32,
+    /// The number of class, attribute and pseudo-class selectors.
+    pub class: u32,
+    /// The number of type and pseudo-element selectors.
+    pub element: u32,
 }
 
-/// A simple implementation of `SelectorImpl` with no pseudo-classes or pseudo-elements.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Simple;
-
-impl parser::SelectorImpl for Simple {
-    type AttrValue = CssString;
-    type Identifier = CssLocalName;
-    type LocalName = CssLocalName;
-    type NamespacePrefix = CssLocalName;
-    type NamespaceUrl = Namespace;
-    type BorrowedNamespaceUrl = Namespace;
-    type BorrowedLocalName = CssLocalName;
-
-    type NonTSPseudoClass = NonTSPseudoClass;
-    type PseudoElement = PseudoElement;
+impl Specificity {
+    /// Unpacks the representation used by `selectors`, which stores each count in 10 bits.
+    fn from_packed(packed: u32) -> Self {
+        const MASK: u32 = (1 << 10) - 1;
 
-    // see: https://github.com/servo/servo/pull/19747#issuecomment-357106065
-    type ExtraMatchingData<'a> = ();
+        Self {
+            id: packed >> 20,
+            class: (packed >> 10) & MASK,
+            element: packed & MASK,
+        }
+    }
 }
 
-/// Wraps [`String`] so that it can be used with [`selectors`]
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct CssString(pub String);
+/// State shared between selector matches on the same documents.
+///
+/// Selecting elements with structural pseudo-classes like `:nth-child` computes the position of
+/// elements among their siblings. [`Html::select`] and [`ElementRef::select`] cache these
+/// positions only for the duration of a single call, whereas passing the same context to
+/// [`Html::select_with_context`], [`ElementRef::select_with_context`] or
+/// [`Selector::matches_in_scope_cached`] reuses them across calls and selectors.
+///
+/// The cache is keyed by the addresses of elements and selectors, so the lifetime `'a` ensures
+/// that every document and selector used with a context outlives it.
+///
+/// # Example
+///
+/// ```
+/// use scraper::{Html, Selector};
+/// use scraper::selector::MatchContext;
+///
+/// let html = Html::parse_fragment("<table><tr><td>1</td><td>2</td></tr></table>");
+/// let first = Selector::parse("td:nth-child(1)").unwrap();
+/// let second = Selector::parse("td:nth-child(2)").unwrap();
+///
+/// let mut context = MatchContext::new();
+/// assert_eq!(html.select_with_context(&first, &mut context).count(), 1);
+/// assert_eq!(html.select_with_context(&second, &mut context).count(), 1);
+/// ```
+///
+/// [`Html::select`]: crate::Html::select
+/// [`Html::select_with_context`]: crate::Html::select_with_context
+/// [`ElementRef::select_with_context`]: crate::ElementRef::select_with_context
+#[derive(Default)]
+pub struct MatchContext<'a> {
+    nth_index_cache: NthIndexCache,
+    marker: PhantomData<&'a Selector>,
+}
 
-impl<'a> From<&'a str> for CssString {
-    fn from(val: &'a str) -> Self {
-        Self(val.to_owned())
+impl MatchContext<'_> {
+    /// Creates a context with empty caches.
+    pub fn new() -> Self {
+        Self::default()
     }
 }
 
-impl AsRef<str> for CssString {
-    fn as_ref(&self) -> &str {
-        &self.0
+impl fmt::Debug for MatchContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MatchContext").finish_non_exhaustive()
     }
 }
 
-impl ToCss for CssString {
-    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
-    where
-        W: fmt::Write,
-    {
-        cssparser::serialize_string(&self.0, dest)
-    }
+/// The `NthIndexCache` of a select iterator, either owned by it or borrowed from a
+/// [`MatchContext`].
+pub(crate) enum SelectCache<'b> {
+    Owned(NthIndexCache),
+    Shared(&'b mut NthIndexCache),
 }
 
-/// Wraps [`LocalName`] so that it can be used with [`selectors`]
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub struct CssLocalName(pub LocalName);
+impl<'b> SelectCache<'b> {
+    pub(crate) fn shared(context: &'b mut MatchContext<'_>) -> Self {
+        Self::Shared(&mut context.nth_index_cache)
+    }
 
-impl<'a> From<&'a str> for CssLocalName {
-    fn from(val: &'a str) -> Self {
-        Self(val.into())
+    pub(crate) fn get(&mut self) -> &mut NthIndexCache {
+        match self {
+            Self::Owned(cache) => cache,
+            Self::Shared(cache) => cache,
+        }
     }
 }
 
-impl ToCss for CssLocalName {
-    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
-    where
-        W: fmt::Write,
-    {
-        dest.write_str(&self.0)
+impl Default for SelectCache<'_> {
+    fn default() -> Self {
+        Self::Owned(NthIndexCache::default())
     }
 }
 
-/// Non Tree-Structural Pseudo-Class.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum NonTSPseudoClass {}
-
-impl parser::NonTSPseudoClass for NonTSPseudoClass {
-    type Impl = Simple;
-
-    fn is_active_or_hover(&self) -> bool {
-        false
+/// Serializes the selector group back to CSS which parses to an equivalent selector.
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_css(f)
     }
+}
 
-    fn is_user_action_state(&self) -> bool {
-        false
+impl<'i> TryFrom<&'i str> for Selector {
+    type Error = SelectorParseError;
+
+    fn try_from(s: &'i str) -> Result<Self, Self::Error> {
+        Selector::parse(s)
     }
 }
 
-impl ToCss for NonTSPseudoClass {
-    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
-    where
-        W: fmt::Write,
-    {
-        dest.write_str("")
+#[cfg(feature = "serde")]
+impl serde::Serialize for Selector {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
     }
 }
 
-/// CSS Pseudo-Element
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PseudoElement {}
+/// Deserializes a selector from its CSS text, failing with the [`SelectorParseError`] message if
+/// it is invalid.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Selector {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SelectorVisitor;
 
-impl parser::PseudoElement for PseudoElement {
-    type Impl = Simple;
-}
+        impl serde::de::Visitor<'_> for SelectorVisitor {
+            type Value = Selector;
 
-impl ToCss for PseudoElement {
-    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
-    where
-        W: fmt::Write,
-    {
-        dest.write_str("")
-    }
-}
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a CSS selector")
+            }
 
-impl<'i> TryFrom<&'i str> for Selector {
-    type Error = SelectorErrorKind<'i>;
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Selector, E> {
+                Selector::parse(s).map_err(E::custom)
+            }
+        }
 
-    fn try_from(s: &'i str) -> Result<Self, Self::Error> {
-        Selector::parse(s)
+        deserializer.deserialize_str(SelectorVisitor)
     }
 }
 
+mod bloom;
+mod builder;
+mod explain;
+mod extensions;
+mod lenient;
+mod parser;
+mod profile;
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::convert::TryInto;
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_toml() {
+        #[derive(Debug, serde::Deserialize, serde::Serialize)]
+        struct Rules {
+            title: Selector,
+            links: Selector,
+        }
+
+        let rules: Rules = toml::from_str(
+            r#"
+            title = "h1.title"
+            links = "nav > a[href], footer a"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.title, Selector::parse("h1.title").unwrap());
+        assert_eq!(
+            toml::to_string(&rules).unwrap(),
+            "title = \"h1.title\"\nlinks = \"nav > a[href], footer a\"\n"
+        );
+
+        let err = toml::from_str::<Rules>(
+            r#"
+            title = "h1.title"
+            links = "nav > > a"
+            "#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("links"), "{}", err);
+        assert!(err.contains("at line 1, column 7"), "{}", err);
+    }
+
+    #[test]
+    fn filter_and_partition() {
+        let rows = "<tr><td>a</td><td class=x>b</td><td>c</td></tr>".repeat(334);
+        let html = crate::Html::parse_document(&format!("<table>{}</table>", rows));
+        let cells = html
+            .select(&Selector::parse("td").unwrap())
+            .collect::<Vec<_>>();
+        assert!(cells.len() > 1000);
+
+        for selector in [
+            "td:nth-child(2)",
+            "tr:nth-child(3n+1) > td:nth-last-child(odd)",
+            "td:nth-of-type(3), .x",
+            "tr:nth-child(even) td:not(.x)",
+        ] {
+            let selector = Selector::parse(selector).unwrap();
+            let expected = cells
+                .iter()
+                .copied()
+                .filter(|cell| selector.matches(cell))
+                .collect::<Vec<_>>();
+
+            assert_eq!(selector.filter(cells.iter().copied()), expected);
+
+            let (matched, unmatched) = selector.partition(cells.iter().copied());
+            assert_eq!(matched, expected);
+            assert_eq!(matched.len() + unmatched.len(), cells.len());
+            assert!(unmatched.iter().all(|cell| !selector.matches(cell)));
+        }
+    }
+
+    #[test]
+    fn rightmost_constraints() {
+        let check = |selector: &str, name: Option<&str>, id: Option<&str>, classes: &[&str]| {
+            let selector = Selector::parse(selector).unwrap();
+            assert_eq!(selector.rightmost_local_name(), name, "{}", selector);
+            assert_eq!(selector.rightmost_id(), id, "{}", selector);
+            assert_eq!(selector.rightmost_classes(), classes, "{}", selector);
+        };
+
+        check("*", None, None, &[]);
+        check("[href], :first-child", None, None, &[]);
+        check("div", Some("div"), None, &[]);
+        check("DIV#Main.a.b", Some("DIV"), Some("Main"), &["a", "b"]);
+        check("DIV, div", None, None, &[]);
+        check("#x > p.a", Some("p"), None, &["a"]);
+        check("p.a ~ .b", None, None, &["b"]);
+        check(".a:not(.b)", None, None, &["a"]);
+        check("a.x.y, a.y.z", Some("a"), None, &["y"]);
+        check("a.x, b.x", None, None, &["x"]);
+        check("a#i, #i", None, Some("i"), &[]);
+        check("a, a.x", Some("a"), None, &[]);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn rightmost_local_name_xml() {
+        let feed = crate::Html::parse_xml("<rss><Item>1</Item><item>2</item><Item>3</Item></rss>");
+        let selector = Selector::parse("rss > Item").unwrap();
+        let name = selector.rightmost_local_name().unwrap();
+
+        assert_eq!(name, "Item");
+        let names = feed
+            .select(&selector)
+            .map(|element| element.value().name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, [name, name]);
+    }
+
+    #[test]
+    fn parse_many() {
+        let errors = Selector::parse_many([
+            ("title", "h1.title"),
+            ("price", "span.price >"),
+            ("links", "a[href]"),
+            ("image", "img:bogus"),
+        ])
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].name(), "price");
+        assert_eq!(errors[0].selector(), "span.price >");
+        assert_eq!(errors[1].name(), "image");
+        assert_eq!(errors[1].error().column(), 5);
+        assert!(errors[1]
+            .to_string()
+            .starts_with("Invalid selector \"image\": "));
+
+        let selectors =
+            Selector::parse_many([("title", "h1"), ("links", "a[href]"), ("title", "h2")]).unwrap();
+        assert_eq!(selectors.len(), 2);
+        assert_eq!(selectors["title"], Selector::parse("h2").unwrap());
+    }
+
+    #[test]
+    fn parse_all() {
+        let errors = Selector::parse_all(&["p", "p >", "a", "[", "b"]).unwrap_err();
+        assert_eq!(
+            errors.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            [1, 3]
+        );
+        assert_eq!(errors[1].1.input(), "[");
+
+        let selectors = Selector::parse_all(&["p", "a"]).unwrap();
+        assert_eq!(
+            selectors,
+            [Selector::parse("p").unwrap(), Selector::parse("a").unwrap()]
+        );
+    }
+
     #[test]
     fn selector_conversions() {
         let s = "#testid.testclass";
@@ -222,4 +1055,745 @@ mod tests {
         let s = "<failing selector>";
         let _sel: Selector = s.try_into().unwrap();
     }
+
+    #[test]
+    fn components_match_like_standalone_selectors() {
+        let html = crate::Html::parse_fragment(
+            r#"<h1 id="a">A</h1><h2 id="b">B</h2><p class="title" id="c">C</p><h3 id="d">D</h3>"#,
+        );
+        let selector = Selector::parse("h1, h2, .title").unwrap();
+        assert_eq!(selector.len(), 3);
+        assert!(!selector.is_empty());
+
+        let components = selector.components().collect::<Vec<_>>();
+        assert_eq!(components.len(), 3);
+
+        for (component, css) in components.iter().zip(["h1", "h2", ".title"]) {
+            assert_eq!(component.len(), 1);
+            assert_eq!(component, &Selector::parse(css).unwrap());
+
+            let standalone = Selector::parse(css).unwrap();
+            let ids = |selector: &Selector| {
+                html.select(selector)
+                    .map(|element| element.value().id().unwrap())
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(ids(component), ids(&standalone));
+            assert_eq!(ids(component).len(), 1);
+        }
+    }
+
+    const MIXED_NAMESPACES: &str = r#"
+        <a id="html-a" href="/a"></a>
+        <svg id="svg"><a id="svg-a" href="/b"><circle id="circle"></circle></a></svg>
+        <math id="math"><mi id="mi">x</mi></math>
+    "#;
+
+    fn select_ns_ids(selector: &str, namespaces: &[(&str, &str)]) -> Vec<String> {
+        let html = crate::Html::parse_fragment(MIXED_NAMESPACES);
+        let selector = Selector::parse_with_namespaces(selector, namespaces).unwrap();
+        html.select(&selector)
+            .map(|element| element.value().id().unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn builtin_namespaces() {
+        assert_eq!(select_ns_ids("svg|a", &[]), ["svg-a"]);
+        assert_eq!(select_ns_ids("*|a", &[]), ["html-a", "svg-a"]);
+        assert_eq!(select_ns_ids("a", &[]), ["html-a", "svg-a"]);
+        assert_eq!(select_ns_ids("svg|*", &[]), ["svg", "svg-a", "circle"]);
+        assert_eq!(select_ns_ids("mathml|*", &[]), ["math", "mi"]);
+        assert_eq!(select_ns_ids("svg|a > svg|circle", &[]), ["circle"]);
+
+        let selector = Selector::parse("svg|a").unwrap();
+        assert_eq!(
+            selector,
+            Selector::parse_with_namespaces("svg|a", &[]).unwrap()
+        );
+    }
+
+    #[test]
+    fn registered_namespaces() {
+        let namespaces = [
+            ("h", "http://www.w3.org/1999/xhtml"),
+            ("s", "http://www.w3.org/2000/svg"),
+        ];
+        assert_eq!(select_ns_ids("h|a", &namespaces), ["html-a"]);
+        assert_eq!(select_ns_ids("s|a", &namespaces), ["svg-a"]);
+
+        let overridden = [("svg", "http://www.w3.org/1999/xhtml")];
+        assert_eq!(select_ns_ids("svg|a", &overridden), ["html-a"]);
+    }
+
+    const XLINK: &str = r##"
+        <svg id="svg" xml:lang="en">
+            <use id="icon" xlink:href="#icon"></use>
+            <use id="logo" xlink:href="#logo" href="/logo.svg"></use>
+            <a id="link" href="/"></a>
+        </svg>
+    "##;
+
+    #[test]
+    fn namespaced_attributes() {
+        let html = crate::Html::parse_fragment(XLINK);
+        let ids = |selector: &str| {
+            html.select(&Selector::parse(selector).unwrap())
+                .map(|element| element.value().id().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("[xlink|href]"), ["icon", "logo"]);
+        assert_eq!(ids(r##"use[xlink|href="#icon"]"##), ["icon"]);
+        assert_eq!(ids("[xlink|href^='#l']"), ["logo"]);
+        assert_eq!(ids("[href]"), ["logo", "link"]);
+        assert_eq!(ids("[*|href]"), ["icon", "logo", "link"]);
+        assert_eq!(ids("[xml|lang=en]"), ["svg"]);
+        assert!(ids("[svg|href]").is_empty());
+
+        let xlink = [("x", "http://www.w3.org/1999/xlink")];
+        let selector = Selector::parse_with_namespaces("[x|href]", &xlink).unwrap();
+        assert_eq!(html.select(&selector).count(), 2);
+    }
+
+    #[test]
+    fn unknown_namespace_prefix() {
+        let err = Selector::parse("foo|a").unwrap_err();
+        assert_eq!(err.token(), Some("foo"));
+        assert!(Selector::parse_with_namespaces("foo|a", &[("foo", "urn:foo")]).is_ok());
+    }
+
+    fn specificity(css: &str) -> Specificity {
+        Selector::parse(css).unwrap().specificity()
+    }
+
+    #[test]
+    fn specificity_components() {
+        let s = |id, class, element| Specificity { id, class, element };
+
+        assert_eq!(specificity("#a"), s(1, 0, 0));
+        assert_eq!(specificity(".a.b"), s(0, 2, 0));
+        assert_eq!(specificity("div span"), s(0, 0, 2));
+        assert_eq!(specificity("a[href]:first-child"), s(0, 2, 1));
+        assert_eq!(specificity("*"), s(0, 0, 0));
+        assert_eq!(specificity("div, #a .b, p.c"), s(1, 1, 0));
+    }
+
+    #[test]
+    fn specificity_ordering() {
+        assert!(specificity("#a") > specificity(".a.b"));
+        assert!(specificity(".a.b") > specificity("div span"));
+        assert!(specificity("div span") > specificity("div"));
+        assert!(specificity(".a") > specificity("div span p ul li"));
+        assert_eq!(specificity("div .a"), specificity("span .b"));
+    }
+
+    fn assert_round_trip(css: &str, expected: &str) {
+        let selector = Selector::parse(css).unwrap();
+        assert_eq!(selector.to_string(), expected);
+        assert_eq!(selector.css(), expected);
+        assert_eq!(Selector::parse(expected).unwrap(), selector);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        assert_round_trip("div.item  >  a", "div.item > a");
+        assert_round_trip(r#"a[title="say \"hi\""]"#, r#"a[title="say \"hi\""]"#);
+        assert_round_trip("[data-x='it\\'s' i]", r#"[data-x="it's" i]"#);
+        assert_round_trip(".foo\\:bar", ".foo\\:bar");
+        assert_round_trip("#\\31 23", "#\\31 23");
+        assert_round_trip("li:nth-child(2n+1)", "li:nth-child(2n+1)");
+        assert_round_trip("p:not(.a, .b)", "p:not(.a, .b)");
+        assert_round_trip("h1,h2 ,  .title", "h1, h2, .title");
+    }
+
+    fn select_ids(html: &str, selector: &str) -> Vec<String> {
+        let html = crate::Html::parse_fragment(html);
+        let selector = Selector::parse(selector).unwrap();
+        html.select(&selector)
+            .filter_map(|element| element.value().id().map(str::to_owned))
+            .collect()
+    }
+
+    const PRODUCTS: &str = r#"
+        <div class="product" id="a"><span class="sale-badge"></span></div>
+        <div class="product" id="b"><p><span class="sale-badge"></span></p></div>
+        <div class="product" id="c"><span class="price"></span></div>
+        <div class="product" id="d"><p></p></div><span class="sale-badge"></span>
+    "#;
+
+    const NEGATION: &str = r#"
+        <div id="d1" class="a b">
+            <p id="p1" class="a">x</p>
+            <p id="p2" class="b">y</p>
+        </div>
+        <section id="s1" class="a">
+            <p id="p3">z</p>
+            <a id="l1" class="b"></a>
+        </section>
+        <a id="l2"></a>
+    "#;
+
+    #[test]
+    fn not_compound() {
+        assert_eq!(
+            select_ids(NEGATION, "[id]:not(.a.b)"),
+            ["p1", "p2", "s1", "p3", "l1", "l2"]
+        );
+        assert_eq!(select_ids(NEGATION, "p:not(:not(.a))"), ["p1"]);
+    }
+
+    #[test]
+    fn not_simple() {
+        let nav = r#"
+            <ul>
+                <li id="i1" class="active">a</li>
+                <li id="i2" hidden>b</li>
+                <li id="i3">c</li>
+            </ul>
+        "#;
+        assert_eq!(select_ids(nav, "li:not(.active)"), ["i2", "i3"]);
+        assert_eq!(select_ids(nav, "li:not([hidden])"), ["i1", "i3"]);
+        assert_eq!(select_ids(nav, "li:not(.active):not([hidden])"), ["i3"]);
+        assert_eq!(select_ids(nav, "li:not(:not(:first-child))"), ["i1"]);
+        assert_eq!(
+            select_ids(nav, "li:not(:not(.active, [hidden]))"),
+            ["i1", "i2"]
+        );
+    }
+
+    #[test]
+    fn not_complex() {
+        assert_eq!(
+            select_ids(NEGATION, "[id]:not(div p)"),
+            ["d1", "s1", "p3", "l1", "l2"]
+        );
+        assert_eq!(select_ids(NEGATION, "p:not(div p)"), ["p3"]);
+        assert_eq!(
+            select_ids(NEGATION, "[id]:not(div > .a)"),
+            ["d1", "p2", "s1", "p3", "l1", "l2"]
+        );
+        assert_eq!(
+            select_ids(NEGATION, "[id]:not(section *)"),
+            ["d1", "p1", "p2", "s1", "l2"]
+        );
+        assert_eq!(
+            select_ids(NEGATION, "[id]:not(.a + .b)"),
+            ["d1", "p1", "s1", "p3", "l1", "l2"]
+        );
+    }
+
+    #[test]
+    fn not_list() {
+        assert_eq!(select_ids(NEGATION, "[id]:not(a, .b)"), ["p1", "s1", "p3"]);
+        assert_eq!(
+            select_ids(NEGATION, "[id]:not(div p, section > *)"),
+            ["d1", "s1", "l2"]
+        );
+    }
+
+    #[test]
+    fn not_structural() {
+        assert_eq!(
+            select_ids(NEGATION, "[id]:not(:first-child)"),
+            ["p2", "s1", "l1", "l2"]
+        );
+    }
+
+    const LANGUAGES: &str = r#"
+        <div lang="de-AT" id="de">
+            <p id="de-p"><span id="de-span">Grüß Gott</span></p>
+            <p lang="fr" id="fr-p">Bonjour</p>
+            <p lang="" id="unknown">?</p>
+            <svg id="svg" xml:lang="en" lang="de"></svg>
+        </div>
+        <div dir="rtl" id="rtl">
+            <p id="rtl-p" dir="sideways"><span id="rtl-span">مرحبا</span></p>
+            <p dir="LTR" id="ltr-p"></p>
+            <p dir="auto" id="auto-p"><span id="auto-span"></span></p>
+        </div>
+    "#;
+
+    #[test]
+    fn lang() {
+        assert_eq!(
+            select_ids(LANGUAGES, ":lang(de)"),
+            ["de", "de-p", "de-span"]
+        );
+        assert_eq!(select_ids(LANGUAGES, "span:lang(DE-at)"), ["de-span"]);
+        assert_eq!(select_ids(LANGUAGES, "p:lang(fr)"), ["fr-p"]);
+        assert!(select_ids(LANGUAGES, ":lang(d)").is_empty());
+        assert!(select_ids(LANGUAGES, ":lang(de-AT-x)").is_empty());
+        assert_eq!(select_ids(LANGUAGES, "p:lang(\"fr\", en)"), ["fr-p"]);
+        assert_eq!(select_ids(LANGUAGES, ":lang(en)"), ["svg"]);
+        assert_eq!(select_ids(LANGUAGES, "p:lang(\"*\")"), ["de-p", "fr-p"]);
+    }
+
+    #[test]
+    fn dir() {
+        assert_eq!(
+            select_ids(LANGUAGES, ":dir(rtl)"),
+            ["rtl", "rtl-p", "rtl-span"]
+        );
+        assert_eq!(
+            select_ids(LANGUAGES, "p:dir(ltr)"),
+            ["de-p", "fr-p", "unknown", "ltr-p"]
+        );
+        assert!(select_ids(LANGUAGES, "[id^=auto]:dir(ltr), [id^=auto]:dir(rtl)").is_empty());
+        assert!(Selector::parse(":dir(up)").is_err());
+        assert!(Selector::parse(":dir(ltr rtl)").is_err());
+        assert!(Selector::parse(":lang()").is_err());
+    }
+
+    const TOTALS: &str = r#"
+        <table>
+            <tr><td id="item">Subtotal</td><td id="item-value">10</td></tr>
+            <tr><td id="total">Grand <b>To</b>tal</td><td id="total-value">12</td></tr>
+            <tr><td id="note">"total" includes tax</td></tr>
+        </table>
+    "#;
+
+    #[test]
+    fn contains() {
+        assert_eq!(select_ids(TOTALS, "td:contains(\"Total\")"), ["total"]);
+        assert_eq!(select_ids(TOTALS, "td:contains(Total)"), ["total"]);
+        assert_eq!(
+            select_ids(TOTALS, "td:contains(\"Grand Total\") + td"),
+            ["total-value"]
+        );
+        assert_eq!(
+            select_ids(TOTALS, "td:contains(\"\\\"total\\\"\")"),
+            ["note"]
+        );
+        assert!(select_ids(TOTALS, "td:contains(\"Totals\")").is_empty());
+        assert!(Selector::parse("td:contains(Grand Total)").is_err());
+        assert!(Selector::parse("td:contains(1)").is_err());
+    }
+
+    #[test]
+    fn icontains() {
+        assert_eq!(
+            select_ids(TOTALS, "td:icontains(\"TOTAL\")"),
+            ["item", "total", "note"]
+        );
+        assert_eq!(
+            select_ids(TOTALS, "td:icontains(\"grand total\")"),
+            ["total"]
+        );
+    }
+
+    const LINKS: &str = r##"
+        <a id="link" href="/">Home</a>
+        <a id="anchor" name="top">Top</a>
+        <map><area id="area" href="#x"><area id="no-href"></map>
+        <link id="stylesheet" rel="stylesheet" href="/style.css">
+        <button id="button">Go</button>
+    "##;
+
+    #[test]
+    fn link() {
+        assert_eq!(select_ids(LINKS, "a:link"), ["link"]);
+        assert_eq!(select_ids(LINKS, ":link"), ["link", "area"]);
+        assert_eq!(select_ids(LINKS, ":any-link"), ["link", "area"]);
+        assert_eq!(select_ids(LINKS, ":LINK"), ["link", "area"]);
+        assert_eq!(select_ids(LINKS, "a:not(:link)"), ["anchor"]);
+    }
+
+    #[test]
+    fn never_matching_pseudo_classes() {
+        for pseudo_class in [
+            ":visited",
+            ":hover",
+            ":active",
+            ":focus",
+            ":focus-visible",
+            ":focus-within",
+        ] {
+            assert!(select_ids(LINKS, &format!("a{}", pseudo_class)).is_empty());
+            assert!(select_ids(LINKS, &format!("button{}", pseudo_class)).is_empty());
+            assert_eq!(
+                select_ids(LINKS, &format!("button:not({})", pseudo_class)),
+                ["button"]
+            );
+            assert_round_trip(pseudo_class, pseudo_class);
+        }
+        assert_eq!(select_ids(LINKS, "a:link, a:visited"), ["link"]);
+        assert!(Selector::parse("a:hover-ish").is_err());
+    }
+
+    #[test]
+    fn pseudo_class_round_trip() {
+        assert_round_trip("td:contains(Total)", "td:contains(\"Total\")");
+        assert_round_trip(":icontains('a\\'b')", ":icontains(\"a'b\")");
+        assert_round_trip(":lang(de-AT, \"*\")", ":lang(de-AT, \"*\")");
+        assert_round_trip(":lang(\"fr\")", ":lang(fr)");
+        assert_round_trip("p:dir(RTL)", "p:dir(rtl)");
+        assert_round_trip("li:nth-child(2n+1 of .a, b)", "li:nth-child(2n+1 of .a, b)");
+    }
+
+    const INTERLEAVED: &str = r#"
+        <ul>
+            <li id="1" class="item"></li>
+            <li id="2" class="ad"></li>
+            <li id="3" class="item"></li>
+            <li id="4" class="item"></li>
+            <li id="5" class="ad"></li>
+            <li id="6" class="item featured"></li>
+        </ul>
+    "#;
+
+    #[test]
+    fn nth_child_of() {
+        assert_eq!(select_ids(INTERLEAVED, "li:nth-child(2)"), ["2"]);
+        assert_eq!(select_ids(INTERLEAVED, "li:nth-child(2 of .item)"), ["3"]);
+        assert_eq!(
+            select_ids(INTERLEAVED, "li:nth-child(odd of .item)"),
+            ["1", "4"]
+        );
+        assert_eq!(
+            select_ids(INTERLEAVED, "li:nth-last-child(1 of .item, .ad)"),
+            ["6"]
+        );
+        assert_eq!(
+            select_ids(INTERLEAVED, "li:nth-last-child(2 of :not(.featured))"),
+            ["4"]
+        );
+        // Elements not matching the inner selector never match.
+        assert!(select_ids(INTERLEAVED, ".ad:nth-child(1 of .item)").is_empty());
+        assert!(Selector::parse("li:nth-of-type(2 of .item)").is_err());
+    }
+
+    #[test]
+    fn nth_child_of_cached() {
+        let html = crate::Html::parse_fragment(INTERLEAVED);
+        let selector = Selector::parse("li:nth-child(2n of .item)").unwrap();
+        let mut context = MatchContext::new();
+        let ids = html
+            .select_with_context(&selector, &mut context)
+            .filter_map(|element| element.value().id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["3", "6"]);
+    }
+
+    #[test]
+    fn has_child() {
+        assert_eq!(
+            select_ids(PRODUCTS, "div.product:has(> .sale-badge)"),
+            ["a"]
+        );
+    }
+
+    #[test]
+    fn has_descendant() {
+        assert_eq!(
+            select_ids(PRODUCTS, "div.product:has(.sale-badge)"),
+            ["a", "b"]
+        );
+        assert_eq!(select_ids(PRODUCTS, "div:has(p span)"), ["b"]);
+    }
+
+    #[test]
+    fn has_sibling() {
+        assert_eq!(select_ids(PRODUCTS, "div:has(+ .sale-badge)"), ["d"]);
+        assert_eq!(
+            select_ids(PRODUCTS, "div:has(~ .sale-badge)"),
+            ["a", "b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn has_no_match() {
+        assert!(select_ids(PRODUCTS, "div.product:has(> .missing)").is_empty());
+        assert!(select_ids(PRODUCTS, "div.product:has(> p > .price)").is_empty());
+    }
+
+    #[test]
+    fn not_has() {
+        assert_eq!(
+            select_ids(PRODUCTS, "div.product:not(:has(.sale-badge))"),
+            ["c", "d"]
+        );
+    }
+
+    fn price_extensions() -> Extensions {
+        let mut extensions = Extensions::new();
+        extensions
+            .add_functional_pseudo_class("has-attr", |element, name| element.attr(name).is_some())
+            .add_pseudo_class("price", |element| {
+                element
+                    .attr("data-price")
+                    .is_some_and(|price| price.parse::<f64>().is_ok())
+            });
+        extensions
+    }
+
+    const PRICES: &str = r#"
+        <span class="item" id="a" data-price="3.50"></span>
+        <span class="item sold" id="b" data-price="n/a"></span>
+        <span class="item" id="c"></span>
+        <span id="d" data-price="1"></span>
+    "#;
+
+    fn select_ids_with_extensions(html: &str, selector: &str) -> Vec<String> {
+        let html = crate::Html::parse_fragment(html);
+        let selector = Selector::parse_with_extensions(selector, &price_extensions()).unwrap();
+        html.select(&selector)
+            .filter_map(|element| element.value().id().map(str::to_owned))
+            .collect()
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(
+            select_ids_with_extensions(PRICES, ".item:has-attr(data-price)"),
+            ["a", "b"]
+        );
+        assert_eq!(
+            select_ids_with_extensions(PRICES, ".item:HAS-ATTR( data-price )"),
+            ["a", "b"]
+        );
+        assert_eq!(
+            select_ids_with_extensions(PRICES, ":price:not(.item)"),
+            ["d"]
+        );
+        assert_eq!(
+            select_ids_with_extensions(PRICES, ".item:not(:price, .sold)"),
+            ["c"]
+        );
+        assert_eq!(
+            select_ids_with_extensions(PRICES, ".item:not(:has-attr(data-price))"),
+            ["c"]
+        );
+    }
+
+    #[test]
+    fn extensions_unknown() {
+        let extensions = price_extensions();
+        assert!(Selector::parse_with_extensions(":cheap", &extensions).is_err());
+        assert!(Selector::parse_with_extensions(":price(1)", &extensions).is_err());
+        assert!(Selector::parse_with_extensions(":has-attr", &extensions).is_err());
+        assert!(Selector::parse(":price").is_err());
+    }
+
+    #[test]
+    fn extensions_display() {
+        let selector =
+            Selector::parse_with_extensions("a:price, b:has-attr( x )", &price_extensions())
+                .unwrap();
+        assert_eq!(selector.css(), "a:price, b:has-attr(x)");
+    }
+
+    #[test]
+    fn matches_in_scope_like_select() {
+        let html = crate::Html::parse_fragment(
+            r#"
+            <div id="outer">
+                <b id="b1"></b>
+                <span id="s1">
+                    <span id="s2"><b id="b2"></b></span>
+                    <b id="b3"></b>
+                    <i id="i1"></i>
+                </span>
+            </div>
+            "#,
+        );
+        let scope = html
+            .select(&Selector::parse("#s1").unwrap())
+            .next()
+            .unwrap();
+
+        let selectors = [
+            ":scope > b",
+            ":scope b",
+            ":scope > :nth-child(2)",
+            ":scope > span b",
+            ":scope + *",
+            ":not(:scope) > b:last-of-type",
+            "b",
+        ]
+        .map(|css| Selector::parse(css).unwrap());
+
+        // The selectors must outlive the context.
+        let mut context = MatchContext::new();
+        for selector in &selectors {
+            let css = selector.css();
+            let expected = scope.select(selector).collect::<Vec<_>>();
+
+            let descendants = scope.descendent_elements().skip(1).collect::<Vec<_>>();
+            let matched = descendants
+                .iter()
+                .copied()
+                .filter(|element| selector.matches_in_scope(element, Some(scope)))
+                .collect::<Vec<_>>();
+            assert_eq!(matched, expected, "{css}");
+
+            let matched = descendants
+                .into_iter()
+                .filter(|element| {
+                    selector.matches_in_scope_cached(element, Some(scope), &mut context)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(matched, expected, "{css}");
+        }
+    }
+
+    #[test]
+    fn parse_forgiving() {
+        let (selector, warnings) = Selector::parse_forgiving("h1, h2, :bogus(3), .title");
+        let selector = selector.unwrap();
+
+        let html = crate::Html::parse_fragment(
+            r#"<h1 id="a"></h1><h2 id="b"></h2><h3 id="c"></h3><p class="title" id="d"></p>"#,
+        );
+        let ids = html
+            .select(&selector)
+            .filter_map(|element| element.value().id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["a", "b", "d"]);
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].selector(), ":bogus(3)");
+        assert_eq!(warnings[0].span(), 8..17);
+        assert!(warnings[0].span().contains(&warnings[0].error().offset()));
+        assert!(warnings[0]
+            .to_string()
+            .starts_with("Ignored selector \":bogus(3)\": "));
+    }
+
+    #[test]
+    fn parse_forgiving_drops_everything() {
+        let (selector, warnings) = Selector::parse_forgiving(" div >  ,, [x= ");
+        assert!(selector.is_none());
+
+        let dropped = warnings
+            .iter()
+            .map(|warning| warning.selector())
+            .collect::<Vec<_>>();
+        assert_eq!(dropped, ["div >", "", "[x="]);
+    }
+
+    #[test]
+    fn parse_forgiving_valid() {
+        let (selector, warnings) = Selector::parse_forgiving("a:not(.b), c > d");
+        assert_eq!(selector, Some(Selector::parse("a:not(.b), c > d").unwrap()));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let (selector, warnings) = Selector::parse_lenient("div:bogus(3) > p.a, h1[=x]");
+        assert_eq!(selector, Selector::parse("div > p.a, h1").unwrap());
+
+        let html = crate::Html::parse_fragment(
+            r#"<div><p class="a" id="a"></p></div><h1 id="b"></h1><p class="a" id="c"></p>"#,
+        );
+        let ids = html
+            .select(&selector)
+            .filter_map(|element| element.value().id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["a", "b"]);
+
+        let dropped = warnings
+            .iter()
+            .map(|warning| warning.selector())
+            .collect::<Vec<_>>();
+        assert_eq!(dropped, [":bogus(3)", "[=x]"]);
+        for warning in &warnings {
+            assert!(warning.span().contains(&warning.error().offset()));
+        }
+    }
+
+    #[test]
+    fn parse_lenient_combinators() {
+        let (selector, warnings) = Selector::parse_lenient("a >> b, c >");
+        assert_eq!(selector, Selector::parse("a > b, c").unwrap());
+
+        let dropped = warnings
+            .iter()
+            .map(|warning| warning.selector())
+            .collect::<Vec<_>>();
+        assert_eq!(dropped, [">", ">"]);
+    }
+
+    #[test]
+    fn parse_lenient_drops_everything() {
+        let (selector, warnings) = Selector::parse_lenient(":bogus, , [=x]");
+        assert_eq!(selector, Selector::parse(":not(*)").unwrap());
+
+        let html = crate::Html::parse_fragment("<p></p>");
+        assert_eq!(html.select(&selector).count(), 0);
+
+        let dropped = warnings
+            .iter()
+            .map(|warning| warning.selector())
+            .collect::<Vec<_>>();
+        assert_eq!(dropped, [":bogus", "", "[=x]"]);
+    }
+
+    #[test]
+    fn parse_lenient_valid() {
+        let (selector, warnings) = Selector::parse_lenient("a:not(.b), c > d");
+        assert_eq!(selector, Selector::parse("a:not(.b), c > d").unwrap());
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn anchor_ids() {
+        let anchors = |css| {
+            Selector::parse(css)
+                .unwrap()
+                .anchor_ids()
+                .map(|ids| ids.into_iter().map(|id| id.to_string()).collect::<Vec<_>>())
+        };
+
+        assert_eq!(anchors("#a .b"), Some(vec!["a".to_owned()]));
+        assert_eq!(anchors("div#a > p#b.c"), Some(vec!["a".to_owned()]));
+        assert_eq!(anchors("#a #b c"), Some(vec!["b".to_owned()]));
+        assert_eq!(anchors("#a li + li a"), Some(vec!["a".to_owned()]));
+        assert_eq!(anchors("#a .b + .c"), Some(vec!["a".to_owned()]));
+        assert_eq!(
+            anchors("#a p, #b > p"),
+            Some(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert_eq!(anchors("#a"), None);
+        assert_eq!(anchors("#a ~ p span"), None);
+        assert_eq!(anchors("#a + p"), None);
+        assert_eq!(anchors("#a p, p"), None);
+        assert_eq!(anchors(":not(#a) p"), None);
+    }
+
+    #[test]
+    fn parse_relative() {
+        let html = crate::Html::parse_fragment(
+            r#"
+            <p id="p1"></p>
+            <ul id="list"><li id="a"><ul><li id="b"></li></ul></li></ul>
+            <p id="p2"><span id="s1"></span></p>
+            <span id="s2"></span>
+            "#,
+        );
+        let list = html
+            .select(&Selector::parse("#list").unwrap())
+            .next()
+            .unwrap();
+        let select_ids = |css| {
+            list.select(&Selector::parse_relative(css).unwrap())
+                .map(|element| element.value().id().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(select_ids("> li"), ["a"]);
+        assert_eq!(select_ids("li"), ["a", "b"]);
+        assert_eq!(select_ids("+ p"), ["p2"]);
+        assert_eq!(select_ids("~ span"), ["s2"]);
+        assert_eq!(select_ids("~ * span"), ["s1"]);
+        assert_eq!(select_ids("> li > ul > li, + p, li ~ li"), ["b", "p2"]);
+    }
+
+    #[test]
+    fn parse_relative_css() {
+        let selector = Selector::parse_relative(" > li , + p,a ~ b").unwrap();
+        assert_eq!(selector.css(), ":scope > li, :scope + p, a ~ b");
+        assert!(Selector::parse_relative("> ").is_err());
+    }
 }
@@ -0,0 +1,127 @@
+//! Fast rejection of selectors requiring ancestors which an element does not have.
+//!
+//! While traversing a subtree, the hashes of the names, namespaces, IDs and classes of the
+//! elements enclosing the current position are kept in a bloom filter. Before walking up the
+//! ancestors of an element to match a selector like `.results .item a`, `selectors` checks that
+//! the filter may contain `.results` and `.item`, rejecting most elements without visiting a
+//! single ancestor.
+
+use ego_tree::NodeRef;
+use precomputed_hash::PrecomputedHash;
+use selectors::bloom::{BloomFilter, BLOOM_HASH_MASK};
+use selectors::matching::{self, QuirksMode};
+use selectors::parser::AncestorHashes;
+use selectors::NthIndexCache;
+
+use super::{matching_context, CssLocalName, Selector};
+use crate::node::Element;
+use crate::{ElementRef, Node};
+
+impl PrecomputedHash for CssLocalName {
+    fn precomputed_hash(&self) -> u32 {
+        self.0.precomputed_hash()
+    }
+}
+
+/// A bloom filter of the elements enclosing the current position of a traversal, together with
+/// the ancestor hashes of each selector of the group matched during it.
+#[derive(Clone)]
+pub(crate) struct AncestorFilter {
+    filter: Box<BloomFilter>,
+    /// The ancestor hashes of each selector of the group, in order.
+    hashes: Vec<AncestorHashes>,
+    /// The hashes inserted for every enclosing element, outermost first.
+    inserted: Vec<u32>,
+    /// The number of hashes inserted for every enclosing element, outermost first.
+    counts: Vec<usize>,
+}
+
+impl AncestorFilter {
+    /// Returns a filter for matching `selector` starting inside the innermost of `ancestors`,
+    /// given innermost first, or `None` if no selector of the group requires any ancestor.
+    pub(crate) fn new<'a>(
+        selector: &Selector,
+        ancestors: impl Iterator<Item = NodeRef<'a, Node>>,
+    ) -> Option<Self> {
+        let hashes = selector
+            .selectors
+            .0
+            .iter()
+            .map(|selector| AncestorHashes::new(selector, QuirksMode::NoQuirks))
+            .collect::<Vec<_>>();
+        if hashes.iter().all(|hashes| hashes.packed_hashes[0] == 0) {
+            return None;
+        }
+
+        let mut filter = Self {
+            filter: Box::default(),
+            hashes,
+            inserted: Vec::new(),
+            counts: Vec::new(),
+        };
+        let ancestors = ancestors.filter_map(ElementRef::wrap).collect::<Vec<_>>();
+        for ancestor in ancestors.iter().rev() {
+            filter.push(ancestor.value());
+        }
+        Some(filter)
+    }
+
+    /// Enters `element`, which encloses the elements matched next.
+    pub(crate) fn push(&mut self, element: &Element) {
+        let start = self.inserted.len();
+        self.inserted.push(element.name.local.precomputed_hash());
+        self.inserted.push(element.name.ns.precomputed_hash());
+        if let Some(id) = element.id() {
+            self.inserted
+                .push(CssLocalName::from(id).precomputed_hash());
+        }
+        self.inserted.extend(
+            element
+                .class_names()
+                .iter()
+                .map(PrecomputedHash::precomputed_hash),
+        );
+
+        for hash in &self.inserted[start..] {
+            self.filter.insert_hash(hash & BLOOM_HASH_MASK);
+        }
+        self.counts.push(self.inserted.len() - start);
+    }
+
+    /// Leaves the innermost enclosing element.
+    pub(crate) fn pop(&mut self) {
+        let Some(count) = self.counts.pop() else {
+            return;
+        };
+        let start = self.inserted.len() - count;
+        for hash in self.inserted.drain(start..) {
+            self.filter.remove_hash(hash & BLOOM_HASH_MASK);
+        }
+    }
+}
+
+impl Selector {
+    /// Like `matching_branch`, but fast-rejecting selectors whose ancestors are missing from
+    /// `filter`, which must hold at least the elements enclosing `element`.
+    pub(crate) fn matching_branch_filtered(
+        &self,
+        element: &ElementRef,
+        scope: Option<ElementRef>,
+        nth_index_cache: &mut NthIndexCache,
+        filter: Option<&AncestorFilter>,
+    ) -> Option<usize> {
+        let Some(filter) = filter else {
+            return self.matching_branch(element, scope, nth_index_cache);
+        };
+
+        let mut context = matching_context(scope, nth_index_cache);
+        context.bloom_filter = Some(&filter.filter);
+        self.selectors
+            .0
+            .iter()
+            .zip(&filter.hashes)
+            .position(|(selector, hashes)| {
+                matching::matches_selector(selector, 0, Some(hashes), element, &mut context)
+            })
+    }
+}
@@ -0,0 +1,325 @@
+//! Building selectors without formatting CSS text by hand.
+
+use std::fmt::{self, Write};
+
+use cssparser::{serialize_identifier, serialize_string};
+
+use super::Selector;
+
+/// Builds a [`Selector`] step by step, escaping names and values as needed.
+///
+/// A builder holds a complex selector, i.e. compound selectors joined by combinators. Combinator
+/// methods like [`child`](Self::child) return a [`CombinatorStep`], which only allows starting
+/// the next compound selector, so that every builder describes a valid selector.
+///
+/// # Example
+///
+/// ```
+/// use scraper::{Html, selector::SelectorBuilder};
+///
+/// let selector = SelectorBuilder::tag("div")
+///     .class("item:new")
+///     .attr_eq("data-id", "5")
+///     .child()
+///     .tag("a")
+///     .build();
+/// assert_eq!(selector.to_string(), r#"div.item\:new[data-id="5"] > a"#);
+///
+/// let html = Html::parse_fragment(r#"<div class="item:new" data-id="5"><a>Link</a></div>"#);
+/// assert_eq!(html.select(&selector).count(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct SelectorBuilder {
+    /// The CSS text built so far.
+    css: String,
+}
+
+/// A [`SelectorBuilder`] ending in a combinator, waiting for the next compound selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct CombinatorStep {
+    css: String,
+}
+
+impl SelectorBuilder {
+    /// Starts a selector with a compound selector matching elements named `name`.
+    pub fn tag(name: &str) -> Self {
+        Self::from_css(String::new()).type_selector(name)
+    }
+
+    /// Starts a selector with a compound selector matching any element.
+    pub fn any() -> Self {
+        Self::from_css("*".to_owned())
+    }
+
+    fn from_css(css: String) -> Self {
+        SelectorBuilder { css }
+    }
+
+    fn type_selector(mut self, name: &str) -> Self {
+        if name.is_empty() {
+            // No element has an empty name.
+            self.css.push_str("*:not(*)");
+        } else {
+            serialize_identifier(name, &mut self.css).unwrap();
+        }
+        self
+    }
+
+    /// Restricts the current compound selector to elements having the class `class`.
+    pub fn class(mut self, class: &str) -> Self {
+        if class.is_empty() {
+            // Class lists never contain empty classes.
+            self.css.push_str(":not(*)");
+        } else {
+            self.css.push('.');
+            serialize_identifier(class, &mut self.css).unwrap();
+        }
+        self
+    }
+
+    /// Restricts the current compound selector to elements with the ID `id`.
+    pub fn id(mut self, id: &str) -> Self {
+        if id.is_empty() {
+            // An empty `id` attribute does not give the element an ID.
+            self.css.push_str(":not(*)");
+        } else {
+            self.css.push('#');
+            serialize_identifier(id, &mut self.css).unwrap();
+        }
+        self
+    }
+
+    /// Restricts the current compound selector to elements having the attribute `name`.
+    pub fn attr(self, name: &str) -> Self {
+        self.attr_selector(name, None)
+    }
+
+    /// Restricts the current compound selector to elements whose attribute `name` is exactly
+    /// `value`.
+    pub fn attr_eq(self, name: &str, value: &str) -> Self {
+        self.attr_selector(name, Some(("=", value)))
+    }
+
+    /// Restricts the current compound selector to elements whose attribute `name` contains
+    /// `value` as one of its whitespace-separated words.
+    pub fn attr_includes(self, name: &str, value: &str) -> Self {
+        self.attr_selector(name, Some(("~=", value)))
+    }
+
+    /// Restricts the current compound selector to elements whose attribute `name` starts with
+    /// `value`.
+    pub fn attr_starts_with(self, name: &str, value: &str) -> Self {
+        self.attr_selector(name, Some(("^=", value)))
+    }
+
+    /// Restricts the current compound selector to elements whose attribute `name` ends with
+    /// `value`.
+    pub fn attr_ends_with(self, name: &str, value: &str) -> Self {
+        self.attr_selector(name, Some(("$=", value)))
+    }
+
+    /// Restricts the current compound selector to elements whose attribute `name` contains
+    /// `value`.
+    pub fn attr_contains(self, name: &str, value: &str) -> Self {
+        self.attr_selector(name, Some(("*=", value)))
+    }
+
+    fn attr_selector(mut self, name: &str, value: Option<(&str, &str)>) -> Self {
+        if name.is_empty() {
+            // Attributes always have a name.
+            self.css.push_str(":not(*)");
+            return self;
+        }
+
+        self.css.push('[');
+        serialize_identifier(name, &mut self.css).unwrap();
+        if let Some((operator, value)) = value {
+            self.css.push_str(operator);
+            serialize_string(value, &mut self.css).unwrap();
+        }
+        self.css.push(']');
+        self
+    }
+
+    /// Restricts the current compound selector to elements matching `:nth-child(an+b)`, i.e.
+    /// elements which are the `(a * n + b)`th child of their parent for some `n >= 0`, counting
+    /// from one.
+    pub fn nth_child(mut self, a: i32, b: i32) -> Self {
+        write!(self.css, ":nth-child({}n{:+})", a, b).unwrap();
+        self
+    }
+
+    /// Restricts the current compound selector to elements not matching `selector`.
+    pub fn not(mut self, selector: SelectorBuilder) -> Self {
+        write!(self.css, ":not({})", selector.css).unwrap();
+        self
+    }
+
+    /// Continues with elements which are descendants of those matched so far.
+    pub fn descendant(self) -> CombinatorStep {
+        self.combinator(" ")
+    }
+
+    /// Continues with elements which are children of those matched so far.
+    pub fn child(self) -> CombinatorStep {
+        self.combinator(" > ")
+    }
+
+    /// Continues with elements which immediately follow those matched so far.
+    pub fn adjacent(self) -> CombinatorStep {
+        self.combinator(" + ")
+    }
+
+    /// Continues with elements which are later siblings of those matched so far.
+    pub fn sibling(self) -> CombinatorStep {
+        self.combinator(" ~ ")
+    }
+
+    fn combinator(mut self, combinator: &str) -> CombinatorStep {
+        self.css.push_str(combinator);
+        CombinatorStep { css: self.css }
+    }
+
+    /// Parses the built selector.
+    pub fn build(&self) -> Selector {
+        Selector::parse(&self.css).expect("SelectorBuilder built an invalid selector")
+    }
+}
+
+impl CombinatorStep {
+    /// Continues with a compound selector matching elements named `name`.
+    pub fn tag(self, name: &str) -> SelectorBuilder {
+        SelectorBuilder::from_css(self.css).type_selector(name)
+    }
+
+    /// Continues with a compound selector matching any element.
+    pub fn any(mut self) -> SelectorBuilder {
+        self.css.push('*');
+        SelectorBuilder::from_css(self.css)
+    }
+}
+
+impl fmt::Display for SelectorBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.css)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SelectorBuilder;
+    use crate::Html;
+
+    fn select_ids(html: &str, builder: SelectorBuilder) -> Vec<String> {
+        let html = Html::parse_fragment(html);
+        html.select(&builder.build())
+            .filter_map(|element| element.value().id().map(str::to_owned))
+            .collect()
+    }
+
+    #[test]
+    fn escaping() {
+        let html = r#"
+            <div id="1" class="foo:bar"></div>
+            <div id="2" class="foo"></div>
+            <p id="3" title='say "hi" and &apos;bye&apos;\'></p>
+            <p id="4" title="say hi"></p>
+            <span id="a.b"></span>
+        "#;
+
+        assert_eq!(
+            select_ids(html, SelectorBuilder::tag("div").class("foo:bar")),
+            ["1"]
+        );
+        assert_eq!(
+            select_ids(
+                html,
+                SelectorBuilder::any().attr_eq("title", r#"say "hi" and 'bye'\"#)
+            ),
+            ["3"]
+        );
+        assert_eq!(select_ids(html, SelectorBuilder::any().id("1")), ["1"]);
+        assert_eq!(select_ids(html, SelectorBuilder::any().id("a.b")), ["a.b"]);
+        assert_eq!(
+            select_ids(html, SelectorBuilder::any().attr_includes("title", "hi")),
+            ["4"]
+        );
+    }
+
+    #[test]
+    fn empty_names() {
+        let html = r#"<div id="1" class="" title=""></div>"#;
+
+        assert!(select_ids(html, SelectorBuilder::tag("")).is_empty());
+        assert!(select_ids(html, SelectorBuilder::any().class("")).is_empty());
+        assert!(select_ids(html, SelectorBuilder::any().id("")).is_empty());
+        assert!(select_ids(html, SelectorBuilder::any().attr("")).is_empty());
+        assert_eq!(
+            select_ids(html, SelectorBuilder::tag("div").attr_eq("title", "")),
+            ["1"]
+        );
+    }
+
+    #[test]
+    fn combinators() {
+        let html = r#"
+            <ul id="list">
+                <li id="1"><a id="a1"></a></li>
+                <li id="2" class="x"><span><a id="a2"></a></span></li>
+                <li id="3"></li>
+                <li id="4"></li>
+            </ul>
+        "#;
+
+        assert_eq!(
+            select_ids(html, SelectorBuilder::tag("ul").descendant().tag("a")),
+            ["a1", "a2"]
+        );
+        assert_eq!(
+            select_ids(html, SelectorBuilder::tag("li").child().tag("a")),
+            ["a1"]
+        );
+        assert_eq!(
+            select_ids(html, SelectorBuilder::any().class("x").adjacent().any()),
+            ["3"]
+        );
+        assert_eq!(
+            select_ids(html, SelectorBuilder::any().class("x").sibling().tag("li")),
+            ["3", "4"]
+        );
+    }
+
+    #[test]
+    fn pseudo_classes() {
+        let html = r#"<ul><li id="1"></li><li id="2"></li><li id="3" class="x"></li></ul>"#;
+
+        assert_eq!(
+            select_ids(html, SelectorBuilder::tag("li").nth_child(2, 1)),
+            ["1", "3"]
+        );
+        assert_eq!(
+            select_ids(html, SelectorBuilder::tag("li").nth_child(0, 2)),
+            ["2"]
+        );
+        assert_eq!(
+            select_ids(html, SelectorBuilder::tag("li").nth_child(-1, 2)),
+            ["1", "2"]
+        );
+        assert_eq!(
+            select_ids(
+                html,
+                SelectorBuilder::tag("li").not(SelectorBuilder::any().class("x"))
+            ),
+            ["1", "2"]
+        );
+        assert_eq!(
+            select_ids(
+                html,
+                SelectorBuilder::tag("li").not(SelectorBuilder::any().adjacent().any())
+            ),
+            ["1"]
+        );
+    }
+}
@@ -0,0 +1,396 @@
+//! Step-by-step explanations of matching a selector against an element, for finding out why it
+//! does not match.
+
+use cssparser::ToCss;
+use selectors::matching::{self, CompoundSelectorMatchingResult};
+use selectors::parser::{Combinator, Component};
+use selectors::NthIndexCache;
+
+use super::{matching_context, Selector, Simple};
+use crate::ElementRef;
+
+/// The result of [`Html::explain_match`](crate::Html::explain_match).
+///
+/// Holds one [`SelectorExplanation`] per comma-separated selector of the group.
+///
+/// # Example
+///
+/// ```
+/// use scraper::selector::Relation;
+/// use scraper::{Html, Selector};
+///
+/// let html = Html::parse_fragment(r#"<div class="menu"><span><a href="/">Home</a></span></div>"#);
+/// let a = html.select(&Selector::parse("a").unwrap()).next().unwrap();
+///
+/// let explanation = html.explain_match(&Selector::parse("div.menu > a[href]").unwrap(), a);
+/// assert!(!explanation.matched());
+///
+/// let failure = explanation.selectors()[0].failure().unwrap();
+/// assert_eq!(failure.css(), "div.menu");
+/// assert_eq!(failure.relation(), Some(Relation::Parent));
+/// assert_eq!(failure.element().unwrap().value().name(), "span");
+/// assert_eq!(
+///     failure.failed_components().map(|c| c.css()).collect::<Vec<_>>(),
+///     ["div", ".menu"]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchExplanation<'a> {
+    selectors: Vec<SelectorExplanation<'a>>,
+}
+
+impl<'a> MatchExplanation<'a> {
+    /// Returns true if any selector of the group matched the element.
+    pub fn matched(&self) -> bool {
+        self.selectors.iter().any(SelectorExplanation::matched)
+    }
+
+    /// Returns the explanations for the selectors of the group, in source order.
+    pub fn selectors(&self) -> &[SelectorExplanation<'a>] {
+        &self.selectors
+    }
+}
+
+/// How matching one selector of a comma-separated group went, as part of a [`MatchExplanation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorExplanation<'a> {
+    css: String,
+    matched: bool,
+    compounds: Vec<CompoundExplanation<'a>>,
+}
+
+impl<'a> SelectorExplanation<'a> {
+    /// Returns the CSS text of the selector.
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    /// Returns true if the selector matched the element.
+    pub fn matched(&self) -> bool {
+        self.matched
+    }
+
+    /// Returns the explanations for the compound selectors, in the order they are matched: from
+    /// the rightmost one, matched against the element itself, to the leftmost one.
+    pub fn compounds(&self) -> &[CompoundExplanation<'a>] {
+        &self.compounds
+    }
+
+    /// Returns the first compound selector which did not match, if any.
+    pub fn failure(&self) -> Option<&CompoundExplanation<'a>> {
+        self.compounds.iter().find(|compound| !compound.matched)
+    }
+}
+
+/// How an element matched against a compound selector relates to the element matched against
+/// the compound selector to its right, as given by the combinator between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Relation {
+    /// An ancestor, for the descendant combinator ` `.
+    Ancestor,
+    /// The parent, for the child combinator `>`.
+    Parent,
+    /// The previous sibling element, for the next-sibling combinator `+`.
+    PreviousSibling,
+    /// A previous sibling element, for the subsequent-sibling combinator `~`.
+    EarlierSibling,
+}
+
+/// How matching one compound selector, like `a.nav[href]`, went, as part of a
+/// [`SelectorExplanation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompoundExplanation<'a> {
+    css: String,
+    relation: Option<Relation>,
+    element: Option<ElementRef<'a>>,
+    matched: bool,
+    components: Vec<ComponentExplanation>,
+}
+
+impl<'a> CompoundExplanation<'a> {
+    /// Returns the CSS text of the compound selector.
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    /// Returns how the element matched against this compound selector relates to the one
+    /// matched against the previous compound selector, or `None` for the rightmost compound
+    /// selector, which is matched against the element itself.
+    pub fn relation(&self) -> Option<Relation> {
+        self.relation
+    }
+
+    /// Returns the element this compound selector was matched against.
+    ///
+    /// This is `None` if there was no element in the required relation, e.g. no parent for
+    /// [`Relation::Parent`], or, for [`Relation::Ancestor`] and [`Relation::EarlierSibling`],
+    /// none of the candidates matched. It is also `None` for all compound selectors after the
+    /// first one which did not match, as they were never reached.
+    ///
+    /// Of several candidate ancestors or siblings, this is the closest one from which the rest
+    /// of the selector matches, or failing that, the closest one matching this compound selector.
+    pub fn element(&self) -> Option<ElementRef<'a>> {
+        self.element
+    }
+
+    /// Returns true if the element matched this compound selector.
+    pub fn matched(&self) -> bool {
+        self.matched
+    }
+
+    /// Returns the explanations for the simple selectors making up this compound selector, in
+    /// source order.
+    pub fn components(&self) -> &[ComponentExplanation] {
+        &self.components
+    }
+
+    /// Returns the simple selectors of this compound selector which did not match its element.
+    ///
+    /// If there is no element, this returns all of them.
+    pub fn failed_components(&self) -> impl Iterator<Item = &ComponentExplanation> {
+        self.components
+            .iter()
+            .filter(|component| !component.matched)
+    }
+}
+
+/// Whether one simple selector, like `.nav` or `:first-child`, matched, as part of a
+/// [`CompoundExplanation`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ComponentExplanation {
+    css: String,
+    matched: bool,
+}
+
+impl ComponentExplanation {
+    /// Returns the CSS text of the simple selector.
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    /// Returns true if the element of the compound selector matched this simple selector.
+    pub fn matched(&self) -> bool {
+        self.matched
+    }
+}
+
+impl Selector {
+    /// Retraces matching this selector against `element`, see [`MatchExplanation`].
+    pub(crate) fn explain<'a>(
+        &self,
+        element: ElementRef<'a>,
+        scope: Option<ElementRef<'a>>,
+    ) -> MatchExplanation<'a> {
+        let mut nth_index_cache = NthIndexCache::default();
+        MatchExplanation {
+            selectors: self
+                .selectors
+                .0
+                .iter()
+                .map(|selector| explain_selector(selector, element, scope, &mut nth_index_cache))
+                .collect(),
+        }
+    }
+}
+
+fn explain_selector<'a>(
+    selector: &selectors::parser::Selector<Simple>,
+    element: ElementRef<'a>,
+    scope: Option<ElementRef<'a>>,
+    nth_index_cache: &mut NthIndexCache,
+) -> SelectorExplanation<'a> {
+    let mut context = matching_context(scope, nth_index_cache);
+    let matches_from = |offset: usize, element: &ElementRef, context: &mut _| {
+        matching::matches_selector(selector, offset, None, element, context)
+    };
+    // Compound selectors are stored right to left, each followed by the combinator to its left.
+    let len = selector.len();
+    let matches_compound = |end: usize, element: &ElementRef, context: &mut _| {
+        !matches!(
+            matching::matches_compound_selector_from(selector, len - end, context, element),
+            CompoundSelectorMatchingResult::NotMatched
+        )
+    };
+
+    let components = selector.iter_raw_match_order().as_slice();
+    let mut compounds = Vec::new();
+    let mut current = Some(element);
+    let mut relation = None;
+    let mut start = 0;
+    loop {
+        let end = components[start..]
+            .iter()
+            .position(|component| matches!(component, Component::Combinator(_)))
+            .map_or(len, |position| start + position);
+
+        let mut candidate = current.filter(|_| compounds.iter().all(CompoundExplanation::matched));
+        if let (Some(element), Some(relation)) = (candidate, relation) {
+            candidate = match relation {
+                Relation::Parent => element.parent().and_then(ElementRef::wrap),
+                Relation::PreviousSibling => element.prev_siblings().find_map(ElementRef::wrap),
+                Relation::Ancestor | Relation::EarlierSibling => {
+                    let candidates = if relation == Relation::Ancestor {
+                        element.ancestors().filter_map(ElementRef::wrap).collect()
+                    } else {
+                        element
+                            .prev_siblings()
+                            .filter_map(ElementRef::wrap)
+                            .collect::<Vec<_>>()
+                    };
+                    candidates
+                        .iter()
+                        .find(|candidate| matches_from(start, candidate, &mut context))
+                        .or_else(|| {
+                            candidates
+                                .iter()
+                                .find(|candidate| matches_compound(end, candidate, &mut context))
+                        })
+                        .copied()
+                }
+            };
+        }
+
+        let matched =
+            candidate.is_some_and(|element| matches_compound(end, &element, &mut context));
+        compounds.push(CompoundExplanation {
+            css: components[start..end]
+                .iter()
+                .map(|component| component.to_css_string())
+                .collect(),
+            relation,
+            element: candidate,
+            matched,
+            components: components[start..end]
+                .iter()
+                .map(|component| component.to_css_string())
+                .filter(|css| !css.is_empty())
+                .map(|css| ComponentExplanation {
+                    matched: candidate.is_some_and(|element| {
+                        Selector::parse(&css)
+                            .is_ok_and(|simple| simple.matches_with_scope(&element, scope))
+                    }),
+                    css,
+                })
+                .collect(),
+        });
+        current = candidate;
+
+        let Some(Component::Combinator(combinator)) = components.get(end) else {
+            break;
+        };
+        relation = match combinator {
+            Combinator::Descendant => Some(Relation::Ancestor),
+            Combinator::Child => Some(Relation::Parent),
+            Combinator::NextSibling => Some(Relation::PreviousSibling),
+            Combinator::LaterSibling => Some(Relation::EarlierSibling),
+            _ => None,
+        };
+        if relation.is_none() {
+            current = None;
+        }
+        start = end + 1;
+    }
+
+    SelectorExplanation {
+        css: selector.to_css_string(),
+        matched: matches_from(0, &element, &mut context),
+        compounds,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Relation;
+    use crate::{Html, Selector};
+
+    #[test]
+    fn matching_chain() {
+        let html = Html::parse_fragment(
+            r#"<ul id=u><li id=one class=a>1</li><li id=two class="b c">2</li></ul>"#,
+        );
+        let li = html
+            .select(&Selector::parse("#two").unwrap())
+            .next()
+            .unwrap();
+        let explanation = html.explain_match(&Selector::parse("ul li.a + li.b.c").unwrap(), li);
+        assert!(explanation.matched());
+
+        let selector = &explanation.selectors()[0];
+        assert_eq!(selector.css(), "ul li.a + li.b.c");
+        assert!(selector.failure().is_none());
+        let steps = selector
+            .compounds()
+            .iter()
+            .map(|compound| {
+                (
+                    compound.css(),
+                    compound.relation(),
+                    compound.element().and_then(|element| element.value().id()),
+                    compound.matched(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [
+                ("li.b.c", None, Some("two"), true),
+                ("li.a", Some(Relation::PreviousSibling), Some("one"), true),
+                ("ul", Some(Relation::Ancestor), Some("u"), true),
+            ]
+        );
+        let components = selector.compounds()[0]
+            .components()
+            .iter()
+            .map(|component| (component.css(), component.matched()))
+            .collect::<Vec<_>>();
+        assert_eq!(components, [("li", true), (".b", true), (".c", true)]);
+    }
+
+    #[test]
+    fn failures() {
+        let html = Html::parse_fragment(
+            "<div class=a><div id=middle><div id=inner><p class=x>p</p></div></div></div>",
+        );
+        let p = html.select(&Selector::parse("p").unwrap()).next().unwrap();
+
+        // The ancestor from which the rest of the selector matches is chosen.
+        let explanation = html.explain_match(&Selector::parse("div.a > div p").unwrap(), p);
+        assert!(explanation.matched());
+        let middle = explanation.selectors()[0].compounds()[1].element().unwrap();
+        assert_eq!(middle.value().id(), Some("middle"));
+
+        // No ancestor matches, so the compound selectors after it are never reached.
+        let explanation =
+            html.explain_match(&Selector::parse("main section > div p.x").unwrap(), p);
+        let selector = &explanation.selectors()[0];
+        assert!(!selector.matched());
+        let compounds = selector.compounds();
+        assert!(compounds[0].matched());
+        assert!(compounds[1].matched());
+        let failure = selector.failure().unwrap();
+        assert_eq!(failure.css(), "section");
+        assert_eq!(failure.relation(), Some(Relation::Parent));
+        assert_eq!(failure.element().unwrap().value().id(), Some("middle"));
+        assert_eq!(failure.failed_components().count(), 1);
+        assert_eq!(compounds[3].css(), "main");
+        assert_eq!(compounds[3].element(), None);
+        assert_eq!(compounds[3].failed_components().count(), 1);
+
+        // The first selector of the group to fail is explained alongside the one matching.
+        let explanation =
+            html.explain_match(&Selector::parse("p:first-child:not(.x), .x").unwrap(), p);
+        assert!(explanation.matched());
+        let [first, second] = explanation.selectors() else {
+            panic!("expected two selectors");
+        };
+        assert!(!first.matched());
+        assert!(second.matched());
+        assert_eq!(
+            first.compounds()[0]
+                .failed_components()
+                .map(|component| component.css())
+                .collect::<Vec<_>>(),
+            [":not(.x)"]
+        );
+    }
+}
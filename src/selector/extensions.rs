@@ -0,0 +1,103 @@
+//! User-defined pseudo-classes.
+
+use std::fmt;
+use std::sync::Arc;
+
+use ahash::AHashMap as HashMap;
+
+use crate::ElementRef;
+
+/// The matching function of a custom pseudo-class, receiving the argument of functional
+/// pseudo-classes.
+pub(crate) type Callback = Arc<dyn Fn(&ElementRef<'_>, Option<&str>) -> bool + Send + Sync>;
+
+/// A pseudo-class registered with [`Extensions`].
+#[derive(Clone)]
+pub(crate) struct Registered {
+    /// Whether the pseudo-class takes an argument, i.e. is written as `:name(argument)`.
+    pub(crate) functional: bool,
+    pub(crate) callback: Callback,
+}
+
+/// Custom pseudo-classes available to [`Selector::parse_with_extensions`].
+///
+/// Pseudo-class names are ASCII case-insensitive, as in CSS. Registering a name a second time
+/// replaces the previous callback. The built-in pseudo-classes, such as `:link`, `:lang()` and
+/// `:contains()`, take precedence over registered ones of the same name.
+///
+/// # Example
+///
+/// ```
+/// use scraper::{Html, Selector};
+/// use scraper::selector::Extensions;
+///
+/// let mut extensions = Extensions::new();
+/// extensions.add_pseudo_class("external", |element| {
+///     element.attr("href").is_some_and(|href| href.starts_with("http"))
+/// });
+///
+/// let fragment = Html::parse_fragment(r#"<a href="/home"></a><a href="https://example.com"></a>"#);
+/// let selector = Selector::parse_with_extensions("a:external", &extensions).unwrap();
+///
+/// let hrefs = fragment.select(&selector).map(|a| a.attr("href").unwrap()).collect::<Vec<_>>();
+/// assert_eq!(hrefs, ["https://example.com"]);
+/// ```
+///
+/// [`Selector::parse_with_extensions`]: super::Selector::parse_with_extensions
+#[derive(Clone, Default)]
+pub struct Extensions {
+    pseudo_classes: HashMap<String, Registered>,
+}
+
+impl Extensions {
+    /// Creates an empty set of extensions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the pseudo-class `:name`, matching the elements for which `callback` returns
+    /// true.
+    pub fn add_pseudo_class<F>(&mut self, name: &str, callback: F) -> &mut Self
+    where
+        F: Fn(&ElementRef<'_>) -> bool + Send + Sync + 'static,
+    {
+        self.register(name, false, Arc::new(move |element, _| callback(element)))
+    }
+
+    /// Registers the functional pseudo-class `:name(argument)`, matching the elements for which
+    /// `callback` returns true.
+    ///
+    /// The argument is passed to `callback` as written in the selector, with surrounding
+    /// whitespace removed.
+    pub fn add_functional_pseudo_class<F>(&mut self, name: &str, callback: F) -> &mut Self
+    where
+        F: Fn(&ElementRef<'_>, &str) -> bool + Send + Sync + 'static,
+    {
+        self.register(
+            name,
+            true,
+            Arc::new(move |element, argument| callback(element, argument.unwrap_or_default())),
+        )
+    }
+
+    fn register(&mut self, name: &str, functional: bool, callback: Callback) -> &mut Self {
+        self.pseudo_classes.insert(
+            name.to_ascii_lowercase(),
+            Registered {
+                functional,
+                callback,
+            },
+        );
+        self
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Registered> {
+        self.pseudo_classes.get(&*name.to_ascii_lowercase())
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.pseudo_classes.keys()).finish()
+    }
+}
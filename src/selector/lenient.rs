@@ -0,0 +1,224 @@
+//! Best-effort parsing of invalid selectors, dropping the parts which fail to parse.
+
+use std::ops::Range;
+
+use cssparser::{Delimiter, ParseError, Parser, ParserInput, Token};
+use selectors::parser::SelectorList;
+
+use super::parser::ParserConfig;
+use super::{Selector, Simple};
+use crate::error::{SelectorParseError, SelectorParseWarning};
+
+/// What a unit returned by `units` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    /// A combinator, including the whitespace around it. `explicit` is false for the
+    /// descendant combinator, which is only whitespace.
+    Combinator { explicit: bool },
+    /// A simple selector like `div`, `.a`, `[href]` or `:not(p)`. `open` is true while it is
+    /// incomplete, e.g. after the `.` of a class selector.
+    Simple { open: bool },
+}
+
+impl Selector {
+    /// Parses `selectors` like [`Selector::parse_lenient`].
+    pub(crate) fn lenient(selectors: &str) -> (Option<Self>, Vec<SelectorParseWarning>) {
+        let mut survivors = Vec::new();
+        let mut warnings = Vec::new();
+
+        for item in list_items(selectors) {
+            let mut kept = units(&selectors[item.clone()])
+                .into_iter()
+                .map(|unit| unit.start + item.start..unit.end + item.start)
+                .collect::<Vec<_>>();
+            if kept.is_empty() {
+                warnings.extend(parse_single("").err().map(|error| {
+                    let location = item.start;
+                    SelectorParseWarning::new(
+                        trim(selectors, item.clone()),
+                        error.relocated(selectors, location),
+                    )
+                }));
+                continue;
+            }
+
+            while !kept.is_empty() {
+                let candidate = kept
+                    .iter()
+                    .map(|unit| &selectors[unit.clone()])
+                    .collect::<String>();
+                let error = match parse_single(&candidate) {
+                    Ok(selector) => {
+                        survivors.push(selector);
+                        break;
+                    }
+                    Err(error) => error,
+                };
+
+                // Drop the unit at which parsing failed, or the last one if it failed at the end.
+                let mut offset = error.offset();
+                let failed = kept.iter().position(|unit| {
+                    if offset < unit.len() {
+                        return true;
+                    }
+                    offset -= unit.len();
+                    false
+                });
+                let (unit, location) = match failed {
+                    Some(index) => {
+                        let unit = kept.remove(index);
+                        let location = unit.start + offset;
+                        (unit, location)
+                    }
+                    None => {
+                        let unit = kept.pop().unwrap();
+                        let location = unit.end;
+                        (unit, location)
+                    }
+                };
+                warnings.push(SelectorParseWarning::new(
+                    trim(selectors, unit),
+                    error.relocated(selectors, location),
+                ));
+            }
+        }
+
+        let selector = (!survivors.is_empty()).then(|| Self {
+            selectors: SelectorList(survivors.into_iter().collect()),
+        });
+        (selector, warnings)
+    }
+}
+
+/// Returns the byte ranges of the comma-separated items of a selector list, without surrounding
+/// whitespace.
+fn list_items(selectors: &str) -> Vec<Range<usize>> {
+    let mut parser_input = ParserInput::new(selectors);
+    let mut parser = Parser::new(&mut parser_input);
+
+    let mut items = Vec::new();
+    loop {
+        let start = parser.position().byte_index();
+        let _ = parser.parse_until_before(Delimiter::Comma, |parser| {
+            while parser.next().is_ok() {}
+            Ok::<_, ParseError<()>>(())
+        });
+        items.push(trim(selectors, start..parser.position().byte_index()));
+
+        if parser.next().is_err() {
+            return items;
+        }
+    }
+}
+
+/// Splits a complex selector into the byte ranges of its simple selectors and combinators, which
+/// together cover all of it.
+fn units(selector: &str) -> Vec<Range<usize>> {
+    let mut parser_input = ParserInput::new(selector);
+    let mut parser = Parser::new(&mut parser_input);
+
+    let mut starts = Vec::<(usize, Unit)>::new();
+    loop {
+        let start = parser.position().byte_index();
+        let Ok(token) = parser.next_including_whitespace() else {
+            break;
+        };
+        let token = token.clone();
+        if matches!(
+            token,
+            Token::Function(_)
+                | Token::ParenthesisBlock
+                | Token::SquareBracketBlock
+                | Token::CurlyBracketBlock
+        ) {
+            // Skip the contents of the block, so the next unit starts after it.
+            let _ = parser.parse_nested_block(|parser| {
+                while parser.next().is_ok() {}
+                Ok::<_, ParseError<()>>(())
+            });
+        }
+
+        let last = starts.last_mut().map(|(_, unit)| unit);
+        let unit = match (&token, last) {
+            (Token::WhiteSpace(_), Some(Unit::Combinator { .. })) => continue,
+            (Token::WhiteSpace(_), _) => Unit::Combinator { explicit: false },
+            (Token::Delim('>' | '+' | '~'), Some(Unit::Combinator { explicit })) if !*explicit => {
+                *explicit = true;
+                continue;
+            }
+            (Token::Delim('>' | '+' | '~'), _) => Unit::Combinator { explicit: true },
+            (token, Some(Unit::Simple { open })) if *open || *token == Token::Delim('|') => {
+                *open = matches!(token, Token::Colon | Token::Delim('|'));
+                continue;
+            }
+            (token, _) => Unit::Simple {
+                open: matches!(token, Token::Colon | Token::Delim('.' | '|')),
+            },
+        };
+        starts.push((start, unit));
+    }
+
+    let ends = starts
+        .iter()
+        .skip(1)
+        .map(|(start, _)| *start)
+        .chain([selector.len()]);
+    starts
+        .iter()
+        .zip(ends)
+        .map(|((start, _), end)| *start..end)
+        .collect()
+}
+
+/// Parses a single complex selector.
+fn parse_single(selector: &str) -> Result<selectors::parser::Selector<Simple>, SelectorParseError> {
+    let mut parser_input = ParserInput::new(selector);
+    let mut parser = Parser::new(&mut parser_input);
+    let config = ParserConfig::default();
+
+    parser
+        .parse_entirely(|parser| selectors::parser::Selector::parse(&config, parser))
+        .map_err(|err| SelectorParseError::new(selector, err))
+}
+
+/// Returns `range` without the whitespace at either end.
+fn trim(text: &str, range: Range<usize>) -> Range<usize> {
+    let unit = &text[range.clone()];
+    let start = range.start + (unit.len() - unit.trim_start().len());
+    start..start.max(range.start + unit.trim_end().len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::units;
+
+    #[test]
+    fn split_units() {
+        let selector =
+            r#"div.a#b[c="d, e"] > svg|rect:not(.x , .y)::before  ~  *|* + .p:nth-child(2n)"#;
+        let split = units(selector)
+            .into_iter()
+            .map(|unit| &selector[unit])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            split,
+            [
+                "div",
+                ".a",
+                "#b",
+                r#"[c="d, e"]"#,
+                " > ",
+                "svg|rect",
+                ":not(.x , .y)",
+                "::before",
+                "  ~  ",
+                "*|*",
+                " + ",
+                ".p",
+                ":nth-child(2n)",
+            ]
+        );
+        assert_eq!(units("a >> b").len(), 4);
+        assert!(units("").is_empty());
+    }
+}
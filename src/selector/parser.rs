@@ -0,0 +1,9 @@
+//! Parser configuration for `selectors`.
+//!
+//! The parser is defined by the `scraper-selector-parser` crate, which shares it with the
+//! `scraper-macros` crate so that `selector!` accepts exactly the selectors `Selector::parse`
+//! does.
+
+use super::extensions::{Callback, Extensions};
+
+scraper_selector_parser::define_parser!(callback = Callback, extensions = Extensions);
@@ -0,0 +1,153 @@
+//! Statistics about matching a selector, for finding out why it is slow or matches nothing.
+
+use std::time::{Duration, Instant};
+
+use cssparser::ToCss;
+use selectors::{matching, NthIndexCache};
+
+use super::{matching_context, Selector};
+use crate::ElementRef;
+
+/// Statistics collected by [`Html::select_profiled`](crate::Html::select_profiled).
+///
+/// Statistics are kept for each selector of the comma-separated group as a whole, not for its
+/// compound selectors: `selectors` matches a complex selector like `ul > li.last` in a single
+/// call, backtracking between its compounds, so they cannot be counted or timed separately
+/// without matching differently from [`Html::select`](crate::Html::select).
+///
+/// # Example
+///
+/// ```
+/// use scraper::{Html, Selector};
+///
+/// let html = Html::parse_fragment("<ul><li>a</li><li class=last>b</li></ul>");
+/// let selector = Selector::parse("li.last, ul > li:last-child").unwrap();
+///
+/// let (elements, profile) = html.select_profiled(&selector);
+/// assert_eq!(elements.len(), 1);
+/// assert_eq!(profile.elements_visited(), 4);
+/// assert_eq!(profile.matches(), 1);
+///
+/// let [first, second] = profile.selectors() else { panic!() };
+/// assert_eq!((first.css(), first.attempts(), first.matches()), ("li.last", 4, 1));
+/// assert_eq!((second.css(), second.attempts(), second.matches()), ("ul > li:last-child", 3, 0));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchProfile {
+    elements_visited: usize,
+    matches: usize,
+    total_time: Duration,
+    selectors: Vec<SelectorProfile>,
+}
+
+impl MatchProfile {
+    /// Returns the number of elements which were matched against the selector.
+    pub fn elements_visited(&self) -> usize {
+        self.elements_visited
+    }
+
+    /// Returns the number of elements matching the selector.
+    pub fn matches(&self) -> usize {
+        self.matches
+    }
+
+    /// Returns the time taken to find the matching elements.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Returns the statistics of each selector in the comma-separated group, in order.
+    pub fn selectors(&self) -> &[SelectorProfile] {
+        &self.selectors
+    }
+
+    /// Returns the statistics of the selector which took the most time to match.
+    pub fn slowest(&self) -> Option<&SelectorProfile> {
+        self.selectors.iter().max_by_key(|selector| selector.time)
+    }
+}
+
+/// Statistics about one selector of a comma-separated group, as part of a [`MatchProfile`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectorProfile {
+    css: String,
+    attempts: usize,
+    matches: usize,
+    time: Duration,
+}
+
+impl SelectorProfile {
+    /// Returns the CSS text of the selector.
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    /// Returns the number of elements which the selector was matched against.
+    ///
+    /// An element is not matched against the selector if an earlier selector of the group
+    /// already matched it.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Returns the number of elements which the selector matched.
+    pub fn matches(&self) -> usize {
+        self.matches
+    }
+
+    /// Returns the time taken to match the selector against all the elements it was attempted
+    /// on.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+}
+
+impl Selector {
+    /// Creates an empty profile for matching this selector.
+    pub(crate) fn profile(&self) -> MatchProfile {
+        MatchProfile {
+            selectors: self
+                .selectors
+                .0
+                .iter()
+                .map(|selector| SelectorProfile {
+                    css: selector.to_css_string(),
+                    ..SelectorProfile::default()
+                })
+                .collect(),
+            ..MatchProfile::default()
+        }
+    }
+
+    /// Like `matches_with_scope_and_cache`, but timing each selector of the group and recording
+    /// the result in `profile`.
+    pub(crate) fn matches_profiled(
+        &self,
+        element: &ElementRef,
+        scope: Option<ElementRef>,
+        nth_index_cache: &mut NthIndexCache,
+        profile: &mut MatchProfile,
+    ) -> bool {
+        let start = Instant::now();
+        let mut context = matching_context(scope, nth_index_cache);
+        let matched =
+            self.selectors
+                .0
+                .iter()
+                .zip(&mut profile.selectors)
+                .any(|(selector, stats)| {
+                    let start = Instant::now();
+                    let matched =
+                        matching::matches_selector(selector, 0, None, element, &mut context);
+                    stats.time += start.elapsed();
+                    stats.attempts += 1;
+                    stats.matches += usize::from(matched);
+                    matched
+                });
+
+        profile.elements_visited += 1;
+        profile.matches += usize::from(matched);
+        profile.total_time += start.elapsed();
+        matched
+    }
+}
@@ -0,0 +1,122 @@
+//! A process-wide cache of parsed selectors.
+//!
+//! [`get`] parses a selector the first time it is asked for and returns the same `Selector` on
+//! every later call, from any thread. This suits selectors written as literals in code which
+//! runs many times, without having to store them anywhere.
+//!
+//! Cached selectors are leaked on purpose, so that they can be handed out as
+//! `&'static Selector`: they are never freed, just like the `'static` strings they are parsed
+//! from. Only use the cache for a bounded set of selectors, not for ones built at runtime.
+//!
+//! The cache is guarded by a read-write lock. Lookups of cached selectors only take the read
+//! lock, so they proceed in parallel; parsing a new selector takes the write lock, which also
+//! ensures each selector is parsed only once.
+//!
+//! # Example
+//!
+//! ```
+//! use scraper::{selector_cache, Html};
+//!
+//! let html = Html::parse_fragment("<ul><li>a</li><li>b</li></ul>");
+//! assert_eq!(html.select(selector_cache::get("ul > li")).count(), 2);
+//! assert!(std::ptr::eq(selector_cache::get("ul > li"), selector_cache::get("ul > li")));
+//! ```
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use crate::error::SelectorParseError;
+use crate::Selector;
+
+static CACHE: Lazy<RwLock<HashMap<&'static str, &'static Selector>>> = Lazy::new(Default::default);
+
+#[cfg(test)]
+static PARSED: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Returns the cached selector for `css`, parsing it if this is the first request for it.
+///
+/// # Panics
+///
+/// Panics if `css` is not a valid selector, with a message showing where parsing failed.
+pub fn get(css: &'static str) -> &'static Selector {
+    match try_get(css) {
+        Ok(selector) => selector,
+        Err(err) => panic!("invalid selector {:?}: {}", css, err),
+    }
+}
+
+/// Returns the cached selector for `css`, parsing it if this is the first request for it, or
+/// the parse error if `css` is not a valid selector.
+///
+/// Invalid selectors are not cached, so every call for one parses it again.
+pub fn try_get(css: &'static str) -> Result<&'static Selector, SelectorParseError> {
+    // A poisoned lock only means another thread panicked while holding it. Since the map is
+    // never left half-updated, it can still be used.
+    if let Some(selector) = CACHE.read().unwrap_or_else(|err| err.into_inner()).get(css) {
+        return Ok(selector);
+    }
+
+    let mut cache = CACHE.write().unwrap_or_else(|err| err.into_inner());
+    // Another thread may have parsed the selector while this one waited for the lock.
+    if let Some(selector) = cache.get(css) {
+        return Ok(selector);
+    }
+
+    let selector: &'static Selector = Box::leak(Box::new(Selector::parse(css)?));
+    cache.insert(css, selector);
+    #[cfg(test)]
+    PARSED.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    Ok(selector)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::{get, try_get, PARSED};
+    use crate::Selector;
+
+    #[test]
+    fn parses_once_across_threads() {
+        const CSS: &str = "section.cache-test > p:nth-child(2n)";
+        let barrier = Barrier::new(8);
+
+        let selectors = thread::scope(|scope| {
+            let handles = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        (0..100)
+                            .map(|_| std::ptr::from_ref(get(CSS)) as usize)
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        assert!(selectors.iter().all(|&selector| selector == selectors[0]));
+        assert_eq!(PARSED.load(Ordering::Relaxed), 1);
+        assert_eq!(*get(CSS), Selector::parse(CSS).unwrap());
+    }
+
+    #[test]
+    fn invalid() {
+        let err = try_get("p >").unwrap_err();
+        assert_eq!(err.input(), "p >");
+        assert!(try_get("p >").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid selector \"p:bogus\"")]
+    fn get_invalid() {
+        get("p:bogus");
+    }
+}
@@ -20,3 +20,19 @@ fn tag_with_newline() {
         Some("https://github.com/causal-agent/scraper")
     );
 }
+
+#[test]
+fn static_selector_is_cached() {
+    fn selector() -> &'static Selector {
+        crate::static_selector!("div > p.note")
+    }
+
+    assert!(std::ptr::eq(selector(), selector()));
+    assert_eq!(*selector(), Selector::parse("div > p.note").unwrap());
+}
+
+#[test]
+#[should_panic(expected = "invalid selector \"div >\"")]
+fn static_selector_invalid() {
+    let _ = crate::static_selector!("div >");
+}